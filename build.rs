@@ -1,15 +1,56 @@
 #![allow(missing_docs)]
+#[cfg(feature = "cc")]
+#[path = "build/ffi_prototypes.rs"]
+mod ffi_prototypes;
+#[cfg(feature = "cc")]
+#[path = "build/abi_layout.rs"]
+mod abi_layout;
 /// 添加文档说明
 fn main() {
     #[cfg(feature = "cc")]
     {
         use ::cc::Build;
-        use ::std::path::Path;
+        use ::std::{env, path::{Path, PathBuf}};
         let project_root = Path::new(env!("CARGO_MANIFEST_DIR"));
         let native_src = project_root.join("native");
         Build::new()
             .file(native_src.join("closure_callback.c"))
             .compile("closure_callback");
         println!("cargo:rustc-link-lib=closure_callback");
+        // 交叉检查：从 Rust 端的 extern 声明生成 C 函数原型，再与 C 端的头文件一起编译。签名不一致即编译失败
+        let rust_sources = [
+            "src/bin/ffi-closure-callback.rs",
+            "src/bin/ffi-closure-callback-by-zst.rs",
+            "src/bin/ffi-nullable-pointer-optimization.rs"
+        ];
+        let prototypes = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("closure_callback_prototypes.c");
+        ffi_prototypes::generate(project_root, &rust_sources, "closure_callback.h", &prototypes)
+            .unwrap_or_else(|err| panic!("生成 C 函数原型失败：{}", err));
+        Build::new()
+            .include(&native_src)
+            .file(&prototypes)
+            .warnings_into_errors(true)
+            .cargo_metadata(false)
+            .compile("closure_callback_prototypes");
+        // 交叉检查：从 Rust 端的 abi_layout! 声明生成 offsetof/sizeof 探针。布局不一致即编译失败。
+        // 运行时探针也被链接进来，以供 Rust 端比对它自己计算出的布局
+        let layout_sources = [
+            "src/bin/ffi-nullable-pointer-optimization.rs",
+            "src/bin/memory-layout_enum.rs"
+        ];
+        let probes = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("abi_layout_probes.c");
+        abi_layout::generate(project_root, &layout_sources, &["closure_callback.h", "memory_layout.h"], &probes)
+            .unwrap_or_else(|err| panic!("生成 C 端布局探针失败：{}", err));
+        Build::new()
+            .include(&native_src)
+            .file(&probes)
+            .warnings_into_errors(true)
+            .compile("abi_layout_probes");
+        println!("cargo:rerun-if-changed=build.rs");
+        println!("cargo:rerun-if-changed=build");
+        println!("cargo:rerun-if-changed=native");
+        for rust_source in rust_sources.iter().chain(&layout_sources) {
+            println!("cargo:rerun-if-changed={}", rust_source);
+        }
     }
 }
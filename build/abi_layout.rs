@@ -0,0 +1,150 @@
+//! 从 Rust 端的`abi_layout!`宏调用生成 C 端的布局探针。
+//!
+//! 对每个被声明的结构体，生成一个由其字段还原的 C 镜像结构体，再以`_Static_assert`逐一比对镜像结构体与头文件里
+//! 同名类型的`sizeof`、`_Alignof`，与每个字段的`offsetof`、`sizeof`。任何不一致都令编译失败，且`#line`指令令
+//! 编译错误指向 Rust 源码里的声明位置。被声明的枚举则没有编译期比对，因为 build.rs 既无从得知 Rust 端的布局，
+//! 也无从将 Rust 端的变体对应到 C 端的枚举常量。它们仅由运行时的`TypeLayout::verify()`比对`sizeof`与`_Alignof`。
+//!
+//! 另外生成供 Rust 端在运行时查询 C 端布局的探针函数（见`src/abi_layout.rs`）：
+//! ```c
+//! size_t abi_layout_sizeof(const char* type);                     // 未知的类型返回 0
+//! size_t abi_layout_alignof(const char* type);                    // 未知的类型返回 0
+//! size_t abi_layout_offsetof(const char* type, const char* field); // 未知的字段返回 (size_t)-1
+//! ```
+use ::std::{fmt::Write, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::Path};
+use crate::ffi_prototypes::{self, Aliases, CType, Lexer, Token};
+/// 被还原的字段
+struct Field {
+    name: String,
+    ty: CType,
+    line: usize
+}
+/// 被还原的结构体或枚举
+struct Item {
+    name: String,
+    /// 结构体的字段。枚举则是`None`
+    fields: Option<Vec<Field>>,
+    line: usize
+}
+/// 跳过属性与可见性修饰符
+fn skip_attrs_and_vis(mut tokens: &[Token]) -> &[Token] {
+    loop {
+        tokens = match tokens {
+            [Token::Punct("#"), Token::Group('[', _), rest @ ..] => rest,
+            [vis, Token::Group('(', _), rest @ ..] if vis.is_ident("pub") => rest,
+            [vis, rest @ ..] if vis.is_ident("pub") => rest,
+            tokens => return tokens
+        };
+    }
+}
+/// 解析一次 abi_layout! 宏调用
+fn layout_items(tokens: &[Token], aliases: &Aliases) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut tokens = tokens;
+    while !tokens.is_empty() {
+        tokens = match skip_attrs_and_vis(tokens) {
+            [keyword, Token::Ident(name, line), Token::Group('{', body), rest @ ..] if keyword.is_ident("struct") => {
+                let fields = ffi_prototypes::split_commas(body).into_iter()
+                    .map(skip_attrs_and_vis)
+                    .filter(|field| !field.is_empty())
+                    .map(|field| match field {
+                        [Token::Ident(name, line), Token::Punct(":"), ty @ ..] => Ok(Field {
+                            name: name.clone(),
+                            ty: ffi_prototypes::rust_to_c(ty, aliases)?,
+                            line: *line
+                        }),
+                        field => Err(ffi_prototypes::describe(field))
+                    })
+                    .collect::<Result<_, _>>()?;
+                items.push(Item { name: name.clone(), fields: Some(fields), line: *line });
+                rest
+            },
+            [keyword, Token::Ident(name, line), Token::Group('{', _), rest @ ..] if keyword.is_ident("enum") => {
+                items.push(Item { name: name.clone(), fields: None, line: *line });
+                rest
+            },
+            tokens => return Err(ffi_prototypes::describe(tokens))
+        };
+    }
+    Ok(items)
+}
+/// 递归地收集 abi_layout! 宏调用。跳过 macro_rules! 的定义体与任何含有宏变量的宏调用
+fn collect_items(tokens: &[Token], aliases: &Aliases, items: &mut Vec<Item>) -> Result<(), String> {
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i..] {
+            [macro_rules, Token::Punct("!"), Token::Ident(_, _), Token::Group(..), ..] if macro_rules.is_ident("macro_rules") => i += 3,
+            [layout, Token::Punct("!"), Token::Group(_, body), ..] if layout.is_ident("abi_layout") => {
+                if !ffi_prototypes::contains_macro_var(body) {
+                    items.extend(layout_items(body, aliases)?);
+                }
+                i += 2;
+            },
+            [Token::Group(_, tokens), ..] => collect_items(tokens, aliases, items)?,
+            _ => ()
+        }
+        i += 1;
+    }
+    Ok(())
+}
+/// 从`sources`里的 Rust 文件生成 C 端的布局探针，并写入`output`。生成的 C 文件会`#include`全部`headers`。
+pub(crate) fn generate(root: &Path, sources: &[&str], headers: &[&str], output: &Path) -> IoResult<()> {
+    let mut items = Vec::new();
+    for source in sources {
+        let tokens = Lexer::new(&fs::read_to_string(root.join(source))?, source).tokens(None).map_err(|err| IoError::new(ErrorKind::Other, err))?;
+        let mut aliases = Aliases::new();
+        ffi_prototypes::collect_aliases(&tokens, &mut aliases);
+        let mut source_items = Vec::new();
+        collect_items(&tokens, &aliases, &mut source_items).map_err(|err| {
+            IoError::new(ErrorKind::Other, format!("{} 里有无法被还原为 C 类型的 abi_layout! 声明：{}", source, err))
+        })?;
+        items.extend(source_items.into_iter().map(|item| (source.replace('\\', "/"), item)));
+    }
+    let mut c_source = String::new();
+    writeln!(c_source, "/* 由 build.rs 从 Rust 端的 abi_layout! 声明生成。若 Rust 端与 C 端的布局不一致，则编译失败 */").unwrap();
+    for header in headers {
+        writeln!(c_source, "#include \"{}\"", header).unwrap();
+    }
+    writeln!(c_source, "#include <stddef.h>\n#include <string.h>").unwrap();
+    // 运行时探针
+    writeln!(c_source, "size_t abi_layout_sizeof(const char* type)\n{{").unwrap();
+    for (_, item) in &items {
+        writeln!(c_source, "    if (strcmp(type, \"{0}\") == 0) return sizeof({0});", item.name).unwrap();
+    }
+    writeln!(c_source, "    return 0;\n}}\nsize_t abi_layout_alignof(const char* type)\n{{").unwrap();
+    for (_, item) in &items {
+        writeln!(c_source, "    if (strcmp(type, \"{0}\") == 0) return _Alignof({0});", item.name).unwrap();
+    }
+    writeln!(c_source, "    return 0;\n}}\nsize_t abi_layout_offsetof(const char* type, const char* field)\n{{").unwrap();
+    for (_, item) in &items {
+        for field in item.fields.iter().flatten() {
+            writeln!(c_source, "    if (strcmp(type, \"{0}\") == 0 && strcmp(field, \"{1}\") == 0) return offsetof({0}, {1});", item.name, field.name).unwrap();
+        }
+    }
+    writeln!(c_source, "    return (size_t)-1;\n}}").unwrap();
+    // 编译期比对：结构体的镜像类型与头文件里的同名类型
+    for (source, item) in &items {
+        let Some(fields) = &item.fields else {
+            continue;
+        };
+        let mirror = format!("abi_layout_{}", item.name);
+        writeln!(c_source, "#line {} \"{}\"", item.line, source).unwrap();
+        let members = fields.iter().map(|field| format!("{};", field.ty.declare(&field.name))).collect::<Vec<_>>().join(" ");
+        writeln!(c_source, "struct {} {{ {} }};", mirror, members).unwrap();
+        writeln!(c_source, "typedef struct {0} {0};", mirror).unwrap();
+        // 断言文本只用 ASCII 字符，因为编译器会转义诊断信息里的非 ASCII 字符。同一声明的断言被写在同一行，以免偏离 #line 行号
+        writeln!(c_source, "#line {} \"{}\"", item.line, source).unwrap();
+        writeln!(c_source, concat!(
+            "_Static_assert(sizeof({0}) == sizeof({1}), \"Rust/C layout mismatch: sizeof({1})\"); ",
+            "_Static_assert(_Alignof({0}) == _Alignof({1}), \"Rust/C layout mismatch: _Alignof({1})\");"
+        ), mirror, item.name).unwrap();
+        for field in fields {
+            writeln!(c_source, "#line {} \"{}\"", field.line, source).unwrap();
+            writeln!(c_source, concat!(
+                "_Static_assert(offsetof({0}, {2}) == offsetof({1}, {2}), \"Rust/C layout mismatch: offsetof({1}, {2})\"); ",
+                "_Static_assert(sizeof((({0}*)0)->{2}) == sizeof((({1}*)0)->{2}), \"Rust/C layout mismatch: sizeof({1}.{2})\");"
+            ), mirror, item.name, field.name).unwrap();
+        }
+    }
+    fs::write(output, c_source)
+}
@@ -0,0 +1,526 @@
+//! 从 Rust 端的 extern 声明生成 C 函数原型。
+//!
+//! 被扫描的声明包括：
+//! 1. `extern "C" { fn 函数名(形参) -> 返回值; }`块。
+//! 2. `ffi_closure_shim_fn!(...)`宏调用：回调函数形参被还原为函数指针，`user_data`被还原为`void*`，
+//!    自动封送的形参取其`as`之后的 C 类型。注销函数与取消函数也被一并还原。
+//! 3. `type 别名 = extern "C" fn(...)`函数指针类型别名。
+//! 4. `abi_layout!`宏调用里的结构体与枚举。它们被还原为 C 端的同名类型。
+//!
+//! 生成的 C 文件先`#include`头文件，再重复声明每个函数。C 编译器会拒绝与头文件冲突的重复声明，
+//! 且`#line`指令令编译错误指向 Rust 源码里的声明位置。
+use ::std::{collections::HashMap, fmt::Write, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::Path};
+// ---- 词法分析 ----
+#[derive(Clone, Debug)]
+pub(crate) enum Token {
+    Ident(String, usize),
+    Punct(&'static str),
+    Literal(String),
+    Group(char, Vec<Token>)
+}
+impl Token {
+    pub(crate) fn is_ident(&self, expected: &str) -> bool {
+        matches!(self, Token::Ident(ident, _) if ident == expected)
+    }
+    pub(crate) fn is_punct(&self, expected: &str) -> bool {
+        matches!(self, Token::Punct(punct) if *punct == expected)
+    }
+}
+const PUNCTS: [&str; 25] = [
+    "->", "=>", "::", "#", "!", "$", "&", "*", "+", "-", "/", "%", "^", "|", "=", "<", ">", ",", ";", ":", ".", "?", "@", "~", "'"
+];
+pub(crate) struct Lexer<'a> {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    file: &'a str
+}
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(source: &str, file: &'a str) -> Self {
+        Self { chars: source.chars().collect(), position: 0, line: 1, file }
+    }
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+    /// 解析至`close`分隔符（或文件末尾），并返回其间的词法单元树
+    pub(crate) fn tokens(&mut self, close: Option<char>) -> Result<Vec<Token>, String> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek(0) {
+            let line = self.line;
+            match c {
+                _ if c.is_whitespace() => { self.bump(); },
+                '/' if self.peek(1) == Some('/') => while !matches!(self.bump(), Some('\n') | None) {},
+                '/' if self.peek(1) == Some('*') => self.block_comment(),
+                '(' | '[' | '{' => {
+                    self.bump();
+                    let close = match c { '(' => ')', '[' => ']', _ => '}' };
+                    tokens.push(Token::Group(c, self.tokens(Some(close))?));
+                },
+                ')' | ']' | '}' => {
+                    self.bump();
+                    return if close == Some(c) {
+                        Ok(tokens)
+                    } else {
+                        Err(format!("{}:{} 多余的 {}", self.file, line, c))
+                    };
+                },
+                '"' => tokens.push(Token::Literal(self.string())),
+                'r' | 'b' if self.raw_string_ahead() => tokens.push(Token::Literal(self.raw_string())),
+                'b' if self.peek(1) == Some('"') => {
+                    self.bump();
+                    tokens.push(Token::Literal(self.string()));
+                },
+                '\'' if self.peek(1) == Some('\\') || self.peek(2) == Some('\'') => tokens.push(Token::Literal(self.char_literal())),
+                _ if c.is_alphanumeric() || c == '_' => {
+                    let mut ident = String::new();
+                    while let Some(c) = self.peek(0).filter(|c| c.is_alphanumeric() || *c == '_') {
+                        ident.push(c);
+                        self.bump();
+                    }
+                    tokens.push(if c.is_ascii_digit() { Token::Literal(ident) } else { Token::Ident(ident, line) });
+                },
+                _ => {
+                    let punct = PUNCTS.iter().find(|punct| punct.chars().enumerate().all(|(i, p)| self.peek(i) == Some(p)));
+                    let punct = punct.copied().unwrap_or("?");
+                    for _ in 0..punct.chars().count().max(1) {
+                        self.bump();
+                    }
+                    tokens.push(Token::Punct(punct));
+                }
+            }
+        }
+        match close {
+            None => Ok(tokens),
+            Some(close) => Err(format!("{} 缺少与 {} 配对的分隔符", self.file, close))
+        }
+    }
+    fn block_comment(&mut self) {
+        let mut depth = 0;
+        while let Some(c) = self.bump() {
+            match (c, self.peek(0)) {
+                ('/', Some('*')) => {
+                    self.bump();
+                    depth += 1;
+                },
+                ('*', Some('/')) => {
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                },
+                _ => ()
+            }
+        }
+    }
+    fn string(&mut self) -> String {
+        let mut literal = String::new();
+        self.bump();
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => { self.bump(); },
+                '"' => break,
+                _ => literal.push(c)
+            }
+        }
+        literal
+    }
+    /// r"..."、r#"..."#、br"..."。注意与原始标识符 r#ident 相区分
+    fn raw_string_ahead(&self) -> bool {
+        let mut offset = if self.peek(0) == Some('b') { 1 } else { 0 };
+        if self.peek(offset) != Some('r') {
+            return false;
+        }
+        offset += 1;
+        while self.peek(offset) == Some('#') {
+            offset += 1;
+        }
+        self.peek(offset) == Some('"')
+    }
+    fn raw_string(&mut self) -> String {
+        while self.bump() != Some('r') {}
+        let mut hashes = 0;
+        while self.peek(0) == Some('#') {
+            self.bump();
+            hashes += 1;
+        }
+        self.bump();
+        let mut literal = String::new();
+        while let Some(c) = self.bump() {
+            if c == '"' && (0..hashes).all(|i| self.peek(i) == Some('#')) {
+                (0..hashes).for_each(|_| { self.bump(); });
+                break;
+            }
+            literal.push(c);
+        }
+        literal
+    }
+    fn char_literal(&mut self) -> String {
+        let mut literal = String::new();
+        self.bump();
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => { self.bump(); },
+                '\'' => break,
+                _ => literal.push(c)
+            }
+        }
+        literal
+    }
+}
+// ---- C 类型 ----
+#[derive(Clone, Debug)]
+pub(crate) enum CType {
+    Named(String),
+    Pointer { pointee: Box<CType>, is_const: bool },
+    /// 函数指针
+    Function { ret: Box<CType>, params: Vec<CType> }
+}
+impl CType {
+    fn void() -> Self {
+        CType::Named("void".to_string())
+    }
+    fn void_ptr() -> Self {
+        CType::Pointer { pointee: Box::new(CType::void()), is_const: false }
+    }
+    /// 以`declarator`（可为空的抽象声明符）声明此类型
+    pub(crate) fn declare(&self, declarator: &str) -> String {
+        match self {
+            CType::Named(name) if declarator.is_empty() => name.clone(),
+            CType::Named(name) => format!("{} {}", name, declarator),
+            CType::Pointer { pointee, is_const: true } => match &**pointee {
+                CType::Named(name) => format!("const {} *{}", name, declarator),
+                pointee => pointee.declare(&format!("const *{}", declarator))
+            },
+            CType::Pointer { pointee, is_const: false } => pointee.declare(&format!("*{}", declarator)),
+            CType::Function { ret, params } => ret.declare(&format!("(*{})({})", declarator, Self::params(params)))
+        }
+    }
+    fn params(params: &[CType]) -> String {
+        if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.iter().map(|param| param.declare("")).collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+/// 被还原的 C 函数原型
+struct Prototype {
+    name: String,
+    ret: CType,
+    params: Vec<CType>,
+    line: usize
+}
+impl Prototype {
+    fn declare(&self) -> String {
+        self.ret.declare(&format!("{}({})", self.name, CType::params(&self.params)))
+    }
+}
+// ---- 语法分析 ----
+/// 类型别名。函数指针类型别名映射至其定义，比如`type Callback2 = extern "C" fn(c_int) -> c_int;`；
+/// `abi_layout!`声明的结构体与枚举则映射至`None`，即 C 端的同名类型
+pub(crate) type Aliases = HashMap<String, Option<Vec<Token>>>;
+/// 以顶层逗号切分词法单元。尖括号不是词法单元树的分组，所以须自行计数其嵌套深度
+pub(crate) fn split_commas(tokens: &[Token]) -> Vec<&[Token]> {
+    let (mut segments, mut start, mut depth) = (Vec::new(), 0, 0_i32);
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Punct("<") => depth += 1,
+            Token::Punct(">") => depth -= 1,
+            Token::Punct(",") if depth == 0 => {
+                segments.push(&tokens[start..i]);
+                start = i + 1;
+            },
+            _ => ()
+        }
+    }
+    segments.push(&tokens[start..]);
+    segments
+}
+/// 在顶层查找关键字`as`
+fn find_as(tokens: &[Token]) -> Option<usize> {
+    tokens.iter().position(|token| token.is_ident("as"))
+}
+pub(crate) fn rust_to_c(tokens: &[Token], aliases: &Aliases) -> Result<CType, String> {
+    match tokens {
+        [Token::Punct("*"), Token::Ident(qualifier, _), rest @ ..] => Ok(CType::Pointer {
+            pointee: Box::new(rust_to_c(rest, aliases)?),
+            is_const: qualifier == "const"
+        }),
+        [Token::Punct("&"), rest @ ..] => {
+            let rest = match rest {
+                [Token::Punct("'"), Token::Ident(_, _), rest @ ..] => rest,
+                rest => rest
+            };
+            let (is_const, rest) = match rest {
+                [mutability, rest @ ..] if mutability.is_ident("mut") => (false, rest),
+                rest => (true, rest)
+            };
+            Ok(CType::Pointer { pointee: Box::new(rust_to_c(rest, aliases)?), is_const })
+        },
+        // 空指针优化：Option<&T> 与 Option<extern fn> 都是可空的指针。其它的 Option<T> 没有对应的 C 类型
+        [option, Token::Punct("<"), inner @ .., Token::Punct(">")] if option.is_ident("Option") => match rust_to_c(inner, aliases)? {
+            pointer @ (CType::Pointer { .. } | CType::Function { .. }) => Ok(pointer),
+            _ => Err(describe(tokens))
+        },
+        [unsafety, rest @ ..] if unsafety.is_ident("unsafe") => rust_to_c(rest, aliases),
+        [abi, Token::Literal(_), function, Token::Group('(', params), rest @ ..] if abi.is_ident("extern") && function.is_ident("fn") => {
+            let params = split_commas(params).into_iter()
+                .filter(|param| !param.is_empty())
+                .map(|param| rust_to_c(strip_name(param), aliases))
+                .collect::<Result<_, _>>()?;
+            let ret = match rest {
+                [] => CType::void(),
+                [Token::Punct("->"), ret @ ..] => rust_to_c(ret, aliases)?,
+                _ => return Err(describe(tokens))
+            };
+            Ok(CType::Function { ret: Box::new(ret), params })
+        },
+        [Token::Group('(', inner)] if inner.is_empty() => Ok(CType::void()),
+        // 路径只看末段，比如 ::std::ffi::c_void 与 libc::c_int
+        [.., Token::Ident(name, _)] if tokens.iter().all(|token| matches!(token, Token::Ident(..) | Token::Punct("::"))) => {
+            match aliases.get(name) {
+                Some(Some(alias)) => return rust_to_c(alias, aliases),
+                Some(None) => return Ok(CType::Named(name.clone())),
+                None => ()
+            }
+            let c_name = match name.as_str() {
+                "c_void" => "void",
+                "c_char" => "char",
+                "c_schar" | "i8" => "signed char",
+                "c_uchar" | "u8" => "unsigned char",
+                "c_short" | "i16" => "short",
+                "c_ushort" | "u16" => "unsigned short",
+                "c_int" | "i32" => "int",
+                "c_uint" | "u32" => "unsigned",
+                "c_long" => "long",
+                "c_ulong" => "unsigned long",
+                "c_longlong" | "i64" => "long long",
+                "c_ulonglong" | "u64" => "unsigned long long",
+                "c_float" | "f32" => "float",
+                "c_double" | "f64" => "double",
+                "usize" | "size_t" => "size_t",
+                "isize" | "ptrdiff_t" => "ptrdiff_t",
+                "bool" => "_Bool",
+                _ => return Err(describe(tokens))
+            };
+            Ok(CType::Named(c_name.to_string()))
+        },
+        _ => Err(describe(tokens))
+    }
+}
+/// 去掉形参名，比如`a: c_int`->`c_int`
+fn strip_name(param: &[Token]) -> &[Token] {
+    match param {
+        [Token::Ident(_, _), Token::Punct(":"), ty @ ..] => ty,
+        ty => ty
+    }
+}
+pub(crate) fn describe(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| match token {
+        Token::Ident(text, _) | Token::Literal(text) => text.clone(),
+        Token::Punct(punct) => punct.to_string(),
+        Token::Group(open, _) => format!("{}..", open)
+    }).collect::<Vec<_>>().join(" ")
+}
+/// 解析 ffi_closure_shim_fn! 的形参。回调函数的形参与（被导入）外部函数的形参共用此语法
+fn shim_params(tokens: &[Token], aliases: &Aliases) -> Result<Vec<CType>, String> {
+    let mut params = Vec::new();
+    let mut legacy = false;
+    for segment in split_commas(tokens) {
+        match segment {
+            // 旧式语法的双逗号
+            [] => legacy = true,
+            [user_data] if user_data.is_ident("user_data") => params.push(CType::void_ptr()),
+            // 旧式语法：回调函数与 void * 都在末尾
+            [callback, Token::Group('(', cb_params)] if legacy && callback.is_ident("callback") => {
+                let mut cb_params = shim_params(cb_params, aliases)?;
+                cb_params.push(CType::void_ptr());
+                params.push(CType::Function { ret: Box::new(CType::void()), params: cb_params });
+                params.push(CType::void_ptr());
+            },
+            [Token::Punct("#"), Token::Group('[', _), rest @ ..] => params.extend(shim_params(rest, aliases)?),
+            [Token::Ident(_, _), Token::Punct(":"), callback, Token::Group('(', cb_params), rest @ ..] if callback.is_ident("callback") => {
+                let ret = match rest {
+                    [] => CType::void(),
+                    [Token::Punct("->"), ret @ ..] => match find_as(ret) {
+                        // -> rust 类型 as C 类型 { 映射 }
+                        Some(at) => match &ret[at + 1..] {
+                            [c_type @ .., Token::Group('{', _)] => rust_to_c(c_type, aliases)?,
+                            c_type => rust_to_c(c_type, aliases)?
+                        },
+                        None => rust_to_c(ret, aliases)?
+                    },
+                    _ => return Err(describe(segment))
+                };
+                params.push(CType::Function { ret: Box::new(ret), params: shim_params(cb_params, aliases)? });
+            },
+            // 自动封送的形参取 C 类型。切片对应“指针 + 长度”两个形参
+            [Token::Ident(_, _), Token::Punct(":"), ty @ ..] => match find_as(ty) {
+                Some(at) => match &ty[at + 1..] {
+                    [Token::Group('(', pair)] => for c_type in split_commas(pair) {
+                        params.push(rust_to_c(c_type, aliases)?);
+                    },
+                    c_type => params.push(rust_to_c(c_type, aliases)?)
+                },
+                None => params.push(rust_to_c(ty, aliases)?)
+            },
+            _ => return Err(describe(segment))
+        }
+    }
+    Ok(params)
+}
+/// 解析一次 ffi_closure_shim_fn! 宏调用
+fn shim_prototypes(tokens: &[Token], aliases: &Aliases) -> Result<Vec<Prototype>, String> {
+    let mut tokens = tokens;
+    let mut is_async = false;
+    loop {
+        match tokens {
+            [Token::Punct("#"), Token::Group('[', _), rest @ ..] => tokens = rest,
+            [keyword, rest @ ..] if keyword.is_ident("async") => {
+                is_async = true;
+                tokens = rest;
+            },
+            _ => break
+        }
+    }
+    let (name, params, line, rest) = match tokens {
+        [Token::Ident(name, line), Token::Group('(', params), rest @ ..] => (name, params, *line, rest),
+        _ => return Err(describe(tokens))
+    };
+    let (ret, rest) = match rest {
+        [Token::Punct("->"), rest @ ..] => {
+            let end = rest.iter().position(|token| token.is_punct(";")).unwrap_or(rest.len());
+            (rust_to_c(&rest[..end], aliases)?, &rest[end..])
+        },
+        rest => (CType::void(), rest)
+    };
+    let mut prototypes = vec![Prototype { name: name.clone(), ret: ret.clone(), params: shim_params(params, aliases)?, line }];
+    match rest {
+        [] => (),
+        // 注销函数与取消函数：实参是注册函数的返回值或 void * 用户数据
+        [Token::Punct(";"), _, Token::Punct("=>"), Token::Ident(name, line), Token::Group('(', args)] => {
+            let params = split_commas(args).into_iter().filter(|arg| !arg.is_empty()).map(|arg| match arg {
+                [user_data] if user_data.is_ident("user_data") => Ok(CType::void_ptr()),
+                [registration] if registration.is_ident("registration") => Ok(ret.clone()),
+                arg => Err(describe(arg))
+            }).collect::<Result<_, _>>()?;
+            let ret = if is_async { CType::Named("int".to_string()) } else { CType::void() };
+            prototypes.push(Prototype { name: name.clone(), ret, params, line: *line });
+        },
+        rest => return Err(describe(rest))
+    }
+    Ok(prototypes)
+}
+/// 解析 extern "C" { ... } 块里的函数声明
+fn extern_prototypes(tokens: &[Token], aliases: &Aliases) -> Result<Vec<Prototype>, String> {
+    let mut prototypes = Vec::new();
+    for item in tokens.split(|token| token.is_punct(";")) {
+        let item = match item {
+            [Token::Punct("#"), Token::Group('[', _), rest @ ..] => rest,
+            item => item
+        };
+        match item {
+            [] => (),
+            [function, Token::Ident(name, line), Token::Group('(', params), rest @ ..] if function.is_ident("fn") => {
+                let params = split_commas(params).into_iter()
+                    .filter(|param| !param.is_empty())
+                    .map(|param| rust_to_c(strip_name(param), aliases))
+                    .collect::<Result<_, _>>()?;
+                let ret = match rest {
+                    [] => CType::void(),
+                    [Token::Punct("->"), ret @ ..] => rust_to_c(ret, aliases)?,
+                    _ => return Err(describe(item))
+                };
+                prototypes.push(Prototype { name: name.clone(), ret, params, line: *line });
+            },
+            item => return Err(describe(item))
+        }
+    }
+    Ok(prototypes)
+}
+pub(crate) fn contains_macro_var(tokens: &[Token]) -> bool {
+    tokens.iter().any(|token| match token {
+        Token::Punct("$") => true,
+        Token::Group(_, tokens) => contains_macro_var(tokens),
+        _ => false
+    })
+}
+/// 递归地收集函数指针类型别名与`abi_layout!`声明的类型名
+pub(crate) fn collect_aliases(tokens: &[Token], aliases: &mut Aliases) {
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Ident(keyword, _) if keyword == "type" => if let [Token::Ident(name, _), Token::Punct("="), rest @ ..] = &tokens[i + 1..] {
+                let end = rest.iter().position(|token| token.is_punct(";")).unwrap_or(rest.len());
+                if rest[..end].iter().any(|token| token.is_ident("fn")) {
+                    aliases.insert(name.clone(), Some(rest[..end].to_vec()));
+                }
+            },
+            Token::Ident(layout, _) if layout == "abi_layout" => if let [Token::Punct("!"), Token::Group(_, items), ..] = &tokens[i + 1..] {
+                for pair in items.windows(2) {
+                    if let [keyword, Token::Ident(name, _)] = pair {
+                        if keyword.is_ident("struct") || keyword.is_ident("enum") {
+                            aliases.insert(name.clone(), None);
+                        }
+                    }
+                }
+            },
+            Token::Group(_, tokens) => collect_aliases(tokens, aliases),
+            _ => ()
+        }
+    }
+}
+/// 递归地收集函数原型。跳过 macro_rules! 的定义体与任何含有宏变量的声明
+fn collect_prototypes(tokens: &[Token], aliases: &Aliases, prototypes: &mut Vec<Prototype>) -> Result<(), String> {
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i..] {
+            [macro_rules, Token::Punct("!"), Token::Ident(_, _), Token::Group(..), ..] if macro_rules.is_ident("macro_rules") => i += 3,
+            [shim, Token::Punct("!"), Token::Group('(', args), ..] if shim.is_ident("ffi_closure_shim_fn") => {
+                if !contains_macro_var(args) && !matches!(args.first(), Some(Token::Punct("@"))) {
+                    prototypes.extend(shim_prototypes(args, aliases)?);
+                }
+                i += 2;
+            },
+            [abi, Token::Literal(_), Token::Group('{', items), ..] if abi.is_ident("extern") => {
+                if !contains_macro_var(items) {
+                    prototypes.extend(extern_prototypes(items, aliases)?);
+                }
+                i += 2;
+            },
+            [Token::Group(_, tokens), ..] => collect_prototypes(tokens, aliases, prototypes)?,
+            _ => ()
+        }
+        i += 1;
+    }
+    Ok(())
+}
+/// 从`sources`里的 Rust 文件生成 C 函数原型，并写入`output`。生成的 C 文件会`#include "header"`。
+pub(crate) fn generate(root: &Path, sources: &[&str], header: &str, output: &Path) -> IoResult<()> {
+    let mut c_source = String::new();
+    writeln!(c_source, "/* 由 build.rs 从 Rust 端的 extern 声明生成。若它们与 {} 不一致，则编译失败 */", header).unwrap();
+    writeln!(c_source, "#include \"{}\"", header).unwrap();
+    for source in sources {
+        let path = root.join(source);
+        let tokens = Lexer::new(&fs::read_to_string(&path)?, source).tokens(None).map_err(|err| IoError::new(ErrorKind::Other, err))?;
+        let mut aliases = Aliases::new();
+        collect_aliases(&tokens, &mut aliases);
+        let mut prototypes = Vec::new();
+        collect_prototypes(&tokens, &aliases, &mut prototypes).map_err(|err| {
+            IoError::new(ErrorKind::Other, format!("{} 里有无法被还原为 C 类型的声明：{}", source, err))
+        })?;
+        for prototype in prototypes {
+            writeln!(c_source, "#line {} \"{}\"", prototype.line, source.replace('\\', "/")).unwrap();
+            writeln!(c_source, "{};", prototype.declare()).unwrap();
+        }
+    }
+    fs::write(output, c_source)
+}
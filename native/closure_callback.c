@@ -1,15 +1,14 @@
-#include <stddef.h>
+#include "closure_callback.h"
+#include <errno.h>
 //
 // صհӦ Rust ˵ bin/ffi-closure-callback.rs  ffi-closure-callback-by-zst.rs ļ
 //
-typedef void (*Callback1)(int result, void* closure);
-
 void add_two_numbers_by_val(int a, int b, Callback1 cb, void* closure)
 {
     int result = a + b;
     cb(result, closure);
 }
-void add_two_numbers_by_ptr(int* a, int* b, Callback1 cb, void* closure)
+void add_two_numbers_by_ptr(int* a, const int* b, Callback1 cb, void* closure)
 {
     int result = *a + *b;
     cb(result, closure);
@@ -17,9 +16,6 @@ void add_two_numbers_by_ptr(int* a, int* b, Callback1 cb, void* closure)
 //
 // ָŻӦ Ruest ˵ bin/ffi-nullable-pointer-optimization.rs ļ
 //
-typedef int (*Callback2)(int a); // Ӧ Rust ˵ Option<Callback2> Ͷ
-typedef int (*Callback3)(Callback2 cb2, int a); // Ӧ Rust ˵ Option<Callback3> Ͷ
-
 static int callback2(int result)
 {
     return result + result;
@@ -33,4 +29,406 @@ int register1(Callback3 cb3, int a)
         return cb3(NULL, a);
     }
     return cb3(callback2, a); // Ҳشպָ
-}
\ No newline at end of file
+}
+// Խṹ崫 register1 ʵ
+int register2(const Registration* registration)
+{
+    int result = register1(registration->cb3, registration->a);
+    return registration->tag == '-' ? -result : result;
+}
+//
+// ص + λõ void* ûݣӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+void parse_brackets(const char* input, OnStart on_start, void* closure, OnEnd on_end, OnError on_error)
+{
+    int depth = 0;
+    for (int position = 0; input[position] != '\0'; position++) {
+        if (input[position] == '(') {
+            on_start(closure, depth++);
+        } else if (input[position] == ')') {
+            if (depth == 0) {
+                on_error(position, closure);
+            } else {
+                on_end(--depth, closure);
+            }
+        }
+    }
+}
+//
+// ֵĻصӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+void sort_numbers(void* closure, int* numbers, size_t len, Compare compare)
+{
+    for (size_t i = 1; i < len; i++) {
+        int number = numbers[i];
+        size_t j = i;
+        for (; j > 0 && compare(closure, &numbers[j - 1], &number) > 0; j--) {
+            numbers[j] = numbers[j - 1];
+        }
+        numbers[j] = number;
+    }
+}
+int visit_numbers(const int* numbers, size_t len, Visit visit, void* closure)
+{
+    for (size_t i = 0; i < len; i++) {
+        int code = visit(numbers[i], closure);
+        if (code != 0) {
+            return code;
+        }
+    }
+    return 0;
+}
+//
+// ص panicӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+static int call_count = 0;
+
+// ûصֱ䷵طֵѵõĴ
+int count_until_stop(int limit, Visit visit, void* closure)
+{
+    call_count = 0;
+    while (call_count < limit) {
+        if (visit(call_count++, closure) != 0) {
+            break;
+        }
+    }
+    return call_count;
+}
+// һ count_until_stop() ûصĴ Rust  count_until_stop() غ׳ panicҲܱѯ
+int last_call_count(void)
+{
+    return call_count;
+}
+//
+//  C ˳ڳеĻصӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+#define MAX_HANDLERS 8
+static struct {
+    Handler handler;
+    void* closure;
+} handlers[MAX_HANDLERS];
+
+// עصţע򷵻 -ENOSPC
+int register_handler(Handler handler, void* closure)
+{
+    for (int id = 0; id < MAX_HANDLERS; id++) {
+        if (handlers[id].handler == NULL) {
+            handlers[id].handler = handler;
+            handlers[id].closure = closure;
+            return id;
+        }
+    }
+    return -ENOSPC;
+}
+void unregister_handler(int id)
+{
+    if (id >= 0 && id < MAX_HANDLERS) {
+        handlers[id].handler = NULL;
+        handlers[id].closure = NULL;
+    }
+}
+//  void * ûעص
+void unregister_closure(void* closure)
+{
+    for (int id = 0; id < MAX_HANDLERS; id++) {
+        if (handlers[id].handler != NULL && handlers[id].closure == closure) {
+            handlers[id].handler = NULL;
+            handlers[id].closure = NULL;
+        }
+    }
+}
+// עĻص㲥¼رõĻص
+int emit_event(int event)
+{
+    int count = 0;
+    for (int id = 0; id < MAX_HANDLERS; id++) {
+        if (handlers[id].handler != NULL) {
+            handlers[id].handler(event, handlers[id].closure);
+            count++;
+        }
+    }
+    return count;
+}
+//
+//  C ˹߳ʱûصӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+#ifndef _WIN32
+#include <pthread.h>
+#include <unistd.h>
+#include <stdlib.h>
+
+#define MAX_TASKS 16
+//  =  * MAX_TASKS + ۡÿһΣһԹڵŲиͬһ۵
+#define MAX_GENERATION (0x7fffffff / MAX_TASKS)
+// صڼ䣬۴ TASK_FIRING ״ֱ̬ص֮űͷ
+enum TaskState { TASK_FREE, TASK_PENDING, TASK_CANCELLED, TASK_FIRING };
+static struct {
+    enum TaskState state;
+    int generation;
+    int a, b;
+    unsigned delay_ms;
+    Done done;
+    void* closure;
+} tasks[MAX_TASKS];
+static pthread_mutex_t tasks_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int fired_count = 0;
+static int cancel_count = 0;
+
+static void* run_task(void* arg)
+{
+    int slot = (int)(size_t)arg;
+    usleep(tasks[slot].delay_ms * 1000);
+    pthread_mutex_lock(&tasks_mutex);
+    int cancelled = tasks[slot].state == TASK_CANCELLED;
+    int sum = tasks[slot].a + tasks[slot].b;
+    Done done = tasks[slot].done;
+    void* closure = tasks[slot].closure;
+    if (cancelled) {
+        tasks[slot].state = TASK_FREE;
+    } else {
+        tasks[slot].state = TASK_FIRING;
+        fired_count++;
+    }
+    pthread_mutex_unlock(&tasks_mutex);
+    if (!cancelled) {
+        done(sum, closure);
+        pthread_mutex_lock(&tasks_mutex);
+        tasks[slot].state = TASK_FREE;
+        pthread_mutex_unlock(&tasks_mutex);
+    }
+    return NULL;
+}
+// ڹ߳ϣʱ delay_ms ص a + bţ޿еۣ򷵻 -1
+int add_later(int a, int b, unsigned delay_ms, Done done, void* closure)
+{
+    pthread_mutex_lock(&tasks_mutex);
+    int slot = 0;
+    while (slot < MAX_TASKS && tasks[slot].state != TASK_FREE) {
+        slot++;
+    }
+    int id = -1;
+    if (slot < MAX_TASKS) {
+        tasks[slot].state = TASK_PENDING;
+        tasks[slot].generation = (tasks[slot].generation + 1) % MAX_GENERATION;
+        tasks[slot].a = a;
+        tasks[slot].b = b;
+        tasks[slot].delay_ms = delay_ms;
+        tasks[slot].done = done;
+        tasks[slot].closure = closure;
+        id = tasks[slot].generation * MAX_TASKS + slot;
+    }
+    pthread_mutex_unlock(&tasks_mutex);
+    if (id < 0) {
+        return -1;
+    }
+    pthread_t thread;
+    pthread_create(&thread, NULL, run_task, (void*)(size_t)slot);
+    pthread_detach(thread);
+    return id;
+}
+// ȡ񡣷 1 ʾȡɹصٱˣ 0 ʾصѾڣãѹ
+int cancel_add(int id)
+{
+    int cancelled = 0;
+    pthread_mutex_lock(&tasks_mutex);
+    cancel_count++;
+    if (id >= 0) {
+        int slot = id % MAX_TASKS;
+        if (tasks[slot].generation == id / MAX_TASKS && tasks[slot].state == TASK_PENDING) {
+            tasks[slot].state = TASK_CANCELLED;
+            cancelled = 1;
+        }
+    }
+    pthread_mutex_unlock(&tasks_mutex);
+    return cancelled;
+}
+typedef struct {
+    int a, b;
+    unsigned delay_ms;
+    Divided done;
+    void* closure;
+} Division;
+static void* run_division(void* arg)
+{
+    Division division = *(Division*)arg;
+    free(arg);
+    usleep(division.delay_ms * 1000);
+    division.done(division.a / division.b, division.a % division.b, division.closure);
+    return NULL;
+}
+// ڹ߳ϣʱ delay_ms ص a  b ɱȡ
+void divide_later(int a, int b, unsigned delay_ms, Divided done, void* closure)
+{
+    Division* division = malloc(sizeof(Division));
+    division->a = a;
+    division->b = b;
+    division->delay_ms = delay_ms;
+    division->done = done;
+    division->closure = closure;
+    pthread_t thread;
+    pthread_create(&thread, NULL, run_division, division);
+    pthread_detach(thread);
+}
+// ѱص
+int fired_tasks(void)
+{
+    pthread_mutex_lock(&tasks_mutex);
+    int count = fired_count;
+    pthread_mutex_unlock(&tasks_mutex);
+    return count;
+}
+// cancel_add õĴ
+int cancel_calls(void)
+{
+    pthread_mutex_lock(&tasks_mutex);
+    int count = cancel_count;
+    pthread_mutex_unlock(&tasks_mutex);
+    return count;
+}
+#endif
+#ifndef _WIN32
+typedef struct {
+    int begin;
+    int end;
+    Work work;
+    void* closure;
+} Chunk;
+static void* run_chunk(void* arg)
+{
+    Chunk* chunk = (Chunk*)arg;
+    for (int index = chunk->begin; index < chunk->end; index++) {
+        chunk->work(index, chunk->closure);
+    }
+    return NULL;
+}
+//  threads ̣߳ͬʱأص work(0..count)ȴȫ 0 threads 򷵻 EINVAL
+int parallel_for(int count, int threads, Work work, void* closure)
+{
+    pthread_t workers[MAX_TASKS];
+    Chunk chunks[MAX_TASKS];
+    if (threads <= 0) {
+        return EINVAL;
+    }
+    if (threads > MAX_TASKS) {
+        threads = MAX_TASKS;
+    }
+    for (int i = 0; i < threads; i++) {
+        chunks[i].begin = count * i / threads;
+        chunks[i].end = count * (i + 1) / threads;
+        chunks[i].work = work;
+        chunks[i].closure = closure;
+        pthread_create(&workers[i], NULL, run_chunk, &chunks[i]);
+    }
+    for (int i = 0; i < threads; i++) {
+        pthread_join(workers[i], NULL);
+    }
+    return 0;
+}
+#endif
+// ---- ԶַͣƬɿָ벼ֵ ----
+#include <ctype.h>
+#include <stdio.h>
+//  name ɿյ title ƴʺٻصshout ʱʺﱻתΪд
+void greet(const char* name, const char* title, int shout, OnGreeting on_greeting, void* closure)
+{
+    char text[128];
+    if (title) {
+        snprintf(text, sizeof(text), "Hello, %s %s!", title, name);
+    } else {
+        snprintf(text, sizeof(text), "Hello, %s!", name);
+    }
+    if (shout) {
+        for (char* c = text; *c; c++) {
+            *c = (char)toupper((unsigned char)*c);
+        }
+    }
+    on_greeting(text, title, shout, closure);
+}
+//  label ɿյ base ƴǩıٻص base ǿգصɾ extra ׷ӷֵ extra ǿָ
+int label_number(const char* label, const int* base, OnLabel on_label, void* closure)
+{
+    char text[64];
+    int extra = 0;
+    if (base) {
+        snprintf(text, sizeof(text), "%s=%d", label, *base);
+    } else {
+        snprintf(text, sizeof(text), "%s", label);
+    }
+    on_label(text, base ? &extra : NULL, closure);
+    return (base ? *base : 0) + extra;
+}
+//  input ӳ outputضн϶̵ĳȣ calls ǿգۼӻص
+void map_numbers(const int* input, size_t len, int* output, size_t out_len, int* calls, Map map, void* closure)
+{
+    size_t count = len < out_len ? len : out_len;
+    for (size_t i = 0; i < count; i++) {
+        output[i] = map(input[i], i + 1 == count, closure);
+        if (calls) {
+            (*calls)++;
+        }
+    }
+}
+//  width Ļڱ numbers׸ڵ previous_sum ǿָ
+void sliding_windows(const int* numbers, size_t len, int width, OnWindow on_window, void* closure)
+{
+    int previous_sum = 0;
+    for (size_t i = 0; i + width <= len; i++) {
+        on_window(numbers + i, width, i == 0 ? NULL : &previous_sum, closure);
+        previous_sum = 0;
+        for (int j = 0; j < width; j++) {
+            previous_sum += numbers[i + j];
+        }
+    }
+}
+// ɻص C ˵Ļ 16 ԪأٷػԪ֮
+int fill_and_sum(int len, Fill fill, void* closure)
+{
+    int buffer[16] = {0};
+    int sum = 0;
+    if (len > 16) {
+        len = 16;
+    }
+    fill(buffer, len, closure);
+    for (int i = 0; i < len; i++) {
+        sum += buffer[i];
+    }
+    return sum;
+}
+//
+// ԼӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+// ԶŷָķǸصرǷַ򷵻 -EINVAL
+int parse_numbers(const char* input, OnNumber on_number, void* closure)
+{
+    int count = 0;
+    while (*input != '\0') {
+        if (*input < '0' || *input > '9') {
+            return -EINVAL;
+        }
+        int number = 0;
+        while (*input >= '0' && *input <= '9') {
+            number = number * 10 + (*input++ - '0');
+        }
+        on_number(number, closure);
+        count++;
+        if (*input == ',') {
+            input++;
+        }
+    }
+    return count;
+}
+// ԻصȨȨƽֵȨ֮Ϊ㣬 errno Ϊ EDOM 0
+double weighted_average(const int* numbers, size_t len, Weight weight, void* closure)
+{
+    double sum = 0, total = 0;
+    for (size_t i = 0; i < len; i++) {
+        double w = weight(numbers[i], closure);
+        sum += numbers[i] * w;
+        total += w;
+    }
+    if (total == 0) {
+        errno = EDOM;
+        return 0;
+    }
+    return sum / total;
+}
@@ -0,0 +1,98 @@
+#ifndef CLOSURE_CALLBACK_H
+#define CLOSURE_CALLBACK_H
+//
+// native/closure_callback.c ĳӿڡbuild.rs  Rust ˵ extern  C ԭͣ뱾ͷļ
+// һ𱻱롣ԣһ Rust  C ˵ĺǩһ£ʧܣʱƻջ
+//
+#include <stddef.h>
+//
+// صհӦ Rust ˵ bin/ffi-closure-callback.rs  ffi-closure-callback-by-zst.rs ļ
+//
+typedef void (*Callback1)(int result, void* closure);
+
+void add_two_numbers_by_val(int a, int b, Callback1 cb, void* closure);
+void add_two_numbers_by_ptr(int* a, const int* b, Callback1 cb, void* closure);
+//
+// ָŻӦ Ruest ˵ bin/ffi-nullable-pointer-optimization.rs ļ
+//
+typedef int (*Callback2)(int a); // Ӧ Rust ˵ Option<Callback2> Ͷ
+typedef int (*Callback3)(Callback2 cb2, int a); // Ӧ Rust ˵ Option<Callback3> Ͷ
+
+int register1(Callback3 cb3, int a);
+// Ӧ Rust  abi_layout!  Registration ṹ塣build.rs ȶ˵Ĳ
+typedef struct {
+    char tag;      // '-' ʾԽȡ
+    Callback3 cb3; // Ϊ
+    int a;
+} Registration;
+
+int register2(const Registration* registration);
+//
+// ص + λõ void* ûݣӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+typedef void (*OnStart)(void* closure, int depth);     // void* λ
+typedef void (*OnEnd)(int depth, void* closure);       // void* ĩλ
+typedef void (*OnError)(int position, void* closure);
+
+void parse_brackets(const char* input, OnStart on_start, void* closure, OnEnd on_end, OnError on_error);
+//
+// ֵĻصӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+typedef int (*Compare)(void* closure, const int* a, const int* b); //  BSD qsort_r ıȽϺvoid* λ
+typedef int (*Visit)(int number, void* closure);                  //  0 ʾֹ
+
+void sort_numbers(void* closure, int* numbers, size_t len, Compare compare);
+int visit_numbers(const int* numbers, size_t len, Visit visit, void* closure);
+//
+// ص panicӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+int count_until_stop(int limit, Visit visit, void* closure);
+int last_call_count(void);
+//
+//  C ˳ڳеĻصӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+typedef void (*Handler)(int event, void* closure);
+
+int register_handler(Handler handler, void* closure); // ע򷵻 -ENOSPC
+void unregister_handler(int id);
+void unregister_closure(void* closure);
+int emit_event(int event);
+//
+// ԶַͣƬɿָ벼ֵӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+typedef void (*OnGreeting)(const char* text, const char* title, int shouted, void* closure);
+typedef int (*Map)(int number, int is_last, void* closure);
+typedef void (*OnWindow)(const int* window, int width, const int* previous_sum, void* closure);
+typedef void (*Fill)(int* buffer, int len, void* closure);
+typedef void (*OnLabel)(const char* text, int* extra, void* closure);
+
+void greet(const char* name, const char* title, int shout, OnGreeting on_greeting, void* closure);
+int label_number(const char* label, const int* base, OnLabel on_label, void* closure);
+void map_numbers(const int* input, size_t len, int* output, size_t out_len, int* calls, Map map, void* closure);
+void sliding_windows(const int* numbers, size_t len, int width, OnWindow on_window, void* closure);
+int fill_and_sum(int len, Fill fill, void* closure);
+//
+// ԼӦ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+typedef void (*OnNumber)(int number, void* closure);
+typedef double (*Weight)(int number, void* closure);
+
+int parse_numbers(const char* input, OnNumber on_number, void* closure); // ʧʱ -EINVAL
+double weighted_average(const int* numbers, size_t len, Weight weight, void* closure); // ʧʱ errno Ϊ EDOM
+//
+// C ˹̣߳Ӧ Rust ˵ bin/ffi-closure-callback.rs ļ
+//
+#ifndef _WIN32
+typedef void (*Done)(int sum, void* closure);
+typedef void (*Work)(int index, void* closure);
+typedef void (*Divided)(int quotient, int remainder, void* closure);
+
+int add_later(int a, int b, unsigned delay_ms, Done done, void* closure);
+int cancel_add(int id);
+int fired_tasks(void);
+int cancel_calls(void);
+void divide_later(int a, int b, unsigned delay_ms, Divided done, void* closure);
+int parallel_for(int count, int threads, Work work, void* closure); // threads ʱ EINVAL
+#endif
+
+#endif
\ No newline at end of file
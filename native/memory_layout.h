@@ -0,0 +1,7 @@
+#ifndef MEMORY_LAYOUT_H
+#define MEMORY_LAYOUT_H
+//
+// Ӧ Rust ˵ bin/memory-layout_enum.rs ļbuild.rs Ϊɲ̽룬 Rust ʱȶ˵Ĳ
+//
+typedef enum { Example2_A } Example2;
+#endif
@@ -0,0 +1,210 @@
+//! 【ABI 布局】（跨`bin`）校验工具箱：计算`#[repr(C)]`类型的字段偏移量、填充字节与 niche，并与 C 端比对。
+//!
+//! 挂载方式（必须被挂载为`crate`根下的`abi_layout`模块，因为宏内部经由`$crate::abi_layout`路径引用本模块的类型）：
+//! ```ignore
+//! #[path = "../abi_layout.rs"]
+//! #[macro_use]
+//! mod abi_layout;
+//! ```
+//! 由`abi_layout!`声明的结构体会被双重地校验，枚举则仅被运行时校验：
+//! 1. 编译期 —— `build.rs`从结构体的宏调用还原出 C 镜像类型，再以`_Static_assert`比对它与 C 端头文件里同名类型的
+//!    `sizeof`与`offsetof`。不一致即编译失败。
+//! 2. 运行时 —— `AbiLayout::layout()`计算 Rust 端的布局，`TypeLayout::verify()`再与 C 端探针函数返回的布局比对。
+//!
+//! 所以，新的`bin`须被登记于`build.rs`的`layout_sources`列表，且 C 端的同名类型须被声明于`native`目录下的头文件。
+#![allow(dead_code, unused_macros)]
+use ::std::{error::Error, ffi::CString, fmt::{Display, Formatter, Result as FmtResult}, mem::{align_of, size_of}, ops::Range, os::raw::c_char};
+/// 字段的布局
+#[derive(Clone, Debug)]
+pub(crate) struct FieldLayout {
+    /// 字段名
+    pub(crate) name: &'static str,
+    /// 字段的 rust 类型
+    pub(crate) ty: &'static str,
+    /// 字段相对于结构体首地址的偏移量
+    pub(crate) offset: usize,
+    /// 字段类型的 size_of
+    pub(crate) size: usize,
+    /// 字段类型的 align_of
+    pub(crate) align: usize,
+    /// 字段类型是否还有空闲的 niche，即`Option<字段类型>`与字段类型等宽
+    pub(crate) niche: bool
+}
+impl FieldLayout {
+    /// 由字段类型`T`计算字段的布局
+    pub(crate) fn new<T>(name: &'static str, ty: &'static str, offset: usize) -> Self {
+        Self { name, ty, offset, size: size_of::<T>(), align: align_of::<T>(), niche: has_niche::<T>() }
+    }
+    /// 字段所占的字节区间
+    pub(crate) fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+/// 类型的布局。枚举没有字段
+#[derive(Clone, Debug)]
+pub(crate) struct TypeLayout {
+    /// 类型名，须与 C 端头文件里的类型名相同
+    pub(crate) name: &'static str,
+    /// size_of
+    pub(crate) size: usize,
+    /// align_of
+    pub(crate) align: usize,
+    /// 类型是否还有空闲的 niche，即`Option<T>`与`T`等宽
+    pub(crate) niche: bool,
+    /// 按声明顺序排列的字段
+    pub(crate) fields: Vec<FieldLayout>
+}
+impl TypeLayout {
+    /// 由类型`T`与它的字段布局计算类型的布局
+    pub(crate) fn new<T>(name: &'static str, fields: Vec<FieldLayout>) -> Self {
+        Self { name, size: size_of::<T>(), align: align_of::<T>(), niche: has_niche::<T>(), fields }
+    }
+    /// 填充字节：字段之间与末尾字段之后的空隙。枚举的字节都属于判别式，没有填充字节
+    pub(crate) fn padding(&self) -> Vec<Range<usize>> {
+        if self.fields.is_empty() {
+            return Vec::new();
+        }
+        let mut fields = self.fields.iter().map(FieldLayout::range).collect::<Vec<_>>();
+        fields.sort_by_key(|range| range.start);
+        let mut padding = Vec::new();
+        let mut end = 0;
+        for range in fields.into_iter().chain(Some(self.size..self.size)) {
+            if range.start > end {
+                padding.push(end..range.start);
+            }
+            end = end.max(range.end);
+        }
+        padding
+    }
+    /// 与 C 端探针函数返回的布局比对
+    pub(crate) fn verify(&self) -> Result<(), LayoutMismatch> {
+        let type_name = CString::new(self.name).unwrap();
+        let mismatch = |item: String, rust: usize, c: usize| LayoutMismatch { type_name: self.name, item, rust, c };
+        let c_size = unsafe { abi_layout_sizeof(type_name.as_ptr()) };
+        if c_size != self.size {
+            return Err(mismatch("sizeof".to_string(), self.size, c_size));
+        }
+        let c_align = unsafe { abi_layout_alignof(type_name.as_ptr()) };
+        if c_align != self.align {
+            return Err(mismatch("alignof".to_string(), self.align, c_align));
+        }
+        for field in &self.fields {
+            let field_name = CString::new(field.name).unwrap();
+            let c_offset = unsafe { abi_layout_offsetof(type_name.as_ptr(), field_name.as_ptr()) };
+            if c_offset != field.offset {
+                return Err(mismatch(format!("offsetof({})", field.name), field.offset, c_offset));
+            }
+        }
+        Ok(())
+    }
+}
+impl Display for TypeLayout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{}: alignment = {}; size = {}; niche = {}", self.name, self.align, self.size, if self.niche { "有" } else { "无" })?;
+        let padding = self.padding();
+        let mut rows = self.fields.iter().map(|field| (field.offset, format!(
+            "  [{:>3}..{:>3}) {}: {}; alignment = {}; niche = {}", field.offset, field.offset + field.size,
+            field.name, field.ty, field.align, if field.niche { "有" } else { "无" }
+        ))).chain(padding.iter().map(|range| (range.start, format!(
+            "  [{:>3}..{:>3}) 填充 {} 字节", range.start, range.end, range.len()
+        )))).collect::<Vec<_>>();
+        rows.sort_by_key(|(offset, _)| *offset);
+        rows.into_iter().try_for_each(|(_, row)| writeln!(f, "{}", row))
+    }
+}
+/// Rust 端与 C 端的布局不一致
+#[derive(Debug)]
+pub(crate) struct LayoutMismatch {
+    /// 类型名
+    pub(crate) type_name: &'static str,
+    /// sizeof、alignof 或 offsetof(字段名)
+    pub(crate) item: String,
+    /// Rust 端的值
+    pub(crate) rust: usize,
+    /// C 端探针的返回值。未知的类型是 0，未知的字段是 usize::MAX
+    pub(crate) c: usize
+}
+impl Display for LayoutMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.c {
+            0 if self.item == "sizeof" => write!(f, "C 端没有 {} 类型的布局探针", self.type_name),
+            usize::MAX => write!(f, "C 端的 {} 类型没有 {} 字段", self.type_name, self.item),
+            c => write!(f, "{} 类型的 {} 不一致：Rust 端是 {}，C 端是 {}", self.type_name, self.item, self.rust, c)
+        }
+    }
+}
+impl Error for LayoutMismatch {}
+/// 由`abi_layout!`实现。
+pub(crate) trait AbiLayout {
+    /// Rust 端的布局
+    fn layout() -> TypeLayout;
+}
+/// `T`是否还有空闲的 niche。若有，则`Option<T>`与`T`等宽，比如非空指针与 extern fn。
+pub(crate) fn has_niche<T>() -> bool {
+    size_of::<Option<T>>() == size_of::<T>()
+}
+extern "C" {
+    // 由 build.rs 生成的 C 端探针
+    fn abi_layout_sizeof(type_name: *const c_char) -> usize;
+    fn abi_layout_alignof(type_name: *const c_char) -> usize;
+    fn abi_layout_offsetof(type_name: *const c_char, field: *const c_char) -> usize;
+}
+/// 声明`#[repr(C)]`的结构体与（无字段的）枚举，并为它们实现`AbiLayout`。宏会自动添加`#[repr(C)]`，所以不必再写。
+/// 类型名须与 C 端头文件里的类型名相同。
+/// ```ignore
+/// abi_layout! {
+///     struct Registration {
+///         tag: c_char,
+///         cb3: Option<Callback3>,
+///         a: c_int
+///     }
+/// }
+/// ```
+#[allow(unused_macro_rules)] // 各`bin`可能只声明结构体，或只声明枚举
+macro_rules! abi_layout {
+    () => {};
+    (
+        $( #[$attr: meta] )*
+        $vis: vis struct $name: ident {
+            $( $( #[$field_attr: meta] )* $field_vis: vis $field: ident : $field_type: ty ),* $(,)?
+        }
+        $( $rest: tt )*
+    ) => {
+        $( #[$attr] )*
+        #[repr(C)]
+        $vis struct $name {
+            $( $( #[$field_attr] )* $field_vis $field: $field_type ),*
+        }
+        impl $crate::abi_layout::AbiLayout for $name {
+            fn layout() -> $crate::abi_layout::TypeLayout {
+                // 字段的地址减去结构体的地址，即是字段的偏移量。仅取地址，而不读取未初始化的内存
+                let uninit = ::std::mem::MaybeUninit::<Self>::uninit();
+                let base = uninit.as_ptr();
+                $crate::abi_layout::TypeLayout::new::<Self>(stringify!($name), vec![$(
+                    $crate::abi_layout::FieldLayout::new::<$field_type>(stringify!($field), stringify!($field_type),
+                        unsafe { ::std::ptr::addr_of!((*base).$field) } as usize - base as usize)
+                ),*])
+            }
+        }
+        abi_layout!($( $rest )*);
+    };
+    (
+        $( #[$attr: meta] )*
+        $vis: vis enum $name: ident {
+            $( $( #[$variant_attr: meta] )* $variant: ident $( = $discriminant: expr )? ),* $(,)?
+        }
+        $( $rest: tt )*
+    ) => {
+        $( #[$attr] )*
+        #[repr(C)]
+        $vis enum $name {
+            $( $( #[$variant_attr] )* $variant $( = $discriminant )? ),*
+        }
+        impl $crate::abi_layout::AbiLayout for $name {
+            fn layout() -> $crate::abi_layout::TypeLayout {
+                $crate::abi_layout::TypeLayout::new::<Self>(stringify!($name), Vec::new())
+            }
+        }
+        abi_layout!($( $rest )*);
+    };
+}
@@ -2,111 +2,816 @@
  * 宏
  * 1. 功能：将（被导入）外部函数 extern "C" fn 形参中的【函数指针】升级为【闭包】。
  * 2. 对（被导入）外部函数的要求
- *    a. 末尾形参·必须是 void * 类型的指针变量，因为它被用来搬运来自 rust 端的【闭包】捕获变量。C 端不需要解构它们，而仅只透传。
- *    b. 倒数第二个形参·必须是【函数指针】回调函数。而且，回调函数的末尾形参·也必须是 void * 类型的指针变量。它被用来将【闭包】捕获变量送回给 rust 端
- *    c. 仅只支持一个回调函数。
+ *    a. 须有一个 void * 类型的形参（位置任意），因为它被用来搬运来自 rust 端的【闭包】捕获变量。C 端不需要解构它们，而仅只透传。
+ *    b. 可有任意多个【函数指针】回调函数（位置任意）。而且，每个回调函数也都须有一个 void * 类型的形参（位置任意）。它被用来将【闭包】捕获变量送回给 rust 端
+ *    c. 所有回调函数共享同一个 void * 用户数据。即，宏会把全部【闭包】收拢于一个【上下文】结构体内，再透传该结构体的指针。
+ *    d. 回调函数可有返回值。【闭包】的返回值既能被原样地返回给 C 端，也能按宏调用里声明的 match 分支被映射为 C 端的约定值。
+ *    e. 【闭包】的 panic 不会穿过 C 栈帧展开。垫片·回调函数会截获它，并向 C 端返回 #[on_panic = 值] 所声明的值（缺省为
+ *       Default::default()）。待（被导入）外部函数返回之后，再于 rust 端重新抛出该 panic。
+ *    f. 若 C 端会保存回调函数与 void * 以备日后调用（即，注册回调函数），则须以 `; drop => 注销函数(实参)` 声明注销函数。
+ *       注销函数的实参只能是 registration（注册函数的返回值，须是 Copy 的）或 user_data。此时，【闭包】须是 'static 的，
+ *       且【上下文】被装箱。注册函数返回 ClosureHandle 句柄，由它在被 drop 时注销回调函数与释放【上下文】。
+ *    g. 若 C 端会（从其它线程）择机调用唯一的回调函数一次，则可用 async 前缀将其转换为 Future。回调函数的实参即是 Future 的
+ *       输出值。可选地以 `; cancel => 取消函数(registration)` 声明取消函数：它返回非零值表示取消成功，回调函数不会再被调用了。
+ *    h. 若 C 端会从其它线程调用（非 async 形式的）回调函数，则须以 #[thread_safe(serialized)] 或 #[thread_safe(concurrent)] 显式地声明线程安全模式。
+ *       前者要求【闭包】是 FnMut + Send 的，并以互斥锁串行化对它的调用；后者要求【闭包】是 Fn + Send + Sync 的，并允许并发调用。
+ *    i. 外部函数与回调函数的形参都可被自动封送，写作`形参名: rust 类型 as C 类型`：
+ *       &str、String、Option<&str> as *const c_char；&[T]、&mut [T] as (指针类型, 长度类型)，对应 C 端相邻的两个形参；
+ *       Option<&T>、Option<&mut T> as 指针类型；bool as 整数类型。所有权规则是：（被导入）外部函数的实参仅被 C 端借用至
+ *       它返回为止；回调函数的借用类实参（&str、切片、Option<&T>）仅被【闭包】借用至回调返回为止，String 则是被复制的。
+ *    j. 若（被导入）外部函数以返回值或 errno 报告错误，则可以 #[error_convention(约定名)] 声明其【错误约定】，令垫片·调用函数
+ *       返回 Result<T, FfiError>。约定名包括 nonzero、negative_errno、null 与 errno，详见 ffi_error 模块。对注册回调函数的
+ *       形式，注册失败时【上下文】被立即释放，且返回 Err 而不是 ClosureHandle 句柄。
  * 3. 例子
  *    C 端
- *      // 回调函数的函数签名。注意末尾形参 void *
- *      typedef void (*Callback)(int result, void *closure);
- *      // 导出函数的函数签名。注意末尾两个形参 Callback 和 void *
- *      void add_two_numbers_by_ptr(int* a, int* b, Callback cb, void *closure) {...}
+ *      // 回调函数的函数签名。注意 void * 形参的位置
+ *      typedef void (*OnStart)(void *closure, int depth);
+ *      typedef void (*OnEnd)(int depth, void *closure);
+ *      // 导出函数的函数签名。注意 void * 形参的位置
+ *      void parse_brackets(const char *input, OnStart on_start, void *closure, OnEnd on_end) {...}
  *    Rust 端
  *      ffi_closure_shim_fn!(
  *          // （被导入）外部函数名
- *          add_two_numbers_by_ptr(
- *              // 形参列表内不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
- *              a: *mut c_int, b: Option<&i32>
- *              // 注意双逗号分隔
- *              ,,
- *              // （被导出）回调函数。形参列表内也不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
- *              callback(result: c_int)
+ *          parse_brackets(
+ *              // 普通形参
+ *              input: *const c_char,
+ *              // （被导出）回调函数。以 user_data 关键字标记其 void * 形参的位置
+ *              on_start: callback(user_data, depth: c_int),
+ *              // 以 user_data 关键字标记（被导入）外部函数 void * 形参的位置
+ *              user_data,
+ *              on_end: callback(depth: c_int, user_data)
  *          )
  *      );
- *      风格有点类似于 nodejs 里的 require('util').promisify(fn) 不包含形参列表里的回调函数。
+ *      回调函数的返回值：
+ *          // 原样返回
+ *          visit: callback(number: c_int, user_data) -> c_int
+ *          // 【闭包】返回 bool，C 端约定 0 表示继续遍历
+ *          visit: callback(number: c_int, user_data) -> bool as c_int { true => 0, false => 1 }
+ *          // 【闭包】返回 Ordering，C 端约定负数、零、正数
+ *          compare: callback(user_data, a: &c_int, b: &c_int) -> Ordering as c_int { Ordering::Less => -1, Ordering::Equal => 0, Ordering::Greater => 1 }
+ *          // 【闭包】返回 Result，C 端约定非零值是错误码
+ *          visit: callback(number: c_int, user_data) -> Result<(), c_int> as c_int { Ok(()) => 0, Err(code) => code }
+ *          // 【闭包】panic 时，向 C 端返回 1 以中止遍历
+ *          #[on_panic = 1] visit: callback(number: c_int, user_data) -> bool as c_int { true => 0, false => 1 }
+ *      注册回调函数：
+ *          ffi_closure_shim_fn!(register_handler(handler: callback(event: c_int, user_data), user_data) -> c_int; drop => unregister_handler(registration));
+ *      转换为 Future：
+ *          ffi_closure_shim_fn!(async add_later(a: c_int, b: c_int, on_done: callback(sum: c_int, user_data), user_data) -> c_int; cancel => cancel_add(registration));
+ *      自动封送：
+ *          ffi_closure_shim_fn!(greet(name: &str as *const c_char, shout: bool as c_int, on_greeting: callback(text: String as *const c_char, user_data), user_data));
+ *          ffi_closure_shim_fn!(sliding_windows(numbers: &[c_int] as (*const c_int, usize), on_window: callback(window: &[c_int] as (*const c_int, c_int), user_data), user_data));
+ *      错误约定：
+ *          ffi_closure_shim_fn!(#[error_convention(negative_errno)] parse_numbers(input: &str as *const c_char, on_number: callback(number: c_int, user_data), user_data) -> c_int);
+ *      线程安全：
+ *          ffi_closure_shim_fn!(#[thread_safe(concurrent)] parallel_for(count: c_int, threads: c_int, work: callback(index: c_int, user_data), user_data) -> c_int);
+ *      风格有点类似于 nodejs 里的 require('util').promisify(fn) 不包含形参列表里的 void * 参数。而 async 形式则真的就是 promisify 了。
+ *    旧式语法（仅一个回调函数，且 void * 分别是外部函数与回调函数的末尾形参）依旧被支持：
+ *      ffi_closure_shim_fn!(add_two_numbers_by_ptr(a: *mut c_int, b: Option<&i32>,, callback(result: c_int)));
  * 4. 宏私下做了哪些工作？
  *    就上例而言，给（导入）外部函数与（导出）回调函数生成【垫片函数】。
- *    a. （导入）外部函数的【垫片函数】装箱【闭包】捕获变量，经由 void * 类型抹平指针，透传给 C 端程序。
- *    b. （导出）回调函数的【垫片函数】拆箱被透传的【闭包】捕获变量。再以回调函数的“有效载荷”实参，调用【闭包】。
+ *    a. （导入）外部函数的【垫片函数】收拢【闭包】为【上下文】结构体，经由 void * 类型抹平指针，透传给 C 端程序。
+ *    b. （导出）回调函数的【垫片函数】从被透传的【上下文】结构体中取出对应的【闭包】。再以回调函数的“有效载荷”实参，调用【闭包】。
  */
+#[path = "../ffi_error.rs"]
+#[macro_use]
+mod ffi_error;
+use ::futures::{future::BoxFuture, FutureExt};
+use ::std::{any::Any, ffi::c_void, fmt::{self, Debug, Formatter}, future::Future, pin::Pin, sync::{Arc, atomic::{AtomicBool, Ordering}},
+            task::{Context as TaskContext, Poll}};
 macro_rules! ffi_closure_shim_fn {
     (
+        // 被导入的外部函数。它会在 C 端的（工作）线程上，择机调用唯一的回调函数一次
+        async $c_fn_name: ident ( $( $params: tt )* ) $( -> $c_fn_ret: ty )?
+        // 取消函数。若有，则回调函数被调用之前 Future 就被 drop 时，会调用它。它的实参只能是 registration：
+        // 回调函数一经调用就释放【上下文】，所以 void * 指针可能已被新任务的【上下文】复用，不能用来指认任务
+        $( ; cancel => $cancel: ident ( $( $cancel_args: ident ),* ) )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name [[$( $c_fn_ret )?] [] []; async $( $cancel ( $( $cancel_args ),* ) )?]; [] [] [] [] [] []; $( $params )*);
+    };
+    (
+        // 错误约定。若有，则垫片·调用函数返回 Result<T, FfiError>。见 ffi_error 模块
+        //   nonzero | negative_errno | null | errno
+        $( #[error_convention($convention: ident)] )?
+        // 线程安全模式。若有，则回调函数可被 C 端从其它线程调用
+        //   serialized：【闭包】须是 FnMut + Send 的，且对它的调用会被互斥锁串行化
+        //   concurrent：【闭包】须是 Fn + Send + Sync 的，且可被多个线程同时调用
+        $( #[thread_safe($threading: ident)] )?
         // 被导入的外部函数。
-        $c_fn_name: ident (
-            // 形参列表内不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
-            $( $c_fn_param_name: ident : $c_fn_param_type: ty),*,,
-            // 被导出回调函数。形参列表内也不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
-            callback( $( $cb_fn_param_name: ident : $cb_fn_param_type: ty ),* )
-        )
+        $c_fn_name: ident ( $( $params: tt )* ) $( -> $c_fn_ret: ty )?
+        // 注销函数。若有，则被导入的外部函数是“注册回调函数”的，而不是“同步调用回调函数”的
+        $( ; drop => $unregister: ident ( $( $unregister_args: ident ),* ) )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name [[$( $c_fn_ret )?] [$( $threading )?] [$( $convention )?]; $( drop $unregister ( $( $unregister_args ),* ) )?]; [] [] [] [] [] []; $( $params )*);
+    };
+    // ---- 逐个解析（被导入）外部函数的形参 ----
+    // 状态：外部函数名 [[外部函数·返回值类型] [线程安全模式] [错误约定]; 生成形式（同步 | drop 注销函数 | async 取消函数）]; [外部函数·形参] [垫片·调用函数·形参] [实参·封送语句] [外部函数·实参] [回调函数·记录] [void * 形参名]; 未解析的形参
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt [$user_data: ident]; $(,)?) => {
+        ffi_closure_shim_fn!(@emit $c_fn_name $c_fn_ret; $ext $wrapper $prelude $args $callbacks [$user_data]);
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt []; $(,)?) => {
+        compile_error!(concat!("外部函数 ", stringify!($c_fn_name), " 缺少 user_data 形参"));
+    };
+    // void * 用户数据
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt [$user_data: ident]; user_data $( $rest: tt )*) => {
+        compile_error!(concat!("外部函数 ", stringify!($c_fn_name), " 仅能有一个 user_data 形参"));
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] $wrapper: tt $prelude: tt [$( $args: tt )*] $callbacks: tt [];
+        user_data $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* user_data: *mut ::std::ffi::c_void,] $wrapper $prelude [$( $args )* user_data,] $callbacks [user_data];
+            $( $( $rest )* )?
+        );
+    };
+    // 旧式语法：双逗号之后，仅一个回调函数，且 void * 都在末尾
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt $user_data: tt;
+        $(,)? callback ( $( $cb_fn_param_name: ident : $cb_fn_param_type: ty ),* )
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret; $ext $wrapper $prelude $args $callbacks $user_data;
+            callback: callback($( $cb_fn_param_name: $cb_fn_param_type, )* user_data), user_data
+        );
+    };
+    // 回调函数：转入回调函数形参的解析，并将外部函数的解析状态打包随行
+    //   a. 无返回值
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt $user_data: tt;
+        $( #[on_panic = $sentinel: expr] )? $cb_fn_name: ident : callback ( $( $cb_params: tt )* ) $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback { $c_fn_name $c_fn_ret; $ext $wrapper $prelude $args $callbacks $user_data; $( $( $rest )* )? }
+            $cb_fn_name [()] [()] { value => value } [$( $sentinel )?]; [] [] [] []; $( $cb_params )*
+        );
+    };
+    //   b. 【闭包】的返回值被原样地返回给 C 端
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt $user_data: tt;
+        $( #[on_panic = $sentinel: expr] )? $cb_fn_name: ident : callback ( $( $cb_params: tt )* ) -> $cb_fn_ret: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback { $c_fn_name $c_fn_ret; $ext $wrapper $prelude $args $callbacks $user_data; $( $( $rest )* )? }
+            $cb_fn_name [$cb_fn_ret] [$cb_fn_ret] { value => value } [$( $sentinel )?]; [] [] [] []; $( $cb_params )*
+        );
+    };
+    //   c. 【闭包】的返回值按 match 分支被映射为 C 端的约定值，比如 Ordering -> c_int
+    (@param $c_fn_name: ident $c_fn_ret: tt; $ext: tt $wrapper: tt $prelude: tt $args: tt $callbacks: tt $user_data: tt;
+        $( #[on_panic = $sentinel: expr] )? $cb_fn_name: ident : callback ( $( $cb_params: tt )* ) -> $closure_ret: ty as $cb_fn_ret: ty { $( $mapping: tt )* } $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback { $c_fn_name $c_fn_ret; $ext $wrapper $prelude $args $callbacks $user_data; $( $( $rest )* )? }
+            $cb_fn_name [$closure_ret] [$cb_fn_ret] { $( $mapping )* } [$( $sentinel )?]; [] [] [] []; $( $cb_params )*
+        );
+    };
+    // 自动封送的形参：`形参名: rust 类型 as C 类型`。垫片·调用函数以 rust 类型接收实参，再转换为 C 类型
+    //   a. &str、String 或 Option<&str> as *const c_char：C 端仅在（被导入）外部函数的调用期间借用该字符串。
+    //      若 C 端需要保存它，须自行复制。含 NUL 字节的字符串会令垫片·调用函数在调用 C 端之前 panic
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : & str as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: &str,]
+            [$( $prelude )* let $c_fn_param_name = marshal::to_c_string($c_fn_param_name, stringify!($c_fn_param_name));]
+            [$( $args )* $c_fn_param_name.as_ptr(),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : String as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: String,]
+            [$( $prelude )* let $c_fn_param_name = marshal::to_c_string($c_fn_param_name, stringify!($c_fn_param_name));]
+            [$( $args )* $c_fn_param_name.as_ptr(),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : Option<&str> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: Option<&str>,]
+            [$( $prelude )* let $c_fn_param_name = $c_fn_param_name.map(|value| marshal::to_c_string(value, stringify!($c_fn_param_name)));]
+            [$( $args )* $c_fn_param_name.as_deref().map_or(::std::ptr::null(), ::std::ffi::CStr::as_ptr),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   b. &[T] 或 &mut [T] as (指针类型, 长度类型)：一个切片形参对应 C 端相邻的“指针 + 长度”两个形参。
+    //      C 端仅在（被导入）外部函数的调用期间借用该切片
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] $prelude: tt [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : & [$elem: ty] as ($c_ptr: ty, $c_len: ty) $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_ptr, _: $c_len,]
+            [$( $wrapper )* $c_fn_param_name: &[$elem],]
+            $prelude
+            [$( $args )* $c_fn_param_name.as_ptr(), marshal::to_c_len($c_fn_param_name.len(), stringify!($c_fn_param_name)),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] $prelude: tt [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : &mut [$elem: ty] as ($c_ptr: ty, $c_len: ty) $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_ptr, _: $c_len,]
+            [$( $wrapper )* $c_fn_param_name: &mut [$elem],]
+            $prelude
+            [$( $args )* $c_fn_param_name.as_mut_ptr(), marshal::to_c_len($c_fn_param_name.len(), stringify!($c_fn_param_name)),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   c. Option<&T> 或 Option<&mut T> as 指针类型：None 对应空指针
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] $prelude: tt [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : Option<&mut $elem: ty> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: Option<&mut $elem>,]
+            $prelude
+            [$( $args )* $c_fn_param_name.map_or(::std::ptr::null_mut(), |value| -> *mut $elem { value }),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] $prelude: tt [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : Option<& $elem: ty> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: Option<&$elem>,]
+            $prelude
+            [$( $args )* $c_fn_param_name.map_or(::std::ptr::null(), |value| -> *const $elem { value }),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   d. bool as 整数类型：false 对应 0，true 对应 1
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] $prelude: tt [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : bool as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_type,]
+            [$( $wrapper )* $c_fn_param_name: bool,]
+            $prelude
+            [$( $args )* <$c_type>::from($c_fn_param_name),]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    // 普通形参
+    (@param $c_fn_name: ident $c_fn_ret: tt; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt $user_data: tt;
+        $c_fn_param_name: ident : $c_fn_param_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name $c_fn_ret;
+            [$( $ext )* $c_fn_param_name: $c_fn_param_type,]
+            [$( $wrapper )* $c_fn_param_name: $c_fn_param_type,]
+            [$( $prelude )*]
+            [$( $args )* $c_fn_param_name,]
+            $callbacks $user_data;
+            $( $( $rest )* )?
+        );
+    };
+    // ---- 逐个解析回调函数的形参 ----
+    // async 形式：回调函数不对应【闭包】，而是完成 Future
+    (@callback { $c_fn_name: ident [$c_fn_ret: tt $threading: tt $convention: tt; async $( $cancel: tt )*]; [$( $ext: tt )*] $wrapper: tt $prelude: tt [$( $args: tt )*] [$( $callbacks: tt )*] $user_data: tt; $( $rest: tt )* }
+        $cb_fn_name: ident [()] [()] $mapping: tt $on_panic: tt;
+        [$( $shim_params: tt )*] $closure_types: tt $closure_args: tt [$cb_user_data: ident]; $(,)?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name [$c_fn_ret $threading $convention; async $( $cancel )*];
+            [$( $ext )* $cb_fn_name: unsafe extern "C" fn($( $shim_params )*),]
+            $wrapper
+            $prelude
+            [$( $args )* Context::$cb_fn_name,]
+            [$( $callbacks )* $cb_fn_name { [$( $shim_params )*] $closure_types $closure_args $cb_user_data }]
+            $user_data;
+            $( $rest )*
+        );
+    };
+    // 状态：{外部函数的解析状态} 回调函数名 [闭包·返回值类型] [垫片·回调函数·返回值类型] {返回值映射} [panic 时的返回值]; [垫片·回调函数·形参] [闭包·形参类型] [闭包·实参] [void * 形参名]; 未解析的形参
+    (@callback { $c_fn_name: ident [$c_fn_ret: tt $threading: tt $convention: tt; $( $form: tt )*]; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] [$( $callbacks: tt )*] $user_data: tt; $( $rest: tt )* }
+        $cb_fn_name: ident [$closure_ret: ty] [$cb_fn_ret: ty] $mapping: tt $on_panic: tt;
+        [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] [$cb_user_data: ident]; $(,)?
+    ) => {
+        ffi_closure_shim_fn!(@param $c_fn_name [$c_fn_ret $threading $convention; $( $form )*];
+            [$( $ext )* $cb_fn_name: unsafe extern "C" fn($( $shim_params )*) -> $cb_fn_ret,]
+            [$( $wrapper )* $cb_fn_name: ffi_closure_shim_fn!(@closure_param [$( $form )*] $threading ($( $closure_types )*) -> $closure_ret),]
+            [$( $prelude )*]
+            [$( $args )* Context::$cb_fn_name,]
+            [$( $callbacks )* $cb_fn_name {
+                [$( $shim_params )*] [$( $closure_types )*] [$( $closure_args )*] $cb_user_data [$closure_ret] [$cb_fn_ret] $mapping $on_panic
+            }]
+            $user_data;
+            $( $rest )*
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; $shim_params: tt $closure_types: tt $closure_args: tt []; $(,)?) => {
+        compile_error!(concat!("回调函数 ", stringify!($cb_fn_name), " 缺少 user_data 形参"));
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; $shim_params: tt $closure_types: tt $closure_args: tt [$cb_user_data: ident]; user_data $( $rest: tt )*) => {
+        compile_error!(concat!("回调函数 ", stringify!($cb_fn_name), " 仅能有一个 user_data 形参"));
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] $closure_types: tt $closure_args: tt [];
+        user_data $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* user_data: *mut ::std::ffi::c_void,] $closure_types $closure_args [user_data];
+            $( $( $rest )* )?
+        );
+    };
+    // 自动封送的形参：`形参名: rust 类型 as C 类型`。垫片·回调函数接收 C 类型的实参，再转换为 rust 类型传给【闭包】
+    //   a. &str 或 Option<&str> as *const c_char：【闭包】仅在回调期间借用该字符串。空指针（对 &str 而言）与非 UTF-8
+    //      字符串会令【闭包】不被调用，而是如同【闭包】panic 一样被处理
+    //      String as *const c_char：复制一份被【闭包】独占的字符串
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : & str as $c_type: ty $(, $( $rest: tt )* )?
     ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* &str,]
+            [$( $closure_args )* { unsafe { marshal::from_c_str($cb_fn_param_name, stringify!($cb_fn_param_name)) } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : String as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* String,]
+            [$( $closure_args )* { unsafe { marshal::from_c_str($cb_fn_param_name, stringify!($cb_fn_param_name)) }.to_owned() },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : Option<&str> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* Option<&str>,]
+            [$( $closure_args )* { unsafe { marshal::from_nullable_c_str($cb_fn_param_name, stringify!($cb_fn_param_name)) } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   b. &[T] 或 &mut [T] as (指针类型, 长度类型)：【闭包】仅在回调期间借用该切片。空指针对应空切片
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : & [$elem: ty] as ($c_ptr: ty, $c_len: ty) $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_ptr, len: $c_len,]
+            [$( $closure_types )* &[$elem],]
+            [$( $closure_args )* { unsafe { marshal::from_raw_parts($cb_fn_param_name, len, stringify!($cb_fn_param_name)) } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : &mut [$elem: ty] as ($c_ptr: ty, $c_len: ty) $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_ptr, len: $c_len,]
+            [$( $closure_types )* &mut [$elem],]
+            [$( $closure_args )* { unsafe { marshal::from_raw_parts_mut($cb_fn_param_name, len, stringify!($cb_fn_param_name)) } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   c. Option<&T> 或 Option<&mut T> as 指针类型：空指针对应 None
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : Option<&mut $elem: ty> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* Option<&mut $elem>,]
+            [$( $closure_args )* { unsafe { $cb_fn_param_name.as_mut() } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : Option<& $elem: ty> as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* Option<&$elem>,]
+            [$( $closure_args )* { unsafe { $cb_fn_param_name.as_ref() } },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    //   d. bool as 整数类型：非零值对应 true
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : bool as $c_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $c_type,]
+            [$( $closure_types )* bool,]
+            [$( $closure_args )* { $cb_fn_param_name != 0 },]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    (@callback $outer: tt $cb_fn_name: ident $closure_ret: tt $cb_fn_ret: tt $mapping: tt $on_panic: tt; [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt )*] $cb_user_data: tt;
+        $cb_fn_param_name: ident : $cb_fn_param_type: ty $(, $( $rest: tt )* )?
+    ) => {
+        ffi_closure_shim_fn!(@callback $outer $cb_fn_name $closure_ret $cb_fn_ret $mapping $on_panic;
+            [$( $shim_params )* $cb_fn_param_name: $cb_fn_param_type,]
+            [$( $closure_types )* $cb_fn_param_type,]
+            [$( $closure_args )* $cb_fn_param_name,]
+            $cb_user_data;
+            $( $( $rest )* )?
+        );
+    };
+    // ---- 生成代码 ----
+    // 同步调用回调函数：【上下文】仅存活于（被导入）外部函数的调用期间
+    (@emit $c_fn_name: ident [[$( $c_fn_ret: ty )?] $threading: tt $convention: tt;]; [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt [$user_data: ident]) => {
         /**
          * 【FFI·垫片·调用函数】
-         * 1. 首先，对【闭包】捕获变量“装箱”，
-         * 2. 然后，代理调用真正的 extern fn 外部函数和以 void * 指针透传【闭包】。
-         * 3. 接着，以【FFI·垫片·回调函数】接收 C 端的反馈结果和被回传的【闭包】。
-         * 4. 于是，“拆箱”【闭包】捕获变量。
-         * 5. 最后，凭借 C 端反馈结果与被“拆箱”的【闭包】捕获变量，调用【闭包】。
+         * 1. 首先，将各个【闭包】收拢于【上下文】结构体内，
+         * 2. 然后，代理调用真正的 extern fn 外部函数和以 void * 指针透传【上下文】。
+         * 3. 接着，以【FFI·垫片·回调函数】接收 C 端的反馈结果和被回传的【上下文】。
+         * 4. 于是，从【上下文】中取出对应的【闭包】。
+         * 5. 最后，凭借 C 端反馈结果，调用【闭包】，并将【闭包】的返回值映射为 C 端约定的返回值。
          */
-        fn $c_fn_name<F>($( $c_fn_param_name: $c_fn_param_type, )* mut closure: F)
-        where F: FnMut($( $cb_fn_param_type),*) {
-            use ::std::ffi::c_void;
-            /**
-             * 【FFI·垫片·回调函数·签名】
-             * 不得不写在【外部块】之外，因为【外部类型】还没有被正式地支持需要开启 feature-gate 开关
-             */
-            type ShimCallback = unsafe extern "C" fn($( $cb_fn_param_type, )* *mut c_void);
+        fn $c_fn_name($( $wrapper )*) -> ffi_closure_shim_fn!(@sync_ret $convention [$( $c_fn_ret )?]) {
+            use ::std::{ffi::c_void, sync::PoisonError};
             extern "C" {
                 /**
                  * 【外部函数】
-                 * 导入 C 端的功能函数 API
-                 * void add_two_numbers_by_ptr(int* a, int* b, Callback cb, void *closure)
+                 * 导入 C 端的功能函数 API。其中，回调函数形参的实参都是【垫片·回调函数】
                  */
-                fn $c_fn_name(
-                    $( $c_fn_param_name: $c_fn_param_type, )*
-                     // 回调函数 — 【垫片·回调函数】将作为其实参
-                    shim_callback: ShimCallback,
-                    // 作为状态值“兜转”传递的 Rust 端【闭包】结构体
-                    closure: *mut c_void
-                );
+                fn $c_fn_name($( $ext )*) $( -> $c_fn_ret )?;
             }
-            let shim_callback = specialize(&closure);
-            unsafe {
-                $c_fn_name($( $c_fn_param_name, )*  shim_callback, &mut closure as *mut _ as *mut c_void);
+            ffi_closure_shim_fn!(@context $threading $callbacks);
+            let context = ffi_closure_shim_fn!(@new_context $threading $callbacks);
+            let $user_data: *mut c_void = ::std::ptr::addr_of!(context).cast_mut().cast();
+            $( $prelude )*
+            ffi_closure_shim_fn!(@before_call $convention [$( $c_fn_ret )?]);
+            let result = ffi_closure_shim_fn!(@check $convention $c_fn_name [$( $c_fn_ret )?] unsafe { $c_fn_name($( $args )*) });
+            if let Some(payload) = context.panic.into_inner().unwrap_or_else(PoisonError::into_inner) {
+                ::std::panic::resume_unwind(payload);
             }
-            /**
-             * 【FFI·垫片·回调函数·定义】
-             * 此导出函数被刻意设计为 unsafe 的，因为需要由它的调用端自觉地确保 void * 指针背后
-             * 的数据值是类型匹配的【闭包】<T: Fn(..)>结构体
-             */
-            unsafe extern "C" fn shim_closure<F>($( $cb_fn_param_name: $cb_fn_param_type, )* closure: *mut c_void)
-            where F: FnMut($( $cb_fn_param_type),*) {
-                let closure = &mut *(closure as *mut F);
-                closure($($cb_fn_param_name),*);
+            result
+        }
+    };
+    // 注册回调函数：【上下文】被装箱，并由 ClosureHandle 句柄负责注销回调函数与释放【上下文】
+    (@emit $c_fn_name: ident [$c_fn_ret: tt $threading: tt $convention: tt; drop $unregister: ident ( $( $unregister_args: ident ),* )];
+        [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*] $callbacks: tt [$user_data: ident]
+    ) => {
+        /**
+         * 【FFI·垫片·注册函数】
+         * 1. 首先，将各个【闭包】收拢于被装箱的【上下文】结构体内，
+         * 2. 然后，代理调用真正的 extern fn 注册函数和以 void * 指针透传【上下文】。C 端会保存该指针。
+         * 3. 最后，返回 ClosureHandle 句柄。待句柄被 drop 时，先调用注销函数，再释放【上下文】。
+         *    若声明了错误约定且注册失败，则 C 端没有保存回调函数，所以直接释放【上下文】，并返回错误。
+         */
+        fn $c_fn_name($( $wrapper )*) -> ffi_closure_shim_fn!(@register_ret $convention ClosureHandle<ffi_closure_shim_fn!(@or_unit $c_fn_ret)>) {
+            use ::std::{any::Any, ffi::c_void, sync::PoisonError};
+            extern "C" {
+                /**
+                 * 【外部函数】
+                 * 导入 C 端的注册函数与注销函数
+                 */
+                fn $c_fn_name($( $ext )*) -> ffi_closure_shim_fn!(@or_unit $c_fn_ret);
+                fn $unregister($( $unregister_args: ffi_closure_shim_fn!(@unregister_type $c_fn_ret $unregister_args) ),*);
+            }
+            ffi_closure_shim_fn!(@context $threading $callbacks);
+            /// 先向 C 端注销回调函数，再释放【上下文】。注销函数的实参可能仅是 user_data，而不需要 registration
+            #[allow(unused_variables)]
+            unsafe fn unregister(registration: &ffi_closure_shim_fn!(@or_unit $c_fn_ret), context: *mut c_void) {
+                unsafe {
+                    $unregister($( ffi_closure_shim_fn!(@unregister_arg $unregister_args; *registration, context) ),*);
+                    drop(Box::from_raw(context.cast::<Context<'_>>()));
+                }
+            }
+            unsafe fn take_panic(context: *mut c_void) -> Option<Box<dyn Any + Send>> {
+                unsafe { &*context.cast::<Context<'_>>() }.panic.lock().unwrap_or_else(PoisonError::into_inner).take()
+            }
+            let $user_data = Box::into_raw(Box::new(ffi_closure_shim_fn!(@new_context $threading $callbacks))) as *mut c_void;
+            $( $prelude )*
+            ffi_closure_shim_fn!(@before_call $convention $c_fn_ret);
+            let registration = unsafe { $c_fn_name($( $args )*) };
+            ffi_closure_shim_fn!(@register $convention $c_fn_name $c_fn_ret; registration, $user_data;
+                unsafe { ClosureHandle::new(registration, $user_data, unregister, take_panic) }
+            )
+        }
+    };
+    // 回调函数被转换为 Future：【上下文】被装箱，并由【FFI·垫片·回调函数】在完成 Future 之后释放
+    (@emit $c_fn_name: ident [$c_fn_ret: tt $threading: tt $convention: tt; async $( $cancel: ident ( $( $cancel_args: ident ),* ) )?];
+        [$( $ext: tt )*] [$( $wrapper: tt )*] [$( $prelude: tt )*] [$( $args: tt )*]
+        [$cb_fn_name: ident { [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt, )*] $cb_user_data: ident }]
+        [$user_data: ident]
+    ) => {
+        /**
+         * 【FFI·垫片·异步函数】
+         * 1. 首先，创建 deferred future，并将“完成它”的闭包装箱为【上下文】，
+         * 2. 然后，立即调用真正的 extern fn 外部函数和以 void * 指针透传【上下文】。
+         * 3. 最后，返回 CallbackFuture。待 C 端（从任意线程）调用回调函数时，【FFI·垫片·回调函数】
+         *    以回调函数的“有效载荷”实参完成 Future，再释放【上下文】。
+         */
+        fn $c_fn_name($( $wrapper )*) -> CallbackFuture<ffi_closure_shim_fn!(@output_type $( $closure_types )*), ffi_closure_shim_fn!(@or_unit $c_fn_ret)> {
+            use ::deferred_future::ThreadDeferredFuture;
+            use ::futures::FutureExt;
+            use ::std::ffi::c_void;
+            extern "C" {
+                /**
+                 * 【外部函数】
+                 * 导入 C 端的异步函数与取消函数。取消函数返回非零值，表示回调函数不会再被调用了
+                 */
+                fn $c_fn_name($( $ext )*) -> ffi_closure_shim_fn!(@or_unit $c_fn_ret);
+                $( fn $cancel($( $cancel_args: ffi_closure_shim_fn!(@unregister_type $c_fn_ret $cancel_args) ),*) -> ::std::ffi::c_int; )?
             }
+            type Output = ffi_closure_shim_fn!(@output_type $( $closure_types )*);
             /**
-             * 【专化函数】
-             * 首先，从【闭包】对象实参推断【闭包】的具体类型 — 这只有编译器能做。
-             * 然后，返回被“专化”的【垫片·回调函数】。
+             * 【上下文】
+             * 一次性地完成 deferred future 的闭包。它须是 Send 的，因为 C 端可能从其它线程调用回调函数。
+             * fired 与 CallbackFuture 共享，以告知它回调函数已被调用、【上下文】已被释放。
              */
-            const fn specialize<F>(_closure: &F) -> ShimCallback
-            where F: FnMut($( $cb_fn_param_type),*) {
-                shim_closure::<F>
+            struct Context {
+                complete: Box<dyn FnOnce(Output) + Send>,
+                fired: ::std::sync::Arc<::std::sync::atomic::AtomicBool>
             }
+            impl Context {
+                /**
+                 * 【FFI·垫片·回调函数·定义】
+                 * 回调函数仅被调用一次，所以“拆箱”并释放【上下文】。
+                 * 封送实参时的 panic（比如，非 UTF-8 字符串）无处可被重新抛出，且绝不能穿过 C 栈帧展开，所以中止进程。
+                 */
+                unsafe extern "C" fn $cb_fn_name($( $shim_params )*) {
+                    use ::std::{panic::{self, AssertUnwindSafe}, process, sync::atomic::Ordering};
+                    let context = unsafe { Box::from_raw($cb_user_data.cast::<Self>()) };
+                    let output = panic::catch_unwind(AssertUnwindSafe(|| ffi_closure_shim_fn!(@output_value $( $closure_args, )*)))
+                        .unwrap_or_else(|_| process::abort());
+                    context.fired.store(true, Ordering::Release);
+                    (context.complete)(output);
+                }
+            }
+            let deferred_future = ThreadDeferredFuture::<Output>::default();
+            let defer = deferred_future.defer();
+            let fired = ::std::sync::Arc::default();
+            let context = Context {
+                complete: Box::new(move |output| defer.lock().unwrap().complete(output)),
+                fired: ::std::sync::Arc::clone(&fired)
+            };
+            let cancel: Option<unsafe fn(&ffi_closure_shim_fn!(@or_unit $c_fn_ret), *mut c_void)> = None $( .or({
+                /// 先向 C 端取消。若取消成功，回调函数便不会再被调用了，所以由此释放【上下文】
+                unsafe fn cancel_and_free(registration: &ffi_closure_shim_fn!(@or_unit $c_fn_ret), context: *mut c_void) {
+                    unsafe {
+                        if $cancel($( ffi_closure_shim_fn!(@cancel_arg $cancel_args; *registration) ),*) != 0 {
+                            drop(Box::from_raw(context.cast::<Context>()));
+                        }
+                    }
+                }
+                Some(cancel_and_free)
+            }) )?;
+            let $user_data = Box::into_raw(Box::new(context)) as *mut c_void;
+            $( $prelude )*
+            let registration = unsafe { $c_fn_name($( $args )*) };
+            unsafe { CallbackFuture::new(deferred_future.boxed(), registration, $user_data, cancel, fired) }
         }
     };
-    (
-        // 被导入的外部函数。
-        $c_fn_name: ident (
-            // 形参列表内不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
-            // 被导出回调函数。形参列表内也不包括末尾的 void * 参数，因为宏会自动为其添加上【回调·垫片·函数】
-            callback( $( $cb_fn_param_name: ident : $cb_fn_param_type: ty ),* )
-        )
-    ) => {
-        ffi_closure_shim_fn!(,, $( $cb_fn_param_name: $cb_fn_param_type),* );
+    // 【上下文】结构体与【FFI·垫片·回调函数】
+    (@context $threading: tt [$(
+        $cb_fn_name: ident {
+            [$( $shim_params: tt )*] [$( $closure_types: tt )*] [$( $closure_args: tt, )*] $cb_user_data: ident
+            [$closure_ret: ty] [$cb_fn_ret: ty] { $( $mapping: tt )* } [$( $sentinel: expr )?]
+        }
+    )*]) => {
+        /**
+         * 【上下文】
+         * 作为状态值“兜转”传递的 Rust 端【闭包】集合。每个回调函数对应一个字段。
+         */
+        struct Context<'closure> {
+            $( $cb_fn_name: ffi_closure_shim_fn!(@closure_field $threading ($( $closure_types )*) -> $closure_ret), )*
+            /// 被截获的【闭包】panic。待（被导入）外部函数返回之后，再于 rust 端重新抛出。
+            /// 它总被互斥锁保护着，因为线程安全模式下的回调函数可能被多个线程同时调用
+            panic: ::std::sync::Mutex<Option<Box<dyn ::std::any::Any + Send>>>
+        }
+        impl Context<'_> {
+            $(
+                /**
+                 * 【FFI·垫片·回调函数·定义】
+                 * 此导出函数被刻意设计为 unsafe 的，因为需要由它的调用端自觉地确保 void * 指针背后
+                 * 的数据值是【上下文】结构体。
+                 * 【上下文】仅被共享地借用，因为线程安全模式下的回调函数可能被多个线程同时调用。
+                 * 【闭包】的 panic 绝不能穿过 C 栈帧展开（未定义行为）。所以，先截获它，再向 C 端
+                 * 返回 panic 时的返回值。一旦有【闭包】panic 了，后续的回调便都不再调用【闭包】。
+                 */
+                unsafe extern "C" fn $cb_fn_name($( $shim_params )*) -> $cb_fn_ret {
+                    use ::std::{panic::{self, AssertUnwindSafe}, sync::PoisonError};
+                    let context = unsafe { &*$cb_user_data.cast::<Self>() };
+                    if context.panic.lock().unwrap_or_else(PoisonError::into_inner).is_none() {
+                        match panic::catch_unwind(AssertUnwindSafe(|| ffi_closure_shim_fn!(@invoke $threading context.$cb_fn_name; $( $closure_args ),*))) {
+                            Ok(value) => return match value {
+                                $( $mapping )*
+                            },
+                            Err(payload) => {
+                                context.panic.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(payload);
+                            }
+                        }
+                    }
+                    ffi_closure_shim_fn!(@on_panic $( $sentinel )?)
+                }
+            )*
+        }
+    };
+    (@new_context $threading: tt [$( $cb_fn_name: ident $record: tt )*]) => {
+        Context { $( $cb_fn_name: ffi_closure_shim_fn!(@new_closure_field $threading $cb_fn_name), )* panic: ::std::sync::Mutex::new(None) }
+    };
+    // 【闭包】形参的生存期：同步调用时仅需活过（被导入）外部函数的调用期间；注册回调函数时则须是 'static 的
+    (@closure_param [] $threading: tt $( $signature: tt )*) => { ffi_closure_shim_fn!(@closure_bound $threading [] $( $signature )*) };
+    (@closure_param [drop $( $form: tt )*] $threading: tt $( $signature: tt )*) => {
+        ffi_closure_shim_fn!(@closure_bound $threading ['static] $( $signature )*)
+    };
+    // 线程安全模式：【闭包】形参的类型、【上下文】字段的类型与调用方式
+    //   缺省：仅由调用线程调用。RefCell 令重入的回调函数 panic，而不是未定义行为
+    (@closure_bound [] [$( $lifetime: lifetime )?] $( $signature: tt )*) => { impl FnMut $( $signature )* $( + $lifetime )? };
+    (@closure_field [] $( $signature: tt )*) => { ::std::cell::RefCell<Box<dyn FnMut $( $signature )* + 'closure>> };
+    (@new_closure_field [] $closure: ident) => { ::std::cell::RefCell::new(Box::new($closure)) };
+    (@invoke [] $closure: expr; $( $arg: tt ),*) => { ($closure.borrow_mut())($( $arg ),*) };
+    //   serialized：由互斥锁串行化对【闭包】的调用
+    (@closure_bound [serialized] [$( $lifetime: lifetime )?] $( $signature: tt )*) => { impl FnMut $( $signature )* + Send $( + $lifetime )? };
+    (@closure_field [serialized] $( $signature: tt )*) => { ::std::sync::Mutex<Box<dyn FnMut $( $signature )* + Send + 'closure>> };
+    (@new_closure_field [serialized] $closure: ident) => { ::std::sync::Mutex::new(Box::new($closure)) };
+    (@invoke [serialized] $closure: expr; $( $arg: tt ),*) => {
+        ($closure.lock().unwrap_or_else(::std::sync::PoisonError::into_inner))($( $arg ),*)
+    };
+    //   concurrent：多个线程同时调用【闭包】
+    (@closure_bound [concurrent] [$( $lifetime: lifetime )?] $( $signature: tt )*) => { impl Fn $( $signature )* + Send + Sync $( + $lifetime )? };
+    (@closure_field [concurrent] $( $signature: tt )*) => { Box<dyn Fn $( $signature )* + Send + Sync + 'closure> };
+    (@new_closure_field [concurrent] $closure: ident) => { Box::new($closure) };
+    (@invoke [concurrent] $closure: expr; $( $arg: tt ),*) => { ($closure)($( $arg ),*) };
+    // 注销函数的形参：注册函数的返回值，或 void * 用户数据
+    (@unregister_type [$c_fn_ret: ty] registration) => { $c_fn_ret };
+    (@unregister_type $c_fn_ret: tt user_data) => { *mut ::std::ffi::c_void };
+    (@unregister_arg registration; $registration: expr, $user_data: expr) => { $registration };
+    (@unregister_arg user_data; $registration: expr, $user_data: expr) => { $user_data };
+    (@cancel_arg registration; $registration: expr) => { $registration };
+    (@cancel_arg user_data; $registration: expr) => {
+        compile_error!("async 形式的取消函数只能以 registration 指认任务：回调函数一经调用就释放【上下文】，其 void * 指针可能已被复用")
+    };
+    // Future 的输出值：单个回调函数实参，或多个回调函数实参组成的元组
+    (@output_type $closure_type: ty,) => { $closure_type };
+    (@output_type $( $closure_type: ty, )*) => { ($( $closure_type, )*) };
+    (@output_value $closure_arg: tt,) => { $closure_arg };
+    (@output_value $( $closure_arg: tt, )*) => { ($( $closure_arg, )*) };
+    // 错误约定：垫片函数的返回值类型、调用之前的准备与对返回值的解码
+    (@sync_ret [] [$( $c_fn_ret: ty )?]) => { ffi_closure_shim_fn!(@or_unit [$( $c_fn_ret )?]) };
+    (@sync_ret [$convention: ident] [$( $c_fn_ret: ty )?]) => {
+        Result<<error_convention!($convention) as ffi_error::ErrorConvention<ffi_closure_shim_fn!(@or_unit [$( $c_fn_ret )?])>>::Ok, ffi_error::FfiError>
+    };
+    (@register_ret [] $handle: ty) => { $handle };
+    (@register_ret [$convention: ident] $handle: ty) => { Result<$handle, ffi_error::FfiError> };
+    (@before_call [] $c_fn_ret: tt) => {};
+    (@before_call [$convention: ident] $c_fn_ret: tt) => {
+        <error_convention!($convention) as ffi_error::ErrorConvention<ffi_closure_shim_fn!(@or_unit $c_fn_ret)>>::before_call()
+    };
+    (@check [] $c_fn_name: ident $c_fn_ret: tt $call: expr) => { $call };
+    (@check [$convention: ident] $c_fn_name: ident $c_fn_ret: tt $call: expr) => {
+        <error_convention!($convention) as ffi_error::ErrorConvention<ffi_closure_shim_fn!(@or_unit $c_fn_ret)>>::check(stringify!($c_fn_name), $call)
+    };
+    (@register [] $c_fn_name: ident $c_fn_ret: tt; $registration: ident, $user_data: ident; $handle: expr) => { $handle };
+    (@register [$convention: ident] $c_fn_name: ident $c_fn_ret: tt; $registration: ident, $user_data: ident; $handle: expr) => {
+        match <error_convention!($convention) as ffi_error::ErrorConvention<ffi_closure_shim_fn!(@or_unit $c_fn_ret)>>::check(stringify!($c_fn_name), $registration) {
+            Ok(_) => Ok($handle),
+            Err(err) => {
+                drop(unsafe { Box::from_raw($user_data.cast::<Context<'_>>()) });
+                Err(err)
+            }
+        }
+    };
+    (@or_unit []) => { () };
+    (@or_unit [$c_fn_ret: ty]) => { $c_fn_ret };
+    // panic 时返回给 C 端的值。缺省为 C 端返回值类型的 Default 值
+    (@on_panic) => { Default::default() };
+    (@on_panic $sentinel: expr) => { $sentinel };
+}
+/// 被 C 端长期持有的【闭包】的 RAII 句柄，由`ffi_closure_shim_fn!`的“注册回调函数”形式返回。
+/// 被 drop 时，先向 C 端注销回调函数，再释放【闭包】。
+pub struct ClosureHandle<R> {
+    /// 注册函数的返回值，比如 C 端分配的回调函数编号
+    registration: R,
+    /// 被装箱的【上下文】
+    context: *mut c_void,
+    unregister: unsafe fn(&R, *mut c_void),
+    take_panic: unsafe fn(*mut c_void) -> Option<Box<dyn Any + Send>>
+}
+impl<R> ClosureHandle<R> {
+    /// # Safety
+    /// `context`须是`unregister`与`take_panic`所期望的【上下文】指针，且由本句柄独占。
+    pub unsafe fn new(registration: R, context: *mut c_void,
+                      unregister: unsafe fn(&R, *mut c_void),
+                      take_panic: unsafe fn(*mut c_void) -> Option<Box<dyn Any + Send>>) -> Self {
+        Self { registration, context, unregister, take_panic }
+    }
+    /// 注册函数的返回值
+    pub fn registration(&self) -> &R {
+        &self.registration
+    }
+    /// 取出被截获的【闭包】panic。C 端会在任意时刻调用回调函数，所以 panic 没法在注册函数返回后被
+    /// 自动地重新抛出，而得由调用端适时地检查。
+    pub fn take_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+        unsafe { (self.take_panic)(self.context) }
+    }
+}
+impl<R> Debug for ClosureHandle<R>
+where R: Debug {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClosureHandle").field("registration", &self.registration).field("context", &self.context).finish_non_exhaustive()
+    }
+}
+impl<R> Drop for ClosureHandle<R> {
+    fn drop(&mut self) {
+        unsafe { (self.unregister)(&self.registration, self.context) }
+    }
+}
+/// 由`ffi_closure_shim_fn!`的`async`形式返回的 Future。C 端调用回调函数时，它即完成。
+///
+/// 在回调函数被调用之前被 drop 就是取消：若声明了取消函数，则向 C 端取消；否则，仅只不再关心结果。无论哪种
+/// 情况，【上下文】都会被恰好释放一次 — 或由取消成功的取消函数，或由最终被调用的回调函数。回调函数一经调用，
+/// 即使 Future 从未被 poll 过，drop 它也不会再向 C 端取消。
+pub struct CallbackFuture<T, R> {
+    deferred: BoxFuture<'static, T>,
+    /// 异步函数的返回值，比如 C 端分配的任务编号
+    registration: R,
+    /// 被装箱的【上下文】
+    context: *mut c_void,
+    cancel: Option<unsafe fn(&R, *mut c_void)>,
+    /// 回调函数是否已被调用（即，【上下文】是否已被它释放）
+    fired: Arc<AtomicBool>
+}
+impl<T, R> CallbackFuture<T, R> {
+    /// # Safety
+    /// `context`须是`cancel`所期望的【上下文】指针，且回调函数须在释放它之前将`fired`置为`true`。
+    pub unsafe fn new(deferred: BoxFuture<'static, T>, registration: R, context: *mut c_void,
+                      cancel: Option<unsafe fn(&R, *mut c_void)>, fired: Arc<AtomicBool>) -> Self {
+        Self { deferred, registration, context, cancel, fired }
+    }
+    /// 异步函数的返回值
+    pub fn registration(&self) -> &R {
+        &self.registration
+    }
+}
+impl<T, R> Future for CallbackFuture<T, R>
+where R: Unpin {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        self.deferred.poll_unpin(cx)
+    }
+}
+impl<T, R> Debug for CallbackFuture<T, R>
+where R: Debug {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackFuture").field("registration", &self.registration).field("fired", &self.fired.load(Ordering::Acquire)).finish_non_exhaustive()
+    }
+}
+impl<T, R> Drop for CallbackFuture<T, R> {
+    fn drop(&mut self) {
+        if let (false, Some(cancel)) = (self.fired.load(Ordering::Acquire), self.cancel) {
+            unsafe { cancel(&self.registration, self.context) }
+        }
+    }
+}
+/// `ffi_closure_shim_fn!`自动封送形参时所用的辅助函数。转换失败即 panic，并在 panic 消息里带上形参名。
+mod marshal {
+    use ::std::{ffi::{c_char, CStr, CString}, slice};
+    /// rust 字符串 -> C 字符串
+    pub(crate) fn to_c_string(value: impl Into<Vec<u8>>, param: &str) -> CString {
+        CString::new(value).unwrap_or_else(|err| panic!("实参 {} 的第 {} 字节是 NUL 字符", param, err.nul_position()))
+    }
+    /// 切片长度 -> C 端的长度类型
+    pub(crate) fn to_c_len<L>(len: usize, param: &str) -> L
+    where L: TryFrom<usize> {
+        L::try_from(len).unwrap_or_else(|_| panic!("实参 {} 的长度 {} 超出了 C 端长度类型的取值范围", param, len))
+    }
+    /// C 字符串 -> rust 字符串。空指针与非 UTF-8 字符串都会 panic
+    pub(crate) unsafe fn from_c_str<'a>(ptr: *const c_char, param: &str) -> &'a str {
+        unsafe { from_nullable_c_str(ptr, param) }.unwrap_or_else(|| panic!("实参 {} 是空指针", param))
+    }
+    /// 可空的 C 字符串 -> rust 字符串。空指针对应 None
+    pub(crate) unsafe fn from_nullable_c_str<'a>(ptr: *const c_char, param: &str) -> Option<&'a str> {
+        (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap_or_else(|err| panic!("实参 {} 不是 UTF-8 字符串：{}", param, err)))
+    }
+    /// C 端的“指针 + 长度” -> 切片。空指针对应空切片
+    pub(crate) unsafe fn from_raw_parts<'a, T, L>(ptr: *const T, len: L, param: &str) -> &'a [T]
+    where L: TryInto<usize> {
+        if ptr.is_null() { &[] } else { unsafe { slice::from_raw_parts(ptr, from_c_len(len, param)) } }
+    }
+    pub(crate) unsafe fn from_raw_parts_mut<'a, T, L>(ptr: *mut T, len: L, param: &str) -> &'a mut [T]
+    where L: TryInto<usize> {
+        if ptr.is_null() { &mut [] } else { unsafe { slice::from_raw_parts_mut(ptr, from_c_len(len, param)) } }
+    }
+    fn from_c_len<L>(len: L, param: &str) -> usize
+    where L: TryInto<usize> {
+        len.try_into().unwrap_or_else(|_| panic!("实参 {} 的长度是负数或超出了 usize 的取值范围", param))
     }
 }
 fn main() {
-    use libc::c_int;
+    use ::std::{cell::RefCell, cmp::Ordering, ffi::CString, panic::{self, AssertUnwindSafe}, rc::Rc};
+    use libc::{c_char, c_int};
     ffi_closure_shim_fn!(
         add_two_numbers_by_ptr(a: *mut c_int, b: Option<&i32>,, callback(result: c_int))
     );
@@ -115,4 +820,410 @@ fn main() {
     let param_b = Some(&2);
     add_two_numbers_by_ptr(param_a, param_b, |result: c_int| got = result);
     assert_eq!(got, 1 + 2);
-}
\ No newline at end of file
+    // 多个回调函数，且 void * 用户数据位于形参列表的中间与首位
+    ffi_closure_shim_fn!(
+        parse_brackets(
+            input: *const c_char,
+            on_start: callback(user_data, depth: c_int),
+            user_data,
+            on_end: callback(depth: c_int, user_data),
+            on_error: callback(position: c_int, user_data)
+        )
+    );
+    let events = RefCell::new(Vec::new());
+    let input = CString::new("(()))").unwrap();
+    parse_brackets(input.as_ptr(),
+        |depth| events.borrow_mut().push(format!("start@{}", depth)),
+        |depth| events.borrow_mut().push(format!("end@{}", depth)),
+        |position| events.borrow_mut().push(format!("error@{}", position))
+    );
+    assert_eq!(events.into_inner(), ["start@0", "start@1", "end@1", "end@0", "error@4"]);
+    // 回调函数的返回值：Ordering -> 比较结果
+    ffi_closure_shim_fn!(
+        sort_numbers(
+            user_data,
+            numbers: *mut c_int,
+            len: usize,
+            compare: callback(user_data, a: &c_int, b: &c_int) -> Ordering as c_int {
+                Ordering::Less => -1,
+                Ordering::Equal => 0,
+                Ordering::Greater => 1
+            }
+        )
+    );
+    let mut numbers = [3, 1, 4, 1, 5, 9, 2, 6];
+    sort_numbers(numbers.as_mut_ptr(), numbers.len(), |a, b| b.cmp(a));
+    assert_eq!(numbers, [9, 6, 5, 4, 3, 2, 1, 1]);
+    // 回调函数的返回值：bool -> 继续或中止遍历
+    {
+        ffi_closure_shim_fn!(
+            visit_numbers(numbers: *const c_int, len: usize, visit: callback(number: c_int, user_data) -> bool as c_int {
+                true => 0,
+                false => 1
+            }, user_data) -> c_int
+        );
+        let mut visited = Vec::new();
+        let stopped = visit_numbers(numbers.as_ptr(), numbers.len(), |number| {
+            visited.push(number);
+            number > 4
+        });
+        assert_eq!((stopped, visited), (1, vec![9, 6, 5, 4]));
+    }
+    // 回调函数的返回值：Result -> 错误码
+    {
+        ffi_closure_shim_fn!(
+            visit_numbers(numbers: *const c_int, len: usize, visit: callback(number: c_int, user_data) -> Result<(), c_int> as c_int {
+                Ok(()) => 0,
+                Err(code) => code
+            }, user_data) -> c_int
+        );
+        let mut sum = 0;
+        let code = visit_numbers(numbers.as_ptr(), numbers.len(), |number| {
+            sum += number;
+            if sum > 20 { Err(-sum) } else { Ok(()) }
+        });
+        assert_eq!(code, -24);
+    }
+    // 回调函数的返回值：原样返回
+    {
+        ffi_closure_shim_fn!(
+            visit_numbers(numbers: *const c_int, len: usize, visit: callback(number: c_int, user_data) -> c_int, user_data) -> c_int
+        );
+        assert_eq!(visit_numbers(numbers.as_ptr(), numbers.len(), |number| if number < 5 { number } else { 0 }), 4);
+    }
+    // 【闭包】panic：C 端收到 #[on_panic = 1] 而中止循环，rust 端在 count_until_stop 返回后重新抛出 panic
+    ffi_closure_shim_fn!(
+        count_until_stop(limit: c_int, #[on_panic = 1] visit: callback(number: c_int, user_data) -> c_int, user_data) -> c_int
+    );
+    let visited = RefCell::new(Vec::new());
+    let payload = panic::catch_unwind(AssertUnwindSafe(|| count_until_stop(10, |number| {
+        visited.borrow_mut().push(number);
+        if number == 2 {
+            panic!("闭包在第 {} 次回调时 panic", number);
+        }
+        0
+    }))).unwrap_err();
+    assert_eq!(payload.downcast_ref::<String>().map(String::as_str), Some("闭包在第 2 次回调时 panic"));
+    assert_eq!(visited.into_inner(), [0, 1, 2]);
+    extern "C" {
+        fn last_call_count() -> c_int;
+    }
+    assert_eq!(unsafe { last_call_count() }, 3);
+    // 缺省 panic 时的返回值 0：C 端继续循环，但【闭包】不再被调用
+    {
+        ffi_closure_shim_fn!(
+            count_until_stop(limit: c_int, visit: callback(number: c_int, user_data) -> c_int, user_data) -> c_int
+        );
+        let visited = RefCell::new(Vec::new());
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| count_until_stop(10, |number| {
+            visited.borrow_mut().push(number);
+            if number == 2 {
+                panic!("闭包 panic");
+            }
+            0
+        }))).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"闭包 panic"));
+        assert_eq!(visited.into_inner(), [0, 1, 2]);
+        assert_eq!(unsafe { last_call_count() }, 10);
+    }
+    // 注册回调函数：C 端保存回调函数，并在日后被 emit_event() 调用
+    ffi_closure_shim_fn!(
+        register_handler(handler: callback(event: c_int, user_data), user_data) -> c_int;
+        drop => unregister_handler(registration)
+    );
+    extern "C" {
+        fn emit_event(event: c_int) -> c_int;
+    }
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let handle1 = register_handler({
+        let events = Rc::clone(&events);
+        move |event| events.borrow_mut().push(("handle1", event))
+    });
+    let mut handle2 = register_handler({
+        let events = Rc::clone(&events);
+        move |event| {
+            if event == 3 {
+                panic!("第 {} 号事件处理失败", event);
+            }
+            events.borrow_mut().push(("handle2", event));
+        }
+    });
+    assert_eq!((*handle1.registration(), *handle2.registration()), (0, 1));
+    assert_eq!(unsafe { emit_event(1) }, 2);
+    drop(handle1);
+    assert_eq!(unsafe { emit_event(2) }, 1);
+    assert_eq!(unsafe { emit_event(3) }, 1);
+    let payload = handle2.take_panic().unwrap();
+    assert_eq!(payload.downcast_ref::<String>().map(String::as_str), Some("第 3 号事件处理失败"));
+    drop(handle2);
+    assert_eq!(unsafe { emit_event(4) }, 0);
+    assert_eq!(*events.borrow(), [("handle1", 1), ("handle2", 1), ("handle2", 2)]);
+    // 注销函数也可按 void * 用户数据（即，【上下文】的地址）注销回调函数
+    {
+        ffi_closure_shim_fn!(
+            register_handler(handler: callback(event: c_int, user_data), user_data) -> c_int;
+            drop => unregister_closure(user_data)
+        );
+        let handle = register_handler({
+            let events = Rc::clone(&events);
+            move |event| events.borrow_mut().push(("handle3", event))
+        });
+        assert_eq!((unsafe { emit_event(5) }, Rc::strong_count(&events)), (1, 2));
+        drop(handle);
+        assert_eq!((unsafe { emit_event(6) }, Rc::strong_count(&events)), (0, 1));
+        assert_eq!(events.borrow().last(), Some(&("handle3", 5)));
+    }
+    // 转换为 Future：C 端工作线程延时回调
+    #[cfg(unix)]
+    {
+        use ::futures::{executor, future::{self, Either}};
+        use ::std::{thread, time::Duration};
+        use libc::c_uint;
+        // 回调函数的实参 sum 即是 Future 的输出值
+        ffi_closure_shim_fn!(
+            async add_later(a: c_int, b: c_int, delay_ms: c_uint, done: callback(sum: c_int, user_data), user_data) -> c_int;
+            cancel => cancel_add(registration)
+        );
+        extern "C" {
+            fn fired_tasks() -> c_int;
+            fn cancel_calls() -> c_int;
+            fn cancel_add(id: c_int) -> c_int;
+        }
+        let task = add_later(1, 2, 50);
+        let stale_id = *task.registration();
+        assert_eq!(executor::block_on(task), 3);
+        // 已完成任务的编号过期了：即使它的任务槽已被新任务复用，取消它也只会返回 0（未找到），而不会误取消新任务
+        thread::sleep(Duration::from_millis(10));
+        let task = add_later(2, 3, 10);
+        assert_eq!((*task.registration() % 16, *task.registration() == stale_id), (stale_id % 16, false));
+        assert_eq!(unsafe { cancel_add(stale_id) }, 0);
+        assert_eq!(executor::block_on(task), 5);
+        // 取消：先完成的 Future 胜出，另一个 Future 被 drop，从而向 C 端取消
+        let slow = add_later(3, 4, 300);
+        let fast = add_later(5, 6, 10);
+        match executor::block_on(future::select(slow, fast)) {
+            Either::Right((sum, slow)) => {
+                assert_eq!(sum, 11);
+                assert!(*slow.registration() >= 0);
+                drop(slow);
+            },
+            Either::Left(_) => unreachable!("慢任务不该先完成")
+        }
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(unsafe { fired_tasks() }, 3);
+        // 回调函数已被调用，但 Future 从未被 poll 过：drop 它不会再向 C 端取消，因为【上下文】已被回调函数释放了
+        let task = add_later(9, 10, 10);
+        thread::sleep(Duration::from_millis(100));
+        let cancels = unsafe { cancel_calls() };
+        drop(task);
+        assert_eq!(unsafe { (cancel_calls(), fired_tasks()) }, (cancels, 4));
+        // 没有取消函数：被 drop 的 Future 仅只不再关心结果。回调函数仍会被调用，并由它释放【上下文】
+        {
+            ffi_closure_shim_fn!(
+                async add_later(a: c_int, b: c_int, delay_ms: c_uint, done: callback(sum: c_int, user_data), user_data) -> c_int
+            );
+            drop(add_later(7, 8, 10));
+        }
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(unsafe { fired_tasks() }, 5);
+        // 多个“有效载荷”实参：Future 的输出值是它们组成的元组
+        {
+            ffi_closure_shim_fn!(
+                async divide_later(a: c_int, b: c_int, delay_ms: c_uint, done: callback(quotient: c_int, remainder: c_int, user_data), user_data)
+            );
+            assert_eq!(executor::block_on(divide_later(17, 5, 10)), (3, 2));
+        }
+    }
+    // 自动封送：字符串、可空的字符串与 bool
+    {
+        ffi_closure_shim_fn!(
+            greet(
+                name: &str as *const c_char,
+                title: Option<&str> as *const c_char,
+                shout: bool as c_int,
+                on_greeting: callback(text: String as *const c_char, title: Option<&str> as *const c_char, shouted: bool as c_int, user_data),
+                user_data
+            )
+        );
+        let mut greetings = Vec::new();
+        greet("Rust", None, false, |text, title, shouted| greetings.push((text, title.map(str::to_owned), shouted)));
+        greet("Ferris", Some("Dr."), true, |text, title, shouted| greetings.push((text, title.map(str::to_owned), shouted)));
+        assert_eq!(greetings, [
+            ("Hello, Rust!".to_owned(), None, false),
+            ("HELLO, DR. FERRIS!".to_owned(), Some("Dr.".to_owned()), true)
+        ]);
+        // 含 NUL 字节的字符串在调用 C 端之前就被拒绝了
+        let payload = panic::catch_unwind(|| greet("Ru\0st", None, false, |_, _, _| ())).unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().map(String::as_str), Some("实参 name 的第 2 字节是 NUL 字符"));
+    }
+    // 自动封送：被移交的 String、可空的只读指针、被借用的 &str 与可空的可写指针
+    {
+        ffi_closure_shim_fn!(
+            label_number(
+                label: String as *const c_char,
+                base: Option<&c_int> as *const c_int,
+                on_label: callback(text: &str as *const c_char, extra: Option<&mut c_int> as *mut c_int, user_data),
+                user_data
+            ) -> c_int
+        );
+        let mut texts = Vec::new();
+        assert_eq!(label_number("answer".to_owned(), Some(&40), |text, extra| {
+            texts.push(text.to_owned());
+            if let Some(extra) = extra {
+                *extra = 2;
+            }
+        }), 42);
+        assert_eq!(label_number("none".to_owned(), None, |text, extra| {
+            texts.push(text.to_owned());
+            assert!(extra.is_none());
+        }), 0);
+        assert_eq!(texts, ["answer=40", "none"]);
+    }
+    // 自动封送：切片与可空指针
+    {
+        ffi_closure_shim_fn!(
+            map_numbers(
+                input: &[c_int] as (*const c_int, usize),
+                output: &mut [c_int] as (*mut c_int, usize),
+                calls: Option<&mut c_int> as *mut c_int,
+                map: callback(number: c_int, is_last: bool as c_int, user_data) -> c_int,
+                user_data
+            )
+        );
+        let mut output = [0; 3];
+        let mut calls = 0;
+        map_numbers(&[1, 2, 3, 4], &mut output, Some(&mut calls), |number, is_last| if is_last { -number } else { number * 10 });
+        assert_eq!((output, calls), ([10, 20, -3], 3));
+        map_numbers(&[5], &mut output, None, |number, _| number);
+        assert_eq!(output, [5, 20, -3]);
+        ffi_closure_shim_fn!(
+            sliding_windows(
+                numbers: &[c_int] as (*const c_int, usize),
+                width: c_int,
+                on_window: callback(window: &[c_int] as (*const c_int, c_int), previous_sum: Option<&c_int> as *const c_int, user_data),
+                user_data
+            )
+        );
+        let mut sums = Vec::new();
+        sliding_windows(&[1, 2, 3, 4], 2, |window, previous_sum| sums.push((window.iter().sum::<c_int>(), previous_sum.copied())));
+        assert_eq!(sums, [(3, None), (5, Some(3)), (7, Some(5))]);
+        ffi_closure_shim_fn!(
+            fill_and_sum(len: c_int, fill: callback(buffer: &mut [c_int] as (*mut c_int, c_int), user_data), user_data) -> c_int
+        );
+        assert_eq!(fill_and_sum(4, |buffer| buffer.iter_mut().zip(1..).for_each(|(slot, number)| *slot = number * number)), 1 + 4 + 9 + 16);
+    }
+    // 错误约定：垫片·调用函数将 C 端的返回值与 errno 解码为 Result<T, FfiError>
+    {
+        use libc::{EDOM, EINVAL, ENOSPC, ERANGE};
+        // nonzero：【闭包】返回的错误码被 C 端原样地返回
+        ffi_closure_shim_fn!(
+            #[error_convention(nonzero)]
+            visit_numbers(
+                numbers: &[c_int] as (*const c_int, usize),
+                visit: callback(number: c_int, user_data) -> Result<(), c_int> as c_int { Ok(()) => 0, Err(code) => code },
+                user_data
+            ) -> c_int
+        );
+        assert!(visit_numbers(&[1, 2, 3], |_| Ok(())).is_ok());
+        let err = visit_numbers(&[1, 2, 3], |number| if number < 2 { Ok(()) } else { Err(ERANGE) }).unwrap_err();
+        assert_eq!((err.function(), err.raw_os_error()), ("visit_numbers", Some(ERANGE)));
+        // negative_errno：非负的返回值即是成功时的值
+        ffi_closure_shim_fn!(
+            #[error_convention(negative_errno)]
+            parse_numbers(input: &str as *const c_char, on_number: callback(number: c_int, user_data), user_data) -> c_int
+        );
+        let mut numbers = Vec::new();
+        assert_eq!(parse_numbers("4,15,0", |number| numbers.push(number)).unwrap(), 3);
+        assert_eq!(numbers, [4, 15, 0]);
+        let err = parse_numbers("4,x", |_| ()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EINVAL));
+        // errno：C 端仅以 errno 报告错误
+        ffi_closure_shim_fn!(
+            #[error_convention(errno)]
+            weighted_average(
+                numbers: &[c_int] as (*const c_int, usize),
+                weight: callback(number: c_int, user_data) -> f64,
+                user_data
+            ) -> f64
+        );
+        assert_eq!(weighted_average(&[1, 2, 3], |number| if number == 2 { 2.0 } else { 1.0 }).unwrap(), 2.0);
+        let err = weighted_average(&[1, 2, 3], |_| 0.0).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EDOM));
+        // 注册回调函数：注册失败时，【上下文】被立即释放，且不会返回句柄
+        ffi_closure_shim_fn!(
+            #[error_convention(negative_errno)]
+            register_handler(handler: callback(event: c_int, user_data), user_data) -> c_int;
+            drop => unregister_handler(registration)
+        );
+        let counter = Rc::new(RefCell::new(0));
+        let handles = (0..8).map(|_| {
+            let counter = Rc::clone(&counter);
+            register_handler(move |_| *counter.borrow_mut() += 1)
+        }).collect::<Result<Vec<_>, _>>().unwrap();
+        let err = register_handler({
+            let counter = Rc::clone(&counter);
+            move |_| *counter.borrow_mut() += 1
+        }).err().unwrap();
+        assert_eq!((err.function(), err.raw_os_error()), ("register_handler", Some(ENOSPC)));
+        assert_eq!(Rc::strong_count(&counter), 1 + 8);
+        assert_eq!(unsafe { emit_event(6) }, 8);
+        drop(handles);
+        assert_eq!((*counter.borrow(), Rc::strong_count(&counter)), (8, 1));
+    }
+    // 线程安全：C 端从多个工作线程回调
+    #[cfg(unix)]
+    {
+        use ::std::sync::{atomic::{AtomicUsize, Ordering}, Mutex};
+        use libc::EINVAL;
+        // concurrent：Fn + Send + Sync 的【闭包】被同时调用
+        {
+            ffi_closure_shim_fn!(
+                #[error_convention(nonzero)]
+                #[thread_safe(concurrent)]
+                parallel_for(count: c_int, threads: c_int, work: callback(index: c_int, user_data), user_data) -> c_int
+            );
+            let sum = AtomicUsize::new(0);
+            parallel_for(1000, 8, |index| {
+                sum.fetch_add(index as usize, Ordering::Relaxed);
+            }).unwrap();
+            assert_eq!(sum.into_inner(), 999 * 1000 / 2);
+            // panic 仍被截获，并在 parallel_for 返回后被重新抛出
+            let payload = panic::catch_unwind(|| parallel_for(100, 4, |index| assert_ne!(index, 42, "并发回调失败"))).unwrap_err();
+            assert!(payload.downcast_ref::<String>().unwrap().contains("并发回调失败"));
+            // 零个工作线程被 C 端拒绝，【闭包】一次也不会被调用
+            let calls = AtomicUsize::new(0);
+            let err = parallel_for(10, 0, |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            }).unwrap_err();
+            assert_eq!((err.function(), err.raw_os_error(), calls.into_inner()), ("parallel_for", Some(EINVAL), 0));
+        }
+        // serialized：FnMut + Send 的【闭包】被互斥锁串行化调用
+        {
+            ffi_closure_shim_fn!(
+                #[error_convention(nonzero)]
+                #[thread_safe(serialized)]
+                parallel_for(count: c_int, threads: c_int, work: callback(index: c_int, user_data), user_data) -> c_int
+            );
+            let mut indexes = Vec::new();
+            parallel_for(1000, 8, |index| indexes.push(index)).unwrap();
+            indexes.sort_unstable();
+            assert_eq!(indexes, (0..1000).collect::<Vec<_>>());
+        }
+        // 注册回调函数亦可声明线程安全模式
+        {
+            ffi_closure_shim_fn!(
+                #[thread_safe(serialized)]
+                register_handler(handler: callback(event: c_int, user_data), user_data) -> c_int;
+                drop => unregister_handler(registration)
+            );
+            let events = std::sync::Arc::new(Mutex::new(Vec::new()));
+            let handle = register_handler({
+                let events = std::sync::Arc::clone(&events);
+                move |event| events.lock().unwrap().push(event)
+            });
+            assert_eq!(unsafe { emit_event(5) }, 1);
+            drop(handle);
+            assert_eq!(*events.lock().unwrap(), [5]);
+        }
+    }
+}
@@ -1,10 +1,23 @@
-use ::std::ffi::c_int;
+#[path = "../abi_layout.rs"]
+#[macro_use]
+mod abi_layout;
+use ::std::ffi::{c_char, c_int};
+use abi_layout::AbiLayout;
+type Callback2 = extern "C" fn(c_int) -> c_int; // 对应 C 端的 Callback2 类型定义
+type Callback3 = extern "C" fn(Option<Callback2>, c_int) -> c_int; // 对应 C 端的 Callback3 类型定义
+abi_layout! {
+    /// 对应 C 端的 Registration 结构体。空指针优化令 Option<Callback3> 字段与 C 端的函数指针等宽
+    struct Registration {
+        tag: c_char,
+        cb3: Option<Callback3>,
+        a: c_int
+    }
+}
 fn main() {
-    type Callback2 = extern "C" fn(c_int) -> c_int; // 对应 C 端的 Callback2 类型定义
-    type Callback3 = extern "C" fn(Option<Callback2>, c_int) -> c_int; // 对应 C 端的 Callback3 类型定义
     // 导入 C 端的‘回调函数注册’程序接口
     extern "C" {
         fn register1(cb: Option<Callback3>, a: c_int) -> c_int;
+        fn register2(registration: *const Registration) -> c_int;
     }
     // 待传值给 C 端的 C ABI 函数
     extern "C" fn callback3(process: Option<Callback2>, a: c_int) -> c_int {
@@ -22,4 +35,14 @@ fn main() {
     println!("Rust ⮂ C 均传递‘非空函数指针’");
     let result = unsafe { register1(Some(callback3), 4) };
     assert_eq!(result, 8);
-}
\ No newline at end of file
+    println!("Rust ➜ C 传递含有‘可空函数指针’字段的结构体");
+    // 函数指针没有空值，所以 Option<Callback3> 以空指针表示 None，而不需要额外的判别式
+    assert!(abi_layout::has_niche::<Callback3>());
+    let layout = Registration::layout();
+    print!("{}", layout);
+    layout.verify().unwrap();
+    let result = unsafe { register2(&Registration { tag: b'-' as c_char, cb3: Some(callback3), a: 4 }) };
+    assert_eq!(result, -8);
+    let result = unsafe { register2(&Registration { tag: 0, cb3: None, a: 4 }) };
+    assert_eq!(result, 4);
+}
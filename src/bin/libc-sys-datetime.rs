@@ -1,17 +1,41 @@
-use ::libc::{ errno_t, time_t, tm };
-use ::std::{ error::Error, io::Error as IoError, mem::MaybeUninit, ptr };
+#[path = "../ffi_error.rs"]
+#[macro_use]
+mod ffi_error;
+use ::libc::{ time_t, tm };
+use ::std::{ error::Error, mem::MaybeUninit, ptr };
+use ffi_error::FfiError;
 
-fn main() -> Result<(), Box<dyn Error>> {
+ffi_result_fn! {
+    #[error_convention(errno)]
+    #[cfg(unix)]
+    unsafe fn time(t: *mut time_t) -> time_t;
+    #[error_convention(null)]
+    #[cfg(unix)]
+    unsafe fn localtime_r(time: *const time_t, tm: *mut tm) -> *mut tm;
+    // MSVC 的 time() 与 localtime_s() 都是头文件里的内联函数，真正被导出的是 64 位版本
+    #[link_name = "_time64"]
+    #[error_convention(errno)]
+    #[cfg(windows)]
+    unsafe fn time(t: *mut time_t) -> time_t;
+    #[link_name = "_localtime64_s"]
+    #[error_convention(nonzero)]
+    #[cfg(windows)]
+    unsafe fn localtime_s(tm: *mut tm, time: *const time_t) -> ::libc::errno_t;
+}
+/// 本地时区的当前时间。不再需要手工检查 errno_t 或调用 IoError::last_os_error()
+fn local_now() -> Result<tm, FfiError> {
     let mut tm: MaybeUninit<tm> = MaybeUninit::uninit();
-    let tm = unsafe {
-        let t: time_t = libc::time(ptr::null_mut());
-        let err: errno_t = libc::localtime_s(tm.as_mut_ptr(), &t);
-        dbg!(err);
-        if err != 0 {
-            return Err(Box::new(IoError::last_os_error()));
-        }
-        tm.assume_init()
-    };
+    unsafe {
+        let t: time_t = time(ptr::null_mut())?;
+        #[cfg(windows)]
+        localtime_s(tm.as_mut_ptr(), &t)?;
+        #[cfg(unix)]
+        localtime_r(&t, tm.as_mut_ptr())?;
+        Ok(tm.assume_init())
+    }
+}
+fn main() -> Result<(), Box<dyn Error>> {
+    let tm = local_now()?;
     println!("当前时间: {}年{}月{}日 {}时{}分{}秒", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec);
     Ok(())
-}
\ No newline at end of file
+}
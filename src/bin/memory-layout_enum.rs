@@ -1,8 +1,18 @@
+#[path = "../abi_layout.rs"]
+#[macro_use]
+mod abi_layout;
+use abi_layout::AbiLayout;
 fn main() {
     use ::std::mem;
     enum Example1 { _A, _B }
     println!("Example1: alignment = {1}; size = {0}", mem::size_of::<Example1>(), mem::align_of::<Example1>());
-    #[repr(C)]
-    enum Example2 { _A }
-    println!("Example2: alignment = {1}; size = {0}", mem::size_of::<Example2>(), mem::align_of::<Example2>());
-}
\ No newline at end of file
+    // 两个变体只用了 u8 的两个取值，余下的取值都是 niche。所以，Option<Example1> 仍只占一个字节
+    assert!(abi_layout::has_niche::<Example1>());
+    // 须与 native/memory_layout.h 里的 Example2 布局相同
+    abi_layout! {
+        enum Example2 { _A }
+    }
+    let layout = Example2::layout();
+    print!("{}", layout);
+    layout.verify().unwrap();
+}
@@ -1,111 +1,571 @@
-/**
- * 该模块是本例程对 N-API bindings 的 Stub，因为 nj_sys 并没有在
- * playground.org 的 top 100 依赖包清单里。
- * 被模拟的 N-API 外部函数接口包括：
- * (1) napi_value 代表 JS VM 堆内存里的一个对象
- * (2) napi_ref   代表指向 napi_value 值的引用计数指针
- * (3) napi_create_reference() 函数从 napi_value 构造 napi_ref
- * (4) napi_delete_reference() 函数析构掉 napi_ref
- */
-#[allow(dead_code, non_camel_case_types)]
-mod nj_sys {
-    #[derive(Copy, Clone, Debug)]
-    pub struct napi_value;
-    #[derive(Copy, Clone, Debug)]
-    pub struct napi_ref {
-        value: napi_value,
-        ref_count: i8
-    }
-    pub fn napi_create_reference(value: napi_value, ref_count: i8) -> napi_ref {
-        napi_ref {value, ref_count}
-    }
-    pub fn napi_delete_reference(reference: napi_ref) {
-        println!("[napi_delete_reference]{:#?}", reference);
-    }
-}
-/**
- * “二段式”引用计数中 Rc<T> 的包装类。根据 Newtypes 设计模式，该包装
- * 类是 Rc<T> 的智能指针 — 智能指针的智能指针。
- * 该包装类同时完成了两个功能：
- * (1) 接管对【引用个数】的跟踪功能。即，克隆智能指针时，引用个数加1；
- *     析构智能指针时，引用个数减一。
- * (2) 在【引用个数】归零时，通知 N-API 清空 JS 堆内存中的引用计数，和
- *     敦促 JS VM 的 GC 回收 JS 堆对象。
- */
-mod napi_rc {
-    use ::std::{rc::Rc, ops::{Deref, DerefMut}};
-    use crate::nj_sys::{napi_create_reference, napi_delete_reference, napi_ref, napi_value};
-    #[derive(Clone, Debug)]
-    pub struct NapiRc(Rc<napi_ref>);
-    // 构造函数
-    impl NapiRc {
-        pub fn new(value: napi_value) -> Self {
-            NapiRc(Rc::new(napi_create_reference(value, 1)))
-        }
-    }
-    // 智能指针标配
-    impl Deref for NapiRc {
-        type Target = Rc<napi_ref>;
-        fn deref(&self) -> &Self::Target {
-            &self.0
-        }
-    }
-    impl DerefMut for NapiRc {
-        fn deref_mut(&mut self) -> &mut Self::Target {
-            &mut self.0
-        }
-    }
-    // 跟踪【引用数量】。当引用计数归零时，就调用 N-API 的外部函数接口
-    // 以清空 JS 堆内存中的引用计数。
-    impl Drop for NapiRc {
-        fn drop(&mut self) {
-            let count = Rc::strong_count(self) - 1;
-            if count > 0 {
-                #[cfg(debug_assertions)]
-                println!("[NapiRc::drop]还有{}个引用，尚不能清空 N-API 端的引用计数", count);
-            } else {
-                Rc::get_mut(&mut self.0).map(|napi_ref| {
-                    #[cfg(debug_assertions)]
-                    println!("[NapiRc::drop]引用计数归零了，级联清空 N-API 端的引用计数");
-                    napi_delete_reference(*napi_ref);
-                });
-            }
-        }
-    }
-}
-#[cfg(debug_assertions)]
-use ::std::rc::Rc;
-use napi_rc::NapiRc;
-use nj_sys::napi_value;
-// 处理复杂业务逻辑的主体代码。
-pub fn napi_export_method(napi_value: napi_value) {
-    let w = NapiRc::new(napi_value);
-    #[cfg(debug_assertions)]
-    eprintln!("[main]初始引用计数：{}", Rc::strong_count(&w));
-    // 经由复杂的业务处理的功能实现
-    {
-        let w = w.clone();
-        {
-            let w = w.clone();
-            print(2, w);
-        } // 还有两个引用计数
-        print(1, w);
-    } // 还有一个引用计数
-    {
-        let w = w.clone();
-        print(3, w);
-    } // 还有一个引用计数
-    print(4, w);
-    fn print(index: u8, rc: NapiRc) {
-        println!("[print]复本{}：{:#?}", index, rc);
-    }
-} // 引用计数归零
-fn main() {
-    println!("===== 开始 =====");
-    // 模拟从 FFI 获取到 napi_value 值。
-    let napi_value = napi_value {};
-    // 执行复杂业务处理逻辑
-    napi_export_method(napi_value);
-    // 再做些其它的工作...
-    println!("===== 结束 =====");
-}
\ No newline at end of file
+#[path = "../napi-ref/nj_sys.rs"]
+mod nj_sys;
+#[path = "../napi-ref/napi_arc.rs"]
+mod napi_arc;
+#[path = "../napi-ref/scope.rs"]
+mod scope;
+#[path = "../napi-ref/wrap.rs"]
+mod wrap;
+#[path = "../napi-ref/napi_serde.rs"]
+mod napi_serde;
+#[path = "../napi-ref/promise.rs"]
+mod promise;
+#[path = "../napi-ref/tracker.rs"]
+mod tracker;
+/**
+ * “二段式”引用计数中 Rc<T> 的包装类。根据 Newtypes 设计模式，该包装
+ * 类是 Rc<T> 的智能指针 — 智能指针的智能指针。
+ * 该包装类同时完成了两个功能：
+ * (1) 接管对【引用个数】的跟踪功能。即，克隆智能指针时，引用个数加1；
+ *     析构智能指针时，引用个数减一。
+ * (2) 在【引用个数】归零时，通知 N-API 将引用计数减为零（即，降级为弱引用），和
+ *     敦促 JS VM 的 GC 回收 JS 堆对象。
+ * 弱引用 NapiWeak 与 NapiRc 共享同一个 napi_ref：
+ * (1) NapiWeak 不计入【引用个数】，所以不阻止 GC 回收 JS 堆对象。
+ * (2) NapiWeak::upgrade() 在 JS 堆对象被回收之前，可将 N-API 端的引用计数从零重新加到一（即，复活强引用）；
+ *     在其被回收之后，则返回 None。
+ * (3) 待 NapiRc 与 NapiWeak 都被析构之后，才调用 napi_delete_reference() 析构 napi_ref。
+ * 启用 tracker 登记簿之后，共享的 napi_ref 被登记，且 NapiRc 与 NapiWeak 的个数被跟踪。
+ */
+mod napi_rc {
+    use ::std::{cell::Cell, fmt::{Debug, Formatter, Result as FmtResult}, ops::Deref, rc::Rc};
+    use crate::{nj_sys::{napi_create_reference, napi_delete_reference, napi_get_reference_value, napi_ref, napi_reference_ref, napi_reference_unref, napi_value},
+        tracker::Tracked};
+    /// NapiRc 与 NapiWeak 共享的 napi_ref
+    struct Shared {
+        reference: napi_ref,
+        /// 【引用个数】，即 NapiRc 的个数
+        strong: Cell<usize>,
+        tracked: Tracked
+    }
+    impl Shared {
+        #[track_caller]
+        fn new(value: napi_value, strong: usize) -> Rc<Self> {
+            let reference = napi_create_reference(value, strong as u32);
+            Rc::new(Shared { reference, strong: Cell::new(strong), tracked: Tracked::new("NapiRc", &reference) })
+        }
+    }
+    impl Drop for Shared {
+        fn drop(&mut self) {
+            #[cfg(debug_assertions)]
+            println!("[NapiRc::drop]强、弱引用都没有了，级联析构 N-API 端的引用");
+            napi_delete_reference(self.reference);
+        }
+    }
+    pub(crate) struct NapiRc(Rc<Shared>);
+    pub(crate) struct NapiWeak(Rc<Shared>);
+    // 构造函数
+    impl NapiRc {
+        #[track_caller]
+        pub(crate) fn new(value: napi_value) -> Self {
+            NapiRc(Shared::new(value, 1))
+        }
+        /// 强引用保证 JS 堆对象不会被回收
+        pub(crate) fn value(&self) -> napi_value {
+            napi_get_reference_value(self.0.reference).expect("被强引用的 JS 堆对象不应被回收")
+        }
+        pub(crate) fn downgrade(this: &Self) -> NapiWeak {
+            this.0.tracked.cloned();
+            NapiWeak(Rc::clone(&this.0))
+        }
+        pub(crate) fn strong_count(this: &Self) -> usize {
+            this.0.strong.get()
+        }
+        pub(crate) fn weak_count(this: &Self) -> usize {
+            Rc::strong_count(&this.0) - this.0.strong.get()
+        }
+    }
+    impl NapiWeak {
+        /// 构造仅被弱引用的 napi_ref
+        #[track_caller]
+        pub(crate) fn new(value: napi_value) -> Self {
+            NapiWeak(Shared::new(value, 0))
+        }
+        /// 若 JS 堆对象已被回收，则返回 None
+        pub(crate) fn upgrade(&self) -> Option<NapiRc> {
+            let shared = &self.0;
+            if shared.strong.get() == 0 {
+                napi_get_reference_value(shared.reference)?;
+                let ref_count = napi_reference_ref(shared.reference);
+                debug_assert_eq!(ref_count, 1);
+                #[cfg(debug_assertions)]
+                println!("[NapiWeak::upgrade]弱引用被升级，N-API 端的引用计数恢复为 1");
+            }
+            shared.strong.set(shared.strong.get() + 1);
+            shared.tracked.cloned();
+            Some(NapiRc(Rc::clone(shared)))
+        }
+        pub(crate) fn strong_count(&self) -> usize {
+            self.0.strong.get()
+        }
+    }
+    impl Clone for NapiRc {
+        fn clone(&self) -> Self {
+            self.0.strong.set(self.0.strong.get() + 1);
+            self.0.tracked.cloned();
+            NapiRc(Rc::clone(&self.0))
+        }
+    }
+    impl Clone for NapiWeak {
+        fn clone(&self) -> Self {
+            self.0.tracked.cloned();
+            NapiWeak(Rc::clone(&self.0))
+        }
+    }
+    // 智能指针标配
+    impl Deref for NapiRc {
+        type Target = napi_ref;
+        fn deref(&self) -> &Self::Target {
+            &self.0.reference
+        }
+    }
+    impl Debug for NapiRc {
+        fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+            f.debug_struct("NapiRc").field("reference", &self.0.reference).field("strong", &self.0.strong.get()).finish()
+        }
+    }
+    impl Debug for NapiWeak {
+        fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+            f.debug_struct("NapiWeak").field("reference", &self.0.reference).field("strong", &self.0.strong.get()).finish()
+        }
+    }
+    // 跟踪【引用数量】。当引用计数归零时，就调用 N-API 的外部函数接口
+    // 将 JS 堆内存中的引用计数减为零，令 JS 堆对象可被回收。
+    impl Drop for NapiRc {
+        fn drop(&mut self) {
+            self.0.tracked.released();
+            let count = self.0.strong.get() - 1;
+            self.0.strong.set(count);
+            if count > 0 {
+                #[cfg(debug_assertions)]
+                println!("[NapiRc::drop]还有{}个引用，尚不能清空 N-API 端的引用计数", count);
+            } else {
+                #[cfg(debug_assertions)]
+                println!("[NapiRc::drop]引用计数归零了，级联清空 N-API 端的引用计数");
+                napi_reference_unref(self.0.reference);
+            }
+        }
+    }
+    impl Drop for NapiWeak {
+        fn drop(&mut self) {
+            self.0.tracked.released();
+        }
+    }
+}
+use ::std::panic;
+use napi_arc::NapiArc;
+use napi_rc::{NapiRc, NapiWeak};
+use nj_sys::napi_value;
+use scope::{EscapableHandleScope, HandleScope, Local, Scope};
+use wrap::{NapiBox, WrapError};
+// 处理复杂业务逻辑的主体代码。
+pub fn napi_export_method(napi_value: napi_value) {
+    let w = NapiRc::new(napi_value);
+    assert_eq!((NapiRc::strong_count(&w), nj_sys::reference_count(napi_value)), (1, 1));
+    // 经由复杂的业务处理的功能实现
+    {
+        let w = w.clone();
+        {
+            let w = w.clone();
+            print(2, w);
+        } // 还有两个引用计数
+        assert_eq!(NapiRc::strong_count(&w), 2);
+        print(1, w);
+    } // 还有一个引用计数
+    {
+        let w = w.clone();
+        print(3, w);
+    } // 还有一个引用计数
+    // 无论被克隆多少次，N-API 端的引用计数都是 1，且仅有一个 napi_ref
+    assert_eq!((NapiRc::strong_count(&w), nj_sys::reference_count(napi_value)), (1, 1));
+    assert_eq!(nj_sys::live_references().len(), 1);
+    print(4, w);
+    fn print(index: u8, rc: NapiRc) {
+        println!("[print]复本{}：{:?}", index, rc);
+    }
+} // 引用计数归零
+// 弱引用：不阻止 GC 回收 JS 堆对象，且可在 JS 堆对象被回收之前被升级为强引用
+fn napi_export_weak_method(napi_value: napi_value) {
+    let strong = NapiRc::new(napi_value);
+    let weak = NapiRc::downgrade(&strong);
+    assert_eq!((NapiRc::strong_count(&strong), NapiRc::weak_count(&strong)), (1, 1));
+    // 强引用阻止 GC 回收 JS 堆对象
+    assert_eq!(nj_sys::gc(), 0);
+    drop(strong);
+    assert_eq!((weak.strong_count(), nj_sys::reference_count(napi_value)), (0, 0));
+    // GC 之前，弱引用仍可被升级
+    let revived = weak.upgrade().expect("JS 堆对象尚未被回收");
+    assert!(nj_sys::napi_strict_equals(revived.value(), napi_value));
+    assert_eq!(nj_sys::reference_count(napi_value), 1);
+    assert_eq!(nj_sys::gc(), 0);
+    drop(revived);
+    // GC 之后，弱引用的升级失败
+    assert_eq!(nj_sys::gc(), 1);
+    assert!(!nj_sys::is_alive(napi_value));
+    assert!(weak.upgrade().is_none());
+    // 仅被弱引用的 napi_ref
+    let object = nj_sys::napi_create_object();
+    let weak = NapiWeak::new(object);
+    let strong = weak.upgrade().unwrap();
+    assert_eq!((NapiRc::strong_count(&strong), NapiRc::weak_count(&strong)), (1, 1));
+    drop(weak);
+    assert_eq!(nj_sys::gc(), 0);
+} // 析构 napi_ref
+// 跨线程的引用：工作线程持有 JS 堆对象，而 napi_ref 总在 JS 线程上被析构
+fn napi_export_threaded_method(napi_value: napi_value) {
+    use ::std::{sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread};
+    // 1. 工作线程将使用 JS 堆对象的闭包排入 JS 线程的事件队列
+    let shared = NapiArc::new(napi_value);
+    let visits = Arc::new(AtomicUsize::new(0));
+    let workers = (0..4).map(|_| {
+        let shared = shared.clone();
+        let visits = Arc::clone(&visits);
+        thread::spawn(move || {
+            // 工作线程取不出 napi_value
+            assert_eq!(shared.value(), None);
+            shared.call(move |value| {
+                assert!(nj_sys::is_alive(value));
+                visits.fetch_add(1, Ordering::Relaxed);
+            });
+        })
+    }).collect::<Vec<_>>();
+    workers.into_iter().for_each(|worker| worker.join().unwrap());
+    // 被排队的闭包持有 NapiArc 的克隆，所以 JS 堆对象一直存活
+    assert_eq!(NapiArc::strong_count(&shared), 1 + 4);
+    drop(shared);
+    assert_eq!(nj_sys::gc(), 0);
+    assert_eq!(visits.load(Ordering::Relaxed), 0);
+    // 最后一个克隆随最后一个闭包在 JS 线程上被析构，所以 napi_ref 被立即析构
+    assert_eq!(nj_sys::run_event_loop(), 4);
+    assert_eq!(visits.load(Ordering::Relaxed), 4);
+    nj_sys::assert_no_leaks();
+    assert_eq!(nj_sys::gc(), 1);
+    // 2. 最后一个克隆在工作线程上被析构：析构 napi_ref 的调用被排入 JS 线程的事件队列
+    let object = nj_sys::napi_create_object();
+    let shared = NapiArc::new(object);
+    let worker = {
+        let shared = shared.clone();
+        thread::spawn(move || println!("[worker]{:?}", shared))
+    };
+    drop(shared);
+    worker.join().unwrap();
+    assert_eq!(nj_sys::reference_count(object), 1);
+    assert_eq!(nj_sys::run_event_loop(), 1);
+    nj_sys::assert_no_leaks();
+}
+// 句柄作用域：及时地释放循环里创建的临时值，并经由逃逸将值返回给外层的句柄作用域
+fn napi_export_scoped_method() {
+    let scope = HandleScope::open();
+    let kept = scope.create_object();
+    // 1. 每次迭代的临时值随内层句柄作用域的关闭而变为可被回收的
+    for _ in 0..100 {
+        let inner = HandleScope::nested(&scope);
+        let temporary = inner.create_object();
+        assert!(!temporary.strict_equals(kept));
+    }
+    assert_eq!(nj_sys::gc(), 100);
+    // 2. 逃逸的值被外层的句柄作用域扎根，而其它临时值被回收
+    let escaped = make_object(&scope);
+    assert_eq!(nj_sys::gc(), 1);
+    assert!(nj_sys::is_alive(escaped.raw()) && nj_sys::is_alive(kept.raw()));
+    fn make_object<'p>(parent: &'p impl Scope) -> Local<'p> {
+        let inner = EscapableHandleScope::nested(parent);
+        inner.create_object();
+        let result = inner.create_object();
+        inner.escape(result)
+    }
+    // 3. 句柄随句柄作用域的关闭而失效。编译器拒绝在守卫被析构之后再使用 Local，但原始的 napi_value 不受编译器检查
+    let raw = {
+        let inner = HandleScope::nested(&scope);
+        inner.create_object().raw()
+    };
+    let closed = panic::catch_unwind(|| nj_sys::napi_create_reference(raw, 1)).unwrap_err();
+    assert!(closed.downcast_ref::<String>().unwrap().contains("所属的句柄作用域已被关闭"));
+    // 4. 仅能逃逸一次
+    let escaped_twice = panic::catch_unwind(|| {
+        let inner = EscapableHandleScope::nested(&scope);
+        let object = inner.create_object();
+        inner.escape(object);
+        inner.escape(object);
+    }).unwrap_err();
+    assert!(escaped_twice.downcast_ref::<String>().unwrap().contains("已逃逸过一次了"));
+    // 5. 新值只能被创建于最内层的句柄作用域
+    let not_innermost = panic::catch_unwind(|| {
+        let _inner = HandleScope::nested(&scope);
+        scope.create_object();
+    }).unwrap_err();
+    assert!(not_innermost.downcast_ref::<&str>().unwrap().contains("最内层的句柄作用域"));
+    // 仅 3. 里的临时值可被回收，因为 4. 里第一次逃逸的值仍被外层的句柄作用域扎根
+    assert_eq!(nj_sys::gc(), 1);
+    drop(scope);
+    nj_sys::assert_no_leaks();
+} // 最外层的句柄作用域被关闭
+// 包装：JS 堆对象拥有 Rust 对象，且 Rust 对象在 GC 的终结阶段被析构
+fn napi_export_wrap_method() {
+    use ::std::{cell::Cell, rc::Rc};
+    #[derive(Debug)]
+    struct Counter {
+        hits: u32,
+        dropped: Rc<Cell<bool>>
+    }
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            self.dropped.set(true);
+        }
+    }
+    // 1. 借用规则在运行时被检查，且经由同一个 JS 堆对象的不同 NapiBox 共享同一个借用标记
+    let object = nj_sys::napi_create_object();
+    let dropped = Rc::new(Cell::new(false));
+    let counter = NapiBox::new(object, Counter { hits: 0, dropped: Rc::clone(&dropped) }).unwrap();
+    counter.borrow_mut().hits += 1;
+    let again = NapiBox::<Counter>::from_value(counter.value()).unwrap();
+    {
+        let (first, second) = (counter.borrow(), again.borrow());
+        assert_eq!((first.hits, second.hits), (1, 1));
+        assert!(counter.try_borrow_mut().is_err());
+    }
+    {
+        let _exclusive = again.borrow_mut();
+        assert!(counter.try_borrow().is_err());
+        println!("[napi_export_wrap_method]{:?}", counter);
+    }
+    // 2. 取回时检查类型
+    assert_eq!(NapiBox::new(object, 0_u8).unwrap_err(), WrapError::AlreadyWrapped);
+    assert!(matches!(NapiBox::<String>::from_value(object), Err(WrapError::TypeMismatch { .. })));
+    assert_eq!(NapiBox::<Counter>::from_value(nj_sys::napi_create_object()).unwrap_err(), WrapError::NotWrapped);
+    // 3. NapiBox 令 JS 堆对象存活。全部 NapiBox 被析构之后，JS 堆对象被回收，Rust 对象才被析构
+    assert_eq!(nj_sys::gc(), 1);
+    drop(counter);
+    assert_eq!(nj_sys::gc(), 0);
+    drop(again);
+    assert!(!dropped.get());
+    assert_eq!(nj_sys::gc(), 1);
+    assert!(dropped.get());
+    // 4. 被包装的 Rust 对象持有另一个 JS 堆对象：终结器析构 NapiRc 之后，下一次 GC 才回收被持有的 JS 堆对象
+    struct Node {
+        child: NapiRc
+    }
+    let (parent, child) = (nj_sys::napi_create_object(), nj_sys::napi_create_object());
+    let node = NapiBox::new(parent, Node { child: NapiRc::new(child) }).unwrap();
+    assert!(nj_sys::napi_strict_equals(node.borrow().child.value(), child));
+    assert_eq!(NapiRc::strong_count(node.rc()), 1);
+    drop(node);
+    assert_eq!(nj_sys::gc(), 1);
+    assert!(nj_sys::is_alive(child) && nj_sys::reference_count(child) == 0);
+    assert_eq!(nj_sys::gc(), 1);
+    nj_sys::assert_no_leaks();
+}
+// serde：任何实现了 Serialize 与 Deserialize 的 Rust 类型都可经由 napi_value 往返
+fn napi_export_serde_method() {
+    use ::serde::{Deserialize, Serialize};
+    use ::std::collections::BTreeMap;
+    use nj_sys::napi_valuetype;
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i8, i8),
+        Rect { width: u32, height: u32 }
+    }
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        count: u16,
+        tags: Vec<String>,
+        shape: Shape,
+        note: Option<String>
+    }
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        serial: i64,
+        total: f64,
+        paid: bool,
+        items: Vec<Item>,
+        extras: BTreeMap<u32, char>,
+        nothing: ()
+    }
+    // 句柄作用域扎根被创建的全部值
+    let scope = HandleScope::open();
+    let order = Order {
+        id: 42,
+        serial: i64::MIN,
+        total: 99.5,
+        paid: false,
+        items: vec![
+            Item { name: "铅笔".to_string(), count: 3, tags: vec!["文具".to_string()], shape: Shape::Line(-1, 1), note: None },
+            Item { name: "橡皮".to_string(), count: 1, tags: Vec::new(), shape: Shape::Rect { width: 2, height: 1 }, note: Some("白色".to_string()) },
+            Item { name: "圆规".to_string(), count: 1, tags: Vec::new(), shape: Shape::Circle(0.5), note: None },
+            Item { name: "图钉".to_string(), count: 100, tags: Vec::new(), shape: Shape::Point, note: None }
+        ],
+        extras: [(7, '赠')].into_iter().collect(),
+        nothing: ()
+    };
+    let value = napi_serde::to_value(&order).unwrap();
+    // 1. JS 端看到的值
+    let property = |object, name| nj_sys::napi_get_named_property(object, name).unwrap();
+    assert_eq!(nj_sys::napi_typeof(property(value, "id")), napi_valuetype::napi_number);
+    assert_eq!(nj_sys::napi_get_value_bigint_int64(property(value, "serial")), Ok((i64::MIN, true)));
+    assert_eq!(nj_sys::napi_typeof(property(value, "nothing")), napi_valuetype::napi_null);
+    assert_eq!(nj_sys::napi_get_value_string_utf8(property(property(value, "extras"), "7")).as_deref(), Ok("赠"));
+    let items = property(value, "items");
+    assert!(nj_sys::napi_is_array(items) && nj_sys::napi_get_array_length(items) == Ok(4));
+    let shape = property(nj_sys::napi_get_element(items, 1).unwrap(), "shape");
+    assert_eq!(nj_sys::napi_get_value_double(property(property(shape, "Rect"), "width")), Ok(2.0));
+    // 2. 往返
+    assert_eq!(napi_serde::from_value::<Order>(value).unwrap(), order);
+    // 3. 类型不符的错误被标注了路径
+    let second = nj_sys::napi_get_element(items, 1).unwrap();
+    nj_sys::napi_set_named_property(second, "count", nj_sys::napi_create_string_utf8("很多")).unwrap();
+    let err = napi_serde::from_value::<Order>(value).unwrap_err();
+    assert_eq!(err.path(), "$.items[1].count");
+    assert!(err.message().starts_with("invalid type: string \"很多\""), "{}", err);
+    // 超出目标类型的范围
+    nj_sys::napi_set_named_property(second, "count", nj_sys::napi_create_double(70000.0)).unwrap();
+    let err = napi_serde::from_value::<Order>(value).unwrap_err();
+    assert_eq!(err.path(), "$.items[1].count");
+    assert!(err.message().contains("70000"), "{}", err);
+    nj_sys::napi_set_named_property(second, "count", nj_sys::napi_create_double(1.0)).unwrap();
+    nj_sys::napi_set_named_property(shape, "Rect", nj_sys::napi_create_array()).unwrap();
+    let err = napi_serde::from_value::<Order>(value).unwrap_err();
+    assert_eq!(err.path(), "$.items[1].shape.Rect");
+    println!("[napi_export_serde_method]{}", err);
+    // 4. 序列化的错误也被标注了路径
+    #[derive(Serialize)]
+    struct Lookup {
+        table: BTreeMap<(u8, u8), u8>
+    }
+    let err = napi_serde::to_value(&Lookup { table: [((1, 2), 3)].into_iter().collect() }).unwrap_err();
+    assert_eq!(err.to_string(), "$.table: JS 对象的属性名只能是字符串、字符或整数");
+    drop(scope);
+    assert!(nj_sys::gc() > 0);
+    nj_sys::assert_no_leaks();
+}
+// Promise：Future 在工作线程上被执行，而 Promise 在 JS 线程上被兑现
+fn napi_export_async_method() {
+    use ::futures::channel::oneshot;
+    use ::std::{thread, time::Duration};
+    // 1. 以 serde 转换的结果兑现或拒绝 Promise
+    let (sender, receiver) = oneshot::channel::<Vec<u32>>();
+    let sum = promise::spawn_serde_promise(async move {
+        let numbers = receiver.await.map_err(|_| "发送端被丢弃了")?;
+        Ok::<_, &str>(numbers.iter().sum::<u32>())
+    });
+    let failed = promise::spawn_serde_promise(async { Err::<(), _>("磁盘已满") });
+    assert!(nj_sys::napi_is_promise(sum) && nj_sys::promise_result(sum).is_none());
+    // 被兑现之前，napi_deferred 扎根 Promise
+    assert_eq!(nj_sys::gc(), 0);
+    // 事件循环等待仍在工作线程上执行的 Future
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        sender.send(vec![1, 2, 3]).unwrap();
+    });
+    assert_eq!(nj_sys::run_event_loop(), 2);
+    let resolved = nj_sys::promise_result(sum).unwrap().unwrap();
+    assert_eq!(napi_serde::from_value::<u32>(resolved), Ok(6));
+    let rejected = nj_sys::promise_result(failed).unwrap().unwrap_err();
+    assert_eq!(nj_sys::napi_get_value_string_utf8(rejected).as_deref(), Ok("磁盘已满"));
+    // 两个 Promise 与它们的结果
+    assert_eq!(nj_sys::gc(), 4);
+    // 2. complete 闭包捕获的 NapiRc 令 JS 堆对象一直存活，直至 Promise 被兑现
+    let target = nj_sys::napi_create_object();
+    let captured = NapiRc::new(target);
+    let promise = promise::spawn_promise(async {
+        thread::sleep(Duration::from_millis(10));
+        42
+    }, move |answer| {
+        let target = captured.value();
+        nj_sys::napi_set_named_property(target, "answer", nj_sys::napi_create_double(answer.into())).unwrap();
+        Ok(target)
+    });
+    assert_eq!(nj_sys::gc(), 0);
+    assert_eq!(nj_sys::reference_count(target), 1);
+    assert_eq!(nj_sys::run_event_loop(), 1);
+    let resolved = nj_sys::promise_result(promise).unwrap().unwrap();
+    assert!(nj_sys::napi_strict_equals(resolved, target));
+    assert_eq!(napi_serde::from_value::<u32>(nj_sys::napi_get_named_property(resolved, "answer").unwrap()), Ok(42));
+    // complete 闭包随之被析构
+    assert_eq!(nj_sys::reference_count(target), 0);
+    // 3. Future 的 panic 拒绝 Promise
+    let panicked = promise::spawn_promise(async { panic!("工作线程上的 panic") }, |()| unreachable!());
+    assert_eq!(nj_sys::run_event_loop(), 1);
+    let rejected = nj_sys::promise_result(panicked).unwrap().unwrap_err();
+    assert_eq!(nj_sys::napi_get_value_string_utf8(rejected).as_deref(), Ok("异步任务 panic 了"));
+    nj_sys::assert_no_leaks();
+}
+// 登记簿：找出被遗忘于长寿结构体里的克隆，及其 napi_ref 的创建位置
+fn track_live_references() {
+    struct Cache {
+        entries: Vec<NapiRc>
+    }
+    let object = nj_sys::napi_create_object();
+    let (leaked, line) = (NapiRc::new(object), line!());
+    let cache = Cache { entries: vec![leaked.clone(), leaked.clone()] };
+    drop(leaked);
+    let live = tracker::live();
+    assert!(matches!(live.as_slice(), [entry] if entry.kind == "NapiRc" && entry.clones == 2 && entry.location.line() == line));
+    let report = panic::catch_unwind(tracker::assert_no_live_refs).unwrap_err();
+    assert!(report.downcast_ref::<String>().unwrap().contains(&format!("{}:{}", file!(), line)));
+    assert_eq!(tracker::dump(), 1);
+    assert_eq!(cache.entries.len(), 2);
+    drop(cache);
+    tracker::assert_no_live_refs();
+    assert_eq!(nj_sys::gc(), 1);
+}
+// 模拟 VM 能发现不平衡的引用计数
+fn detect_unbalanced_references() {
+    // 泄漏：被 mem::forget() 的 NapiRc 永远不会析构它的 napi_ref，所以 JS 堆对象也永远不会被回收
+    let object = nj_sys::napi_create_object();
+    ::std::mem::forget(NapiRc::new(object));
+    assert_eq!(nj_sys::gc(), 0);
+    let live = nj_sys::live_references();
+    assert!(matches!(live.as_slice(), [(_, value, 1)] if *value == object));
+    let leak = panic::catch_unwind(nj_sys::assert_no_leaks).unwrap_err();
+    assert!(leak.downcast_ref::<String>().unwrap().starts_with("泄漏了 1 个 napi_ref"));
+    // 重复析构
+    let (reference, ..) = live[0];
+    nj_sys::napi_delete_reference(reference);
+    let double_free = panic::catch_unwind(|| nj_sys::napi_delete_reference(reference)).unwrap_err();
+    assert!(double_free.downcast_ref::<String>().unwrap().contains("已被析构"));
+    // 引用计数减至负数
+    let reference = nj_sys::napi_create_reference(object, 0);
+    let underflow = panic::catch_unwind(|| nj_sys::napi_reference_unref(reference)).unwrap_err();
+    assert!(underflow.downcast_ref::<String>().unwrap().contains("引用计数已是零"));
+    nj_sys::napi_delete_reference(reference);
+    // 引用已被回收的 JS 堆对象
+    assert_eq!(nj_sys::gc(), 1);
+    let collected = panic::catch_unwind(|| nj_sys::napi_create_reference(object, 1)).unwrap_err();
+    assert!(collected.downcast_ref::<String>().unwrap().contains("已被 GC 回收"));
+}
+fn main() {
+    println!("===== 开始 =====");
+    // 登记此后被创建的 napi_ref。main() 返回时，守卫打印仍存活的 napi_ref
+    let _tracker = tracker::enable();
+    // 模拟从 FFI 获取到 napi_value 值。
+    let napi_value = nj_sys::napi_create_object();
+    // 执行复杂业务处理逻辑
+    napi_export_method(napi_value);
+    // 引用计数归零之后，napi_ref 被析构，JS 堆对象可被回收
+    nj_sys::assert_no_leaks();
+    assert_eq!(nj_sys::gc(), 1);
+    napi_export_weak_method(nj_sys::napi_create_object());
+    nj_sys::assert_no_leaks();
+    assert_eq!(nj_sys::gc(), 1);
+    napi_export_threaded_method(nj_sys::napi_create_object());
+    assert_eq!(nj_sys::gc(), 1);
+    napi_export_scoped_method();
+    assert_eq!(nj_sys::gc(), 3);
+    napi_export_wrap_method();
+    napi_export_serde_method();
+    assert_eq!(nj_sys::gc(), 0);
+    napi_export_async_method();
+    // target、answer、两个 Promise 与被拒绝的原因
+    assert_eq!(nj_sys::gc(), 5);
+    track_live_references();
+    detect_unbalanced_references();
+    // 再做些其它的工作...
+    nj_sys::assert_no_leaks();
+    // 被 mem::forget() 的 NapiRc 仍被登记着
+    assert!(matches!(tracker::live().as_slice(), [entry] if entry.kind == "NapiRc"));
+    println!("===== 结束 =====");
+}
@@ -1,168 +1,73 @@
-#![doc = ::embed_doc_image::embed_image!("drone-states-diagram", "docs/images/drone-type-states-diagram.png")]
 //! 这段例程代码，通过给“操控【无人机】飞行”建立【程序模型】，来演示【类型·状态】设计模式的编码套路。
 //!
 //! 在此例程中，被涉及到的子技术知识点包括：
 //! 1. 零抽象成本·状态字段（见`Flying<S: Motionless>.destination_state`）
 //! 2. 按【智能指针】存储的多个状态共有字段值（见`Drone<S: State>.coordinate`）
-//! 3. 状态类型·分组（见`group_by_trait!()`宏）
+//! 3. 状态类型·分组（见`src/type_states.rs`里的`group_by_trait!()`宏）
 //! 4. 【状态组】独有成员方法（见`Drone<S: Midair>::take_picture(&self)`和`Drone<Flying<S: Motionless>>::inner_fly(mut self, state, step)`）
-//! 5. 密封【状态类型】以禁止下游代码扩展额外状态（见`seal_by_trait!()`宏）
+//! 5. 密封【状态类型】以禁止下游代码扩展额外状态（见`src/type_states.rs`里的`seal_by_trait!()`宏）
 //! 6. 编译时多态的【状态过渡】（见`Drone<Flying<Idle>>::fly(mut self, step)`和`Drone<Flying<Hovering>>::fly(mut self, step)`）
-//! 7. 【状态】独有成员方法（见`Drone<Idle>::take_off(self)`）
+//! 7. 【状态】独有成员方法（见由`transitions!()`宏生成的`Drone<Idle>::take_off(self)`）
 //! 8. 【状态】独有数据缓存字段（见`Flying<S: Motionless>.origin`）
 //! 9. `intra-doc link`文档注释指令
+//! 10. 【故障】驱动的【状态过渡】（见`Drone<Flying<S: Motionless>>::fly(self, step) -> Result<Drone<S>, Drone<Emergency>>`）
+//! 11. 从代码生成【状态·过渡】图（见`src/type_states.rs`里的`transitions!()`宏）
+//! 12. 与【异步运行时】无关的飞行执行器（见`FlightRuntime`。它有 async-std、futures `ThreadPool`与`LocalPool`三类适配器）
 //!
-//! 【无人机】飞行过程与状态结点包括：
+//! 【无人机】飞行过程与状态结点见 Mermaid 状态图`docs/drone-type-states.mmd`。它由`transitions!()`宏从【状态·过渡】
+//! 成员方法的声明生成（执行`cargo run --bin type-states-drone -- table|dot|mermaid`可输出机读表、DOT 图与 Mermaid 图）：
 //!
-//! ![无人机·飞行状态图][drone-states-diagram]
+#![doc = concat!("```text\n", include_str!("../../docs/drone-type-states.mmd"), "```")]
 //!
-//! 【无人机】总共在三个状态之间切换：
+//! 【无人机】总共在四个状态之间切换：
 //! 1. 待命`Idle` —— 无人机·在地面上
 //! 2. 飞行`Flying` —— 无人机·空中飞行
 //! 3. 悬浮`Hovering` —— 无人机·原地悬浮于空中
+//! 4. 应急`Emergency` —— 无人机·发生故障（比如，遥控信号丢失、电机故障、坐标互斥锁中毒），只能受控迫降
 //!
-//! 接着，这三个状态又按两个维度分成了两组：
+//! 接着，这四个状态又按三个维度分成了三组：
 //! 1. “静止”状态组`Motionless`，包括`Idle`和`Hovering`
 //!     * `Flying`状态的紧下一个状态必须是“静止组”内的状态。
-//! 2. “空中”状态组`Midair`，包括`Flying`和`Hovering`
+//! 2. “空中”状态组`Midair`，包括`Flying`、`Hovering`和`Emergency`
 //!     * 空中的无人机有一个额外的功能就是“拍照”。而停在地面上不能拍照。
+//! 3. “可操控”状态组`Manoeuvrable`，包括`Flying`和`Hovering`
+//!     * 可操控的无人机随时都能被切入`Emergency`状态。
 //!
-//! 【无人机】三个状态各有独特的行为：
+//! 【无人机】四个状态各有独特的行为：
 //! 1. `Idle`有`take_off()`起飞·行为，从而将`Idle`状态过渡为`Flying`
 //! 2. `Hovering`有`move_to()`前往·与`land()`着落·两个行为，从而将`Hovering`状态过渡为`Flying`
 //! 3. `Flying`有`fly()`飞行·行为。该行为
 //!     1. 既是【异步】的：
 //!         * 用跨线程【迭代器】模拟【无人机】（缓慢）飞行过程。
+//!         * 飞行过程由【无人机】构造时指定的`FlightRuntime`派发与计时，默认是 async-std。
 //!     2. 还是【多态】的：
 //!         1. 若紧前状态是`Idle`，那么当前状态过渡的目标就一定是`Hovering`。即，`Idle -> Flying -> Hovering`
 //!         2. 若紧前状态是`Hovering`，那么当前状态过渡的目标既有可能是`Idle`，还可能还是`Hovering`。这取决于之前`Hovering`是如何过渡到`Flying`的。
 //!
 //!         `fly()`行为的输出状态是不确定的，得看它的紧上一个状态是什么！
+//!     3. 还是【可失败】的：
+//!         * 若飞行途中发生了故障，那么当前状态过渡的目标就是`Emergency`。
+//! 4. `Emergency`仅有`descend()`迫降·行为，从而将`Emergency`状态过渡为`Idle`
 //!
+//! 此外，仿真程序可经由`Drone::fault_injector()`向【无人机】注入故障。
+//!
+//! 远程操控：`type-states-drone serve tcp://127.0.0.1:7878`启动仿真服务端，再由另一进程
+//! `type-states-drone remote tcp://127.0.0.1:7878`经由`RemoteDrone<S>`遥控。Unix 上也可用`unix://路径`。
+//!
+#[path = "../type_states.rs"]
+#[macro_use]
+mod type_states;
+#[path = "../drone-remote/mod.rs"]
+mod remote;
 mod drone_model {
-    /// 收拢了几段值得复用宏的宏工具箱
-    #[macro_use]
-    mod macro_utils {
-        /// 宏功能：限定 + 密封【状态·类型】
-        /// 意图：使公开的【业务功能】接口拒绝接收“下游”代码自定义的任
-        /// 何【状态·类型】。
-        macro_rules! seal_by_trait {
-            (
-                @private $subtrait: ident,
-                $supertrait_mod: ident,
-                [$($state1: ident$(<$state_generic1: ident>)?),*],
-                [$($state2: ident<$state_generic2: ident: $where: ident>),*]
-            ) => {
-                /// （私有的）密封`trait`
-                mod $supertrait_mod {
-                    pub trait Sealed {}
-                }
-                /// （公开的）【状态·类型】`trait`对外不可实现，
-                /// 因为它继承了（私有的）密封`trait`。
-                pub trait $subtrait: $supertrait_mod::Sealed {}
-                /// 给【状态·类型】限定条件
-                $(
-                    impl $supertrait_mod::Sealed for $state1$(<$state_generic1>)? {}
-                    impl $subtrait for $state1$(<$state_generic1>)? {}
-                )+
-                $(
-                    impl<$state_generic2: $where> $supertrait_mod::Sealed for $state2<$state_generic2> {}
-                    impl<$state_generic2: $where> $subtrait for $state2<$state_generic2> {}
-                )*
-            };
-            // 这是【宏】入口，提供了默认的【密封`trait`】名。
-            (
-                $subtrait: ident,
-                [$($state1: ident$(<$state_generic1: ident>)?),*],
-                [$($state2: ident<$state_generic2: ident: $where: ident>),*]
-            ) => {
-                seal_by_trait!(
-                    @private $subtrait,
-                    sealed,
-                    [$($state1$(<$state_generic1>)?),*],
-                    [$($state2<$state_generic2: $where>),*]
-                );
-            };
-            (
-                $subtrait: ident,
-                [$($state1: ident$(<$state_generic1: ident>)?),*]
-            ) => {
-                seal_by_trait!(
-                    @private $subtrait,
-                    [$($state1$(<$state_generic1>)?),*],
-                    []
-                );
-            };
-            (
-                $subtrait: ident,
-                [$($state2: ident<$state_generic2: ident: $where: ident>),*]
-            ) => {
-                seal_by_trait!(
-                    @private $subtrait,
-                    [],
-                    [$($state2<$state_generic2: $where>),*]
-                );
-            };
-        }
-        /// 宏功能：分组【状态·类型】
-        /// 意图：使每组【状态·类型】都有（组内）独有的【成员方法】。
-        macro_rules! group_by_trait {
-            (
-                $group: ident $(: $supertrait: ident)?,
-                [$($state1: ident$(<$state_generic1: ident>)?),*],
-                [$($state2: ident<$state_generic2: ident: $where: ident>),*]
-            ) => {
-                /// 定义·分组`marker trait`
-                pub trait $group $(:$supertrait)? {}
-                /// 通过标记【状态·类型】，将【状态·类型】分组
-                $(
-                    impl $group for $state1$(<$state_generic1>)? {}
-                )*
-                $(
-                    impl<$state_generic2: $where> $group for $state2<$state_generic2> {}
-                )*
-            };
-            (
-                $group: ident $(: $supertrait: ident)?,
-                [$($state1: ident$(<$state_generic1: ident>)?),*]
-            ) => {
-                group_by_trait!(
-                    $group $(: $supertrait)?,
-                    [$($state1$(<$state_generic1>)?),*],
-                    []
-                );
-            };
-            (
-                $group: ident $(: $supertrait: ident)?,
-                [$($state2: ident<$state_generic2: ident: $where: ident>),*]
-            ) => {
-                group_by_trait!(
-                    $group $(: $supertrait)?,
-                    [],
-                    [$($state2<$state_generic2: $where>),*]
-                );
-            }
-        }
-        /// 宏功能：若前一段占用互斥锁的程序运行崩溃了，当前程序依旧获取
-        /// 被保护数据的读写权限，而不是随着前一段程序一起崩溃掉。但，此
-        /// 决策是有读到脏数据风险的。
-        macro_rules! get_mutex_lock {
-            ($mutex: expr, $closure: expr) => {
-                match $mutex.lock() {
-                    Ok(mut value) => $closure(&mut value),
-                    Err(mut err) => $closure(err.get_mut())
-                }
-            };
-        }
-    }
-    /// 定义了【无人机】的三个状态。并对这些状态进行
+    /// 定义了【无人机】的四个状态。并对这些状态进行
     /// 1. 限定 + 密封 —— 禁止“下游”代码扩展
-    /// 2. 分组 —— 静止状态组·和·空中状态组
+    /// 2. 分组 —— 静止状态组·空中状态组·和·可操控状态组
     mod drone_states {
-        use ::async_std::task::JoinHandle;
         use ::derive_builder::Builder;
+        use ::futures::future::RemoteHandle;
         use ::std::marker::PhantomData;
-        use super::Coordinate;
+        use super::{Coordinate, Fault};
         // -------------------------
         // 状态·类型 — 描述·无人机·工作状态
         // -------------------------
@@ -170,29 +75,35 @@ mod drone_model {
         pub struct Idle;
         /// [`Drone<Hovering>`](struct@super::Drone#impl-Drone<Hovering>) 无人机·原地悬浮于空中
         pub struct Hovering;
+        /// [`Drone<Emergency>`](struct@super::Drone#impl-Drone<Emergency>) 无人机·发生故障，只能受控迫降
+        pub struct Emergency {
+            pub(super) fault: Fault // 【应急状态】独有·故障原因字段
+        }
         /// [`Drone<Flying<S: Motionless>>`](struct@super::Drone#impl-Drone<Flying<S>>) 无人机·空中飞行。它的下一个状态必须是隶属于`Motionless`组的状态
         #[derive(Builder, Debug, Default)]
         #[builder(pattern = "owned")]
-        pub struct Flying<S>
+        pub(crate) struct Flying<S>
         where S: Motionless {
             pub(super) origin: Coordinate,      // 【飞行状态】独有·起点字段
             pub(super) destination: Coordinate, // 【飞行状态】独有·终点字段
             #[builder(default = "Coordinate::step(1_f32)")]
             pub(super) step: Coordinate,        // 【飞行状态】独有·步长字段
             #[builder(setter(skip))]
-            pub(super) handle: Option<JoinHandle<()>>,
+            pub(super) handle: Option<RemoteHandle<Option<Fault>>>,
             /// 零抽象成本的状态字段
             #[builder(setter(skip))]
             destination_state: PhantomData<S>
         }
         // 1. 限定【状态·类型】都必须实现`trait State`
         // 2. 禁止下游代码扩充新【状态·类型】
-        seal_by_trait!(State, [Idle, Hovering], [Flying<S: Motionless>]);
+        seal_by_trait!(pub(crate) State, [Idle, Hovering, Emergency, impl[S: Motionless] Flying<S>]);
         // 无人机·状态·分组：
         // 1. 静止的无人机 — 作为【飞行·状态】的过渡目标【状态】
         // 2. 空中的无人机 — 处于这类【状态】的【无人机】的拍照功能
-        group_by_trait!(Motionless: State, [Idle, Hovering]);
-        group_by_trait!(Midair: State, [Hovering], [Flying<S: Motionless>]);
+        // 3. 可操控的无人机 — 空中且尚未故障，能被切入【应急·状态】
+        group_by_trait!(pub(crate) Motionless: State, [Idle, Hovering]);
+        group_by_trait!(pub(crate) Midair: State, [Hovering, Emergency, impl[S: Motionless] Flying<S>]);
+        group_by_trait!(pub(crate) Manoeuvrable: Midair, [Hovering, impl[S: Motionless] Flying<S>]);
     }
     /// 无人机·极坐标位置
     mod coordinate {
@@ -210,6 +121,21 @@ mod drone_model {
             pub(super) altitude: f32
         }
         impl Coordinate {
+            pub fn new(longitude: f32, latitude: f32, altitude: f32) -> Self {
+                Coordinate { longitude, latitude, altitude }
+            }
+            /// 经度
+            pub fn longitude(&self) -> f32 {
+                self.longitude
+            }
+            /// 纬度
+            pub fn latitude(&self) -> f32 {
+                self.latitude
+            }
+            /// 高度
+            pub fn altitude(&self) -> f32 {
+                self.altitude
+            }
             pub(super) fn step(value: f32) -> Self {
                 Coordinate {
                     longitude: value,
@@ -230,11 +156,306 @@ mod drone_model {
             }
         }
     }
+    /// 无人机·故障与故障注入
+    mod fault {
+        use ::std::{error::Error, fmt::{Display, Formatter, self}, sync::{Arc, Mutex, MutexGuard}, thread};
+        use super::Coordinate;
+        /// 致使【无人机】切入【应急·状态】的故障原因
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum Fault {
+            /// 遥控信号丢失
+            LinkLost,
+            /// 电机故障（电机编号）
+            Motor(u8),
+            /// 坐标互斥锁中毒了 — 持有该锁的另一段程序崩溃了
+            PoisonedCoordinate,
+            /// 操控员中止飞行
+            Aborted
+        }
+        impl Display for Fault {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                match self {
+                    Fault::LinkLost => write!(f, "遥控信号丢失"),
+                    Fault::Motor(motor) => write!(f, "{}号电机故障", motor),
+                    Fault::PoisonedCoordinate => write!(f, "坐标互斥锁中毒"),
+                    Fault::Aborted => write!(f, "操控员中止飞行")
+                }
+            }
+        }
+        impl Error for Fault {}
+        /// 供仿真程序使用的【故障注入器】。
+        ///
+        /// 它与【无人机】共享状态，所以即便【无人机】正在（异步地）飞行中，也能从别处向其注入故障。
+        #[derive(Clone, Debug, Default)]
+        pub struct FaultInjector {
+            /// 尚未被【无人机】察觉的故障
+            pending: Arc<Mutex<Option<Fault>>>,
+            /// 被“投毒”的坐标
+            coordinate: Arc<Mutex<Coordinate>>
+        }
+        impl FaultInjector {
+            pub(super) fn new(coordinate: Arc<Mutex<Coordinate>>) -> Self {
+                Self {
+                    pending: Arc::default(),
+                    coordinate
+                }
+            }
+            /// 注入故障。其中，`Fault::PoisonedCoordinate`会被真实地模拟：令一个持有坐
+            /// 标互斥锁的线程崩溃掉。
+            pub fn inject(&self, fault: Fault) {
+                if let Fault::PoisonedCoordinate = fault {
+                    let coordinate = Arc::clone(&self.coordinate);
+                    let _ = thread::spawn(move || {
+                        let _guard = coordinate.lock();
+                        panic!("[FaultInjector]模拟持有坐标互斥锁的程序崩溃");
+                    }).join();
+                } else {
+                    get_mutex_lock!(self.pending, |pending: &mut MutexGuard<'_, Option<Fault>>| {
+                        pending.replace(fault);
+                    });
+                }
+            }
+            /// 取走尚未被察觉的故障
+            pub(crate) fn take(&self) -> Option<Fault> {
+                get_mutex_lock!(self.pending, |pending: &mut MutexGuard<'_, Option<Fault>>| {
+                    pending.take()
+                })
+            }
+        }
+    }
+    /// 与【异步运行时】无关的飞行执行器：由【派发器】`Spawn`与【定时器】`Timer`两部分组成。
+    ///
+    /// 既有现成的 async-std 与 futures 执行器（`ThreadPool`与`LocalPool`）适配器，也能由下游代码
+    /// 对接自定义的【反应器】。
+    mod runtime {
+        use ::async_std::task;
+        use ::futures::{channel::mpsc, executor::LocalSpawner, future::{BoxFuture, FutureObj}, FutureExt, StreamExt, task::{LocalSpawnExt, Spawn, SpawnError}};
+        use ::std::{collections::BTreeMap, fmt::{Debug, Formatter, self}, future::Future, mem, pin::Pin, sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+                    task::{Context, Poll, Waker}, thread, time::{Duration, Instant}};
+        /// 【定时器】抽象
+        pub trait Timer: Send + Sync {
+            /// 异步地等待`duration`时长
+            fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+        }
+        /// async-std 的【派发器】适配器
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct AsyncStdSpawner;
+        impl Spawn for AsyncStdSpawner {
+            fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+                task::spawn(future);
+                Ok(())
+            }
+        }
+        /// async-std 的【定时器】适配器
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct AsyncStdTimer;
+        impl Timer for AsyncStdTimer {
+            fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+                task::sleep(duration).boxed()
+            }
+        }
+        /// 截止时刻与编号。编号区分截止时刻相同的多个计时
+        type TimerKey = (Instant, u64);
+        /// 按截止时刻排列的、尚未到期的计时
+        #[derive(Default)]
+        struct Timers {
+            next_id: u64,
+            wakers: BTreeMap<TimerKey, Waker>,
+            /// 全部`ThreadTimer`都已被析构，计时线程应退出
+            closed: bool
+        }
+        impl Timers {
+            /// 取出截止时刻不晚于`now`的计时
+            fn expire(&mut self, now: Instant) -> Vec<Waker> {
+                let later = self.wakers.split_off(&(now, u64::MAX));
+                mem::replace(&mut self.wakers, later).into_values().collect()
+            }
+            fn next_deadline(&self) -> Option<Instant> {
+                self.wakers.keys().next().map(|(deadline, _)| *deadline)
+            }
+        }
+        /// 计时线程与各个`Sleep`共享的计时队列。队列有变化时，经由`changed`唤醒计时线程
+        #[derive(Default)]
+        struct TimerQueue {
+            timers: Mutex<Timers>,
+            changed: Condvar
+        }
+        impl TimerQueue {
+            fn lock(&self) -> MutexGuard<'_, Timers> {
+                self.timers.lock().unwrap_or_else(PoisonError::into_inner)
+            }
+            /// 计时线程：睡至最早的截止时刻，再唤醒到期的等待方
+            fn run(&self) {
+                let mut timers = self.lock();
+                while !timers.closed {
+                    let now = Instant::now();
+                    let expired = timers.expire(now);
+                    if !expired.is_empty() {
+                        // 先释放锁，再唤醒等待方，因为被唤醒的任务可能立即在别的线程上轮询`Sleep`
+                        drop(timers);
+                        expired.into_iter().for_each(Waker::wake);
+                        timers = self.lock();
+                        continue;
+                    }
+                    timers = match timers.next_deadline() {
+                        Some(deadline) => self.changed.wait_timeout(timers, deadline - now).unwrap_or_else(PoisonError::into_inner).0,
+                        None => self.changed.wait(timers).unwrap_or_else(PoisonError::into_inner)
+                    };
+                }
+            }
+        }
+        /// 计时线程的所有者。它被析构时，令计时线程退出
+        struct TimerThread(Arc<TimerQueue>);
+        impl Drop for TimerThread {
+            fn drop(&mut self) {
+                self.0.lock().closed = true;
+                self.0.changed.notify_one();
+            }
+        }
+        /// 不依赖任何【反应器】的【定时器】：全部计时共享一条计时线程。它按截止时刻排列各个等待方的`Waker`，
+        /// 睡至最早的截止时刻，再唤醒到期的等待方。futures 执行器没有自带【定时器】，所以搭配此【定时器】使用。
+        ///
+        /// 克隆体共享同一条计时线程。全部克隆体与由它们创建的`Sleep`都被析构之后，计时线程才退出。
+        #[derive(Clone)]
+        pub struct ThreadTimer(Arc<TimerThread>);
+        impl ThreadTimer {
+            /// 启动计时线程
+            pub fn new() -> Self {
+                let queue = Arc::new(TimerQueue::default());
+                let thread_queue = Arc::clone(&queue);
+                thread::Builder::new().name("ThreadTimer".to_string()).spawn(move || thread_queue.run()).expect("[ThreadTimer][new]无法启动计时线程");
+                Self(Arc::new(TimerThread(queue)))
+            }
+        }
+        impl Default for ThreadTimer {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+        impl Debug for ThreadTimer {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.debug_struct("ThreadTimer").field("pending", &self.0.0.lock().wakers.len()).finish()
+            }
+        }
+        impl Timer for ThreadTimer {
+            fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+                Sleep { deadline: Instant::now() + duration, key: None, timer: self.clone() }.boxed()
+            }
+        }
+        /// 由`ThreadTimer`的计时线程唤醒的计时
+        struct Sleep {
+            deadline: Instant,
+            key: Option<TimerKey>,
+            timer: ThreadTimer
+        }
+        impl Future for Sleep {
+            type Output = ();
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let queue = Arc::clone(&self.timer.0.0);
+                let mut timers = queue.lock();
+                if Instant::now() >= self.deadline {
+                    if let Some(key) = self.key.take() {
+                        timers.wakers.remove(&key);
+                    }
+                    return Poll::Ready(());
+                }
+                let key = self.key.unwrap_or_else(|| {
+                    timers.next_id += 1;
+                    (self.deadline, timers.next_id)
+                });
+                let earliest = timers.next_deadline().map_or(true, |deadline| self.deadline < deadline);
+                timers.wakers.insert(key, cx.waker().clone());
+                drop(timers);
+                self.key = Some(key);
+                // 新的截止时刻早于计时线程正在等待的截止时刻，所以唤醒它重新计时
+                if earliest {
+                    queue.changed.notify_one();
+                }
+                Poll::Pending
+            }
+        }
+        impl Drop for Sleep {
+            fn drop(&mut self) {
+                if let Some(key) = self.key {
+                    self.timer.0.0.lock().wakers.remove(&key);
+                }
+            }
+        }
+        /// `LocalPool`的【派发器】适配器。
+        ///
+        /// `LocalSpawner`不能跨线程，所以先将被派发的`Future`经由`mpsc`频道转交给一个常驻于`LocalPool`
+        /// 内的“转派”任务，再由它在`LocalPool`的线程上派发。
+        #[derive(Clone, Debug)]
+        pub struct LocalPoolSpawner(mpsc::UnboundedSender<FutureObj<'static, ()>>);
+        impl LocalPoolSpawner {
+            pub fn new(spawner: &LocalSpawner) -> Result<Self, SpawnError> {
+                let (sender, receiver) = mpsc::unbounded::<FutureObj<'static, ()>>();
+                let local_spawner = spawner.clone();
+                spawner.spawn_local(receiver.for_each(move |future| {
+                    if let Err(err) = local_spawner.spawn_obj(future) {
+                        eprintln!("[LocalPoolSpawner]{}", err);
+                    }
+                    async {}
+                }))?;
+                Ok(Self(sender))
+            }
+        }
+        impl Spawn for LocalPoolSpawner {
+            fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+                self.0.unbounded_send(future).map_err(|_| SpawnError::shutdown())
+            }
+        }
+        /// 飞行执行器
+        #[derive(Clone)]
+        pub struct FlightRuntime {
+            spawner: Arc<dyn Spawn + Send + Sync>,
+            timer: Arc<dyn Timer>
+        }
+        impl FlightRuntime {
+            pub fn new<P, T>(spawner: P, timer: T) -> Self
+            where P: Spawn + Send + Sync + 'static,
+                  T: Timer + 'static {
+                Self {
+                    spawner: Arc::new(spawner),
+                    timer: Arc::new(timer)
+                }
+            }
+            /// 在 async-std 上飞行
+            pub fn async_std() -> Self {
+                Self::new(AsyncStdSpawner, AsyncStdTimer)
+            }
+            /// 在 futures 的`ThreadPool`（或其它可跨线程的【派发器】）上飞行
+            pub fn futures<P>(spawner: P) -> Self
+            where P: Spawn + Send + Sync + 'static {
+                Self::new(spawner, ThreadTimer::new())
+            }
+            /// 在 futures 的`LocalPool`上飞行
+            pub fn local_pool(spawner: &LocalSpawner) -> Result<Self, SpawnError> {
+                Ok(Self::futures(LocalPoolSpawner::new(spawner)?))
+            }
+            pub(super) fn spawner(&self) -> &(dyn Spawn + Send + Sync) {
+                &*self.spawner
+            }
+            pub(super) fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+                self.timer.sleep(duration)
+            }
+        }
+        impl Default for FlightRuntime {
+            fn default() -> Self {
+                Self::async_std()
+            }
+        }
+        impl Debug for FlightRuntime {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.debug_struct("FlightRuntime").finish_non_exhaustive()
+            }
+        }
+    }
     /// 模拟【无人机】缓慢飞行过程的【迭代器】
     mod flying_iterator {
         use ::derive_builder::Builder;
         use ::std::{iter::Iterator, sync::{Arc, Mutex, MutexGuard}};
-        use super::Coordinate;
+        use super::{Coordinate, Fault, FaultInjector};
         /// 跟踪·无人机·的飞行位置
         #[derive(Builder, Debug, Default)]
         pub struct FlyingIter {
@@ -245,7 +466,19 @@ mod drone_model {
             /// 当前位置
             current: Arc<Mutex<Coordinate>>,
             /// 移动步长值
-            step: Coordinate
+            step: Coordinate,
+            /// 故障监测。若为`None`，则忽略一切故障（比如，迫降过程）
+            #[builder(default)]
+            faults: Option<FaultInjector>,
+            /// 中断了飞行的故障
+            #[builder(setter(skip))]
+            fault: Option<Fault>
+        }
+        impl FlyingIter {
+            /// 取走中断了飞行的故障
+            pub fn take_fault(&mut self) -> Option<Fault> {
+                self.fault.take()
+            }
         }
         impl Iterator for FlyingIter {
             type Item = Coordinate;
@@ -271,7 +504,12 @@ mod drone_model {
                         }
                     }
                 }
-                get_mutex_lock!(self.current, |coord: &mut MutexGuard<Coordinate>| -> Option<Coordinate> {
+                if let Some(fault) = self.faults.as_ref().and_then(FaultInjector::take) {
+                    self.fault.replace(fault);
+                    return None;
+                }
+                let mut poisoned = false;
+                let next = get_mutex_lock!(self.current, |coord: &mut MutexGuard<Coordinate>| -> Option<Coordinate> {
                     coord.longitude = translate!(tweak longitude, coord);
                     coord.latitude = translate!(tweak latitude, coord);
                     coord.altitude = translate!(tweak altitude, coord);
@@ -279,24 +517,35 @@ mod drone_model {
                         return None;
                     }
                     Some((*coord).clone())
-                })
+                }, || poisoned = true);
+                if poisoned && self.faults.is_some() {
+                    self.fault.replace(Fault::PoisonedCoordinate);
+                    return None;
+                }
+                next
             }
         }
     }
-    use ::async_std::task;
+    use ::futures::task::SpawnExt;
     use ::std::{sync::{Arc, Mutex, MutexGuard}, time::Duration};
-    use coordinate::Coordinate;
     use flying_iterator::FlyingIterBuilder;
-    use drone_states::{Flying, FlyingBuilder, Hovering, Midair, Motionless, State};
-    pub use coordinate::CoordinateBuilder;
-    pub use drone_states::Idle;
+    use drone_states::{Flying, FlyingBuilder, Manoeuvrable, Motionless};
+    pub use coordinate::{Coordinate, CoordinateBuilder};
+    pub use drone_states::{Emergency, Hovering, Idle};
+    pub(crate) use drone_states::{Midair, State};
+    pub use fault::{Fault, FaultInjector};
+    pub use runtime::{FlightRuntime, ThreadTimer, Timer};
     /// 无人机·泛型类型
     /// 1. 最低内存成本的·按（普通）【引用】保存坐标位置`coordinate`
     /// 2. 不接收“下游”代码扩充的【状态·类型】`State`
-    pub struct Drone<S>
+    pub(crate) struct Drone<S>
     where S: State {
         /// 所有状态共有的坐标字段
         coordinate: Arc<Mutex<Coordinate>>,
+        /// 所有状态共有的故障注入器
+        faults: FaultInjector,
+        /// 所有状态共有的飞行执行器
+        runtime: FlightRuntime,
         state: S,
     }
     /// 所有状态共有的成员方法
@@ -308,64 +557,113 @@ mod drone_model {
                 coord.clone()
             })
         }
+        /// 获取·无人机·的【故障注入器】，以便仿真程序从别处注入故障
+        pub fn fault_injector(&self) -> FaultInjector {
+            self.faults.clone()
+        }
+        /// 【状态·过渡】保留共有字段，切换至另一个状态
+        fn transit<T>(self, state: T) -> Drone<T>
+        where T: State {
+            Drone {
+                coordinate: self.coordinate,
+                faults: self.faults,
+                runtime: self.runtime,
+                state
+            }
+        }
     }
     /// [`Idle`](struct@drone_states::Idle) - 无人机·在地面上
     ///
     /// 【待命】状态独有的【关联函数】与【成员方法】
     impl Drone<Idle> {
         /// 所有【新】无人机都得从【待命】状态开始，因为无人机的其它状态
-        /// 都没有【构造函数】。
-        pub fn new(mut coordinate: Coordinate) -> Self {
+        /// 都没有【构造函数】。默认在 async-std 上飞行。
+        pub fn new(coordinate: Coordinate) -> Self {
+            Self::with_runtime(coordinate, FlightRuntime::default())
+        }
+        /// 同上，但在指定的【飞行执行器】上飞行
+        pub fn with_runtime(mut coordinate: Coordinate, runtime: FlightRuntime) -> Self {
             if coordinate.altitude != 0_f32 {
                 #[cfg(debug_assertions)]
                 println!("无人机的出生地必须在地面上，所以将忽略高度值 {}", coordinate.altitude);
                 coordinate.altitude = 0_f32;
             }
+            let coordinate = Arc::new(Mutex::new(coordinate));
             Self {
-                coordinate: Arc::new(Mutex::new(coordinate)),
+                faults: FaultInjector::new(Arc::clone(&coordinate)),
+                coordinate,
+                runtime,
                 state: Idle
             }
         }
-        /// 【起飞 - 状态·过渡】无人机·从地面到空中
-        pub fn take_off(self, altitude: f32) -> Drone<Flying<Hovering>> {
+    }
+    // 全部【状态·过渡】成员方法。【状态·过渡】表`TRANSITION_GRAPH`也是从这些声明生成的，所以新增、删、改
+    // 【状态·过渡】成员方法都会改变被生成的【状态·过渡】图。
+    transitions! {
+        Drone::transit => TRANSITION_GRAPH, initial Idle;
+        /// [`Idle`](struct@drone_states::Idle) - 【起飞 - 状态·过渡】无人机·从地面到空中
+        pub(crate) fn take_off(self: Idle, altitude: f32) -> Flying<Hovering> {
             let origin = self.coordinate();
             let mut destination = origin.clone();
             destination.altitude = altitude;
-            Drone {
-                coordinate: self.coordinate,
-                state: FlyingBuilder::default()
-                    .origin(origin)
-                    .destination(destination)
-                    .build().unwrap()
-            }
+            FlyingBuilder::default()
+                .origin(origin)
+                .destination(destination)
+                .build().unwrap()
         }
-    }
-    /// [`Hovering`](struct@drone_states::Hovering) - 无人机·原地悬浮于空中
-    ///
-    /// 【悬浮】状态独有的成员方法
-    impl Drone<Hovering> {
-        /// 【着落 - 状态·过渡】无人机·从空中到地面
-        pub fn land(self) -> Drone<Flying<Idle>> {
+        /// [`Hovering`](struct@drone_states::Hovering) - 【着落 - 状态·过渡】无人机·从空中到地面
+        pub(crate) fn land(self: Hovering) -> Flying<Idle> {
             let origin = self.coordinate();
             let mut destination = origin.clone();
             destination.altitude = 0_f32;
-            Drone {
-                coordinate: self.coordinate,
-                state: FlyingBuilder::default()
-                    .origin(origin)
-                    .destination(destination)
-                    .build().unwrap()
-            }
+            FlyingBuilder::default()
+                .origin(origin)
+                .destination(destination)
+                .build().unwrap()
         }
-        // 【飘移 - 状态·过渡】无人机·在空中从一处飞行到另一处
-        pub fn move_to(self, destination: Coordinate) -> Drone<Flying<Hovering>> {
+        /// [`Hovering`](struct@drone_states::Hovering) - 【飘移 - 状态·过渡】无人机·在空中从一处飞行到另一处
+        pub(crate) fn move_to(self: Hovering, destination: Coordinate) -> Flying<Hovering> {
             let origin = self.coordinate();
-            Drone {
-                coordinate: self.coordinate,
+            FlyingBuilder::default()
+                .origin(origin)
+                .destination(destination)
+                .build().unwrap()
+        }
+        /// 可操控的【无人机】 - 【应急 - 状态·过渡】无人机·因故障（比如，操控员主动中止）切入【应急·状态】
+        pub(crate) fn emergency[S: Manoeuvrable] for[Flying<Hovering>, Hovering, Flying<Idle>](self: S, fault: Fault) -> Emergency {
+            Emergency { fault }
+        }
+        /// [`Flying<Hovering>`](struct@drone_states::Flying) - 以【悬浮】为下一状态的【飞行】。若飞行途中发生了故障，则返回`Err(Drone<Emergency>)`
+        pub(crate) async fn fly(self: Flying<Hovering>, step: Option<Coordinate>) -> Hovering | Emergency {
+            self.inner_fly(Hovering, step, true).await
+        }
+        /// [`Flying<Idle>`](struct@drone_states::Flying) - 面向【着落】的【飞行】。若飞行途中发生了故障，则返回`Err(Drone<Emergency>)`
+        pub(crate) async fn fly(self: Flying<Idle>, step: Option<Coordinate>) -> Idle | Emergency {
+            self.inner_fly(Idle, step, true).await
+        }
+        /// [`Emergency`](struct@drone_states::Emergency) - 【迫降 - 状态·过渡】无人机·从空中受控地降落至地面。这是【应急·状态】唯一的出路。
+        ///
+        /// 迫降过程忽略一切故障。着落后，【无人机】换用一把未中毒的坐标互斥锁与一个新的【故
+        /// 障注入器】，就像是刚被检修过一样。
+        pub(crate) async fn descend(self: Emergency, step: Option<Coordinate>) -> Idle {
+            #[cfg(debug_assertions)]
+            println!("因为{}，无人机·开始迫降", self.state.fault);
+            let origin = self.coordinate();
+            let mut destination = origin.clone();
+            destination.altitude = 0_f32;
+            let coordinate = Arc::new(Mutex::new(origin.clone()));
+            let flying_drone = Drone {
+                faults: FaultInjector::new(Arc::clone(&coordinate)),
+                coordinate,
+                runtime: self.runtime,
                 state: FlyingBuilder::default()
                     .origin(origin)
                     .destination(destination)
                     .build().unwrap()
+            };
+            match flying_drone.inner_fly(Idle, step, false).await {
+                Ok(idle_drone) => idle_drone,
+                Err(_) => unreachable!("迫降过程不监测故障")
             }
         }
     }
@@ -374,7 +672,8 @@ mod drone_model {
     /// 【飞行】状态独有的成员方法
     impl<S> Drone<Flying<S>>
     where S: Motionless {
-        async fn inner_fly(mut self, state: S, step: Option<Coordinate>) -> Drone<S>
+        /// 若`monitored`为`false`，则飞行过程忽略一切故障，且一定不会返回`Err`。
+        async fn inner_fly(mut self, state: S, step: Option<Coordinate>, monitored: bool) -> Result<Drone<S>, Drone<Emergency>>
         where S: Motionless {
             if self.state.handle.is_none() {
                 if let Some(mut step) = step {
@@ -382,37 +681,37 @@ mod drone_model {
                     self.state.step = step;
                 }
                 let mut move_iter = FlyingIterBuilder::default()
-                    .origin(self.state.origin)
-                    .destination(self.state.destination)
-                    .step(self.state.step)
+                    .origin(self.state.origin.clone())
+                    .destination(self.state.destination.clone())
+                    .step(self.state.step.clone())
                     .current(Arc::clone(&self.coordinate))
+                    .faults(monitored.then(|| self.faults.clone()))
                     .build().unwrap();
-                self.state.handle.replace(task::spawn(async move {
+                let runtime = self.runtime.clone();
+                let handle = self.runtime.spawner().spawn_with_handle(async move {
                     while let Some(_) = move_iter.next() {
-                        task::sleep(Duration::from_millis(200)).await;
+                        runtime.sleep(Duration::from_millis(200)).await;
                     }
-                }));
+                    move_iter.take_fault()
+                }).expect("飞行执行器已被关闭了");
+                self.state.handle.replace(handle);
             } else if let Some(step) = step {
                 #[cfg(debug_assertions)]
                 println!("因一旦开始飞行就不能再修改步长值了，但是忽略了此值 {}", step);
             }
-            self.state.handle.unwrap().await;
-            Drone {
-                coordinate: self.coordinate,
-                state
+            match self.state.handle.take().unwrap().await {
+                Some(fault) => Err(self.transit(Emergency { fault })),
+                None => Ok(self.transit(state))
             }
         }
     }
-    /// 面向【着落】的【飞行】状态的独有成员方法
-    impl Drone<Flying<Idle>> {
-        pub async fn fly(self, step: Option<Coordinate>) -> Drone<Idle> {
-            self.inner_fly(Idle, step).await
-        }
-    }
-    /// 以【悬浮】为下一状态的【飞行】状态独有成员方法
-    impl Drone<Flying<Hovering>> {
-        pub async fn fly(self, step: Option<Coordinate>) -> Drone<Hovering> {
-            self.inner_fly(Hovering, step).await
+    /// [`Emergency`](struct@drone_states::Emergency) - 无人机·发生故障，只能受控迫降
+    ///
+    /// 【应急】状态独有的成员方法
+    impl Drone<Emergency> {
+        /// 致使【无人机】切入【应急·状态】的故障原因
+        pub fn fault(&self) -> &Fault {
+            &self.state.fault
         }
     }
     /// 空中的【无人机】独有成员方法
@@ -423,11 +722,47 @@ mod drone_model {
             println!("拍照一张在{}", self.coordinate());
         }
     }
+    /// 可操控的【无人机】独有成员方法
+    impl<S> Drone<S>
+    where S: Manoeuvrable {
+        /// 自检：若有尚未被察觉的（被注入）故障，或坐标互斥锁已中毒，则切入【应急·状态】
+        pub fn check_faults(self) -> Result<Self, Drone<Emergency>> {
+            if self.coordinate.is_poisoned() {
+                return Err(self.emergency(Fault::PoisonedCoordinate));
+            }
+            match self.faults.take() {
+                Some(fault) => Err(self.emergency(fault)),
+                None => Ok(self)
+            }
+        }
+    }
 }
-use ::async_std::task;
-use ::std::error::Error;
-use drone_model::{CoordinateBuilder, Drone, Idle};
+use ::async_std::{net::TcpListener, task};
+use ::futures::{executor::LocalPool, join};
+use ::std::{error::Error, time::Duration};
+use drone_model::{CoordinateBuilder, Drone, Fault, FlightRuntime, Idle, ThreadTimer, Timer, TRANSITION_GRAPH};
+use remote::{Endpoint, RemoteClient, SimulatorServer, Telemetry};
+/// 被文档化的【状态·过渡】图。它必须与从代码生成的【状态·过渡】图一致。
+const DOCUMENTED_GRAPH: &str = include_str!("../../docs/drone-type-states.mmd");
 fn main() -> Result<(), Box<dyn Error>> {
+    // 仅输出【状态·过渡】图，或仅运行远程操控的仿真服务端/遥控端
+    let mut args = ::std::env::args().skip(1);
+    if let Some(subcommand) = args.next() {
+        let mut endpoint = || -> Result<Endpoint, Box<dyn Error>> {
+            Ok(args.next().ok_or("缺少监听地址，比如 tcp://127.0.0.1:7878 或 unix:///tmp/drone.sock")?.parse()?)
+        };
+        match subcommand.as_str() {
+            "table" => print!("{}", TRANSITION_GRAPH.to_table()),
+            "dot" => print!("{}", TRANSITION_GRAPH.to_dot()),
+            "mermaid" => print!("{}", TRANSITION_GRAPH.to_mermaid()),
+            "serve" => task::block_on(SimulatorServer::default().serve(&endpoint()?))?,
+            "remote" => task::block_on(remote_flight(&endpoint()?))?,
+            _ => return Err(format!("不支持的子命令 {}。可选子命令：table、dot、mermaid、serve <地址>、remote <地址>", subcommand).into())
+        }
+        return Ok(());
+    }
+    // 若文档与代码“脱节”了，就得重新生成文档：cargo run --bin type-states-drone -- mermaid > docs/drone-type-states.mmd
+    assert_eq!(TRANSITION_GRAPH.to_mermaid(), DOCUMENTED_GRAPH, "docs/drone-type-states.mmd 与代码里的【状态·过渡】不一致");
     task::block_on(async {
         // 在地面上放一架【待命】模式的【无人机】
         let idle_drone1 = Drone::<Idle>::new(CoordinateBuilder::default()
@@ -444,7 +779,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         // 【无人机】拉升至 10 米高度，进入【悬浮】模式
         let hovering_drone1 = flying_drone1.fly(Some(CoordinateBuilder::default()
             .altitude(0.5_f32)
-            .build()?)).await;
+            .build()?)).await.map_err(|drone| drone.fault().clone())?;
         hovering_drone1.take_picture();
         #[cfg(debug_assertions)]
         println!("【悬浮·状态】无人机·静止于空中{}。", hovering_drone1.coordinate());
@@ -462,7 +797,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             .longitude(1.1)
             .latitude(1.2)
             .altitude(0.5)
-            .build()?)).await;
+            .build()?)).await.map_err(|drone| drone.fault().clone())?;
         #[cfg(debug_assertions)]
         println!("【悬浮·状态】无人机·静止于空中{}。", hovering_drone2.coordinate());
         // 命令【无人机】原地着落
@@ -470,9 +805,119 @@ fn main() -> Result<(), Box<dyn Error>> {
         // 【无人机】安全着落，两次进入待命模式
         let idle_drone2 = flying_drone2.fly(Some(CoordinateBuilder::default()
             .altitude(0.6_f32)
-            .build()?)).await;
+            .build()?)).await.map_err(|drone| drone.fault().clone())?;
         #[cfg(debug_assertions)]
         println!("【待命·状态】无人机·着落于地面{}。", idle_drone2.coordinate());
-        Ok(())
+        // 仿真：【无人机】在拉升途中丢失了遥控信号
+        let flying_drone3 = idle_drone2.take_off(8_f32);
+        let faults = flying_drone3.fault_injector();
+        task::spawn(async move {
+            task::sleep(Duration::from_millis(500)).await;
+            faults.inject(Fault::LinkLost);
+        });
+        let emergency_drone1 = match flying_drone3.fly(None).await {
+            Ok(_) => unreachable!("已被注入了故障"),
+            Err(emergency_drone) => emergency_drone
+        };
+        assert_eq!(emergency_drone1.fault(), &Fault::LinkLost);
+        emergency_drone1.take_picture();
+        #[cfg(debug_assertions)]
+        println!("【应急·状态】无人机·因{}，被困于空中{}。", emergency_drone1.fault(), emergency_drone1.coordinate());
+        // 【无人机】受控迫降，再次进入待命模式
+        let idle_drone3 = emergency_drone1.descend(None).await;
+        #[cfg(debug_assertions)]
+        println!("【待命·状态】无人机·迫降于地面{}。", idle_drone3.coordinate());
+        // 仿真：【无人机】悬浮时，坐标互斥锁中毒了
+        let hovering_drone3 = idle_drone3.take_off(3_f32).fly(None).await.map_err(|drone| drone.fault().clone())?;
+        hovering_drone3.fault_injector().inject(Fault::PoisonedCoordinate);
+        let emergency_drone2 = match hovering_drone3.check_faults() {
+            Ok(_) => unreachable!("坐标互斥锁已中毒"),
+            Err(emergency_drone) => emergency_drone
+        };
+        assert_eq!(emergency_drone2.fault(), &Fault::PoisonedCoordinate);
+        let idle_drone4 = emergency_drone2.descend(None).await;
+        #[cfg(debug_assertions)]
+        println!("【待命·状态】无人机·迫降于地面{}。", idle_drone4.coordinate());
+        // 仿真：操控员发现电机故障，主动中止【飞行】
+        let emergency_drone3 = idle_drone4.take_off(6_f32).emergency(Fault::Motor(2));
+        let idle_drone5 = emergency_drone3.descend(None).await;
+        #[cfg(debug_assertions)]
+        println!("【待命·状态】无人机·迫降于地面{}。", idle_drone5.coordinate());
+        Ok::<_, Box<dyn Error>>(())
+    })?;
+    // 同一套【无人机】模型，换用 futures 的`LocalPool`执行器飞行
+    let mut executor = LocalPool::new();
+    let runtime = FlightRuntime::local_pool(&executor.spawner())?;
+    executor.run_until(async move {
+        let idle_drone1 = Drone::with_runtime(CoordinateBuilder::default()
+            .longitude(3_f32)
+            .latitude(4_f32)
+            .build()?, runtime);
+        let hovering_drone1 = idle_drone1.take_off(2_f32).fly(None).await.map_err(|drone| drone.fault().clone())?;
+        #[cfg(debug_assertions)]
+        println!("【悬浮·状态】经由 LocalPool 驱动的无人机·静止于空中{}。", hovering_drone1.coordinate());
+        let idle_drone2 = hovering_drone1.land().fly(None).await.map_err(|drone| drone.fault().clone())?;
+        #[cfg(debug_assertions)]
+        println!("【待命·状态】经由 LocalPool 驱动的无人机·着落于地面{}。", idle_drone2.coordinate());
+        Ok::<_, Box<dyn Error>>(())
+    })?;
+    // `ThreadTimer`的全部计时共享同一条计时线程，且按截止时刻（而不是创建次序）到期
+    let timer = ThreadTimer::new();
+    let expired = ::std::cell::RefCell::new(Vec::new());
+    let sleep = |id: u32, millis: u64| {
+        let sleep = timer.sleep(Duration::from_millis(millis));
+        let expired = &expired;
+        async move {
+            sleep.await;
+            expired.borrow_mut().push(id);
+        }
+    };
+    executor.run_until(async {
+        join!(sleep(1, 60), sleep(2, 20), sleep(3, 40));
+    });
+    assert_eq!(expired.into_inner(), [2, 3, 1]);
+    // 在同一进程内，经由 localhost 的 TCP 套接字远程操控【无人机】
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = Endpoint::Tcp(listener.local_addr()?.to_string());
+        task::spawn(SimulatorServer::default().serve_tcp(listener));
+        remote_flight(&endpoint).await
     })
 }
+/// 远程操控【无人机】：起飞、拍照、巡航途中被另一条连接中止飞行、迫降
+async fn remote_flight(endpoint: &Endpoint) -> Result<(), Box<dyn Error>> {
+    let client = RemoteClient::connect(endpoint).await?;
+    let idle_drone1 = client.spawn(1_f32, 2_f32).await?;
+    let hovering_drone1 = idle_drone1.take_off(3_f32).await?.map_err(|drone| drone.fault().clone())?;
+    let picture = hovering_drone1.take_picture().await?;
+    println!("【遥控】{}号无人机·拍照一张在{}", hovering_drone1.id(), picture);
+    let id = hovering_drone1.id();
+    let aborter = RemoteClient::connect(endpoint).await?;
+    let (flight, ack) = join!(
+        hovering_drone1.move_to(CoordinateBuilder::default()
+            .longitude(20_f32)
+            .latitude(20_f32)
+            .altitude(3_f32)
+            .build()?),
+        async {
+            task::sleep(Duration::from_millis(400)).await;
+            aborter.abort(id).await
+        }
+    );
+    assert_eq!(ack?, Telemetry::Ack { id });
+    let emergency_drone1 = match flight? {
+        Ok(_) => unreachable!("巡航已被中止"),
+        Err(emergency_drone) => emergency_drone
+    };
+    assert_eq!(emergency_drone1.fault(), &Fault::Aborted);
+    println!("【遥控】{}号无人机·因{}，被困于空中{}", id, emergency_drone1.fault(), emergency_drone1.coordinate());
+    let idle_drone2 = emergency_drone1.descend().await?;
+    println!("【遥控】{}号无人机·迫降于地面{}", id, idle_drone2.coordinate());
+    // 正常着落，与悬浮时的主动中止
+    let hovering_drone2 = idle_drone2.take_off(2_f32).await?.map_err(|drone| drone.fault().clone())?;
+    let idle_drone3 = hovering_drone2.land().await?.map_err(|drone| drone.fault().clone())?;
+    let hovering_drone3 = idle_drone3.take_off(1_f32).await?.map_err(|drone| drone.fault().clone())?;
+    let idle_drone4 = hovering_drone3.abort().await?;
+    println!("【遥控】{}号无人机·中止悬浮，迫降于地面{}", id, idle_drone4.coordinate());
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+/**
+ * 在 Linux 上，以内存传输层测试`nwg-webview/bridge.rs`：`MemoryPage`扮演注入了`BRIDGE_SCRIPT`的页面。
+ */
+#[path = "../nwg-webview/bridge.rs"]
+mod bridge;
+
+use ::futures::{channel::oneshot, executor::LocalPool, FutureExt};
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::{json, Value};
+use ::std::{cell::RefCell, error::Error, rc::Rc};
+use bridge::{Bridge, MemoryPage, RpcError};
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64
+}
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut pool = LocalPool::new();
+    let (transport, inbox) = bridge::memory_transport();
+    let bridge = Bridge::new(transport, pool.spawner());
+    let page = MemoryPage::new(bridge.clone(), inbox);
+    // 同步处理函数：位置参数被反序列化为元组，命名参数被反序列化为结构体
+    bridge.register("add", |(a, b): (i64, i64)| Ok(a + b));
+    bridge.register("divide", |(a, b): (f64, f64)| if b == 0.0 { Err("除数不能是零".into()) } else { Ok(a / b) });
+    bridge.register("mirror", |point: Point| Ok(Point { x: -point.x, y: -point.y }));
+    let pings = Rc::new(RefCell::new(Vec::new()));
+    bridge.register("ping", {
+        let pings = Rc::clone(&pings);
+        move |message: String| {
+            pings.borrow_mut().push(message);
+            Ok(())
+        }
+    });
+    // 异步处理函数：它的 Future 等待 Rust 端的某个信号
+    let (signal, signaled) = oneshot::channel::<u32>();
+    let signaled = signaled.shared();
+    bridge.register_async("wait", move |offset: u32| {
+        let signaled = signaled.clone();
+        async move { signaled.await.map(|value| value + offset).map_err(|_| RpcError::from("信号已被丢弃")) }
+    });
+    // 1. 同步处理函数被立即回复，页面端的 Promise 在下一轮 pump 之后兑现
+    let sum = page.call::<i64>("add", [1, 2]);
+    let quotient = page.call::<f64>("divide", [1, 0]);
+    let mirrored = page.call::<Point>("mirror", json!({ "x": 1.5, "y": -2 }));
+    assert_eq!(page.pump(), 3);
+    assert_eq!(sum.now_or_never(), Some(Ok(3)));
+    let err = quotient.now_or_never().unwrap().unwrap_err();
+    assert_eq!((err.code, err.message.as_str()), (RpcError::SERVER_ERROR, "除数不能是零"));
+    assert_eq!(mirrored.now_or_never(), Some(Ok(Point { x: -1.5, y: 2.0 })));
+    // 2. 方法不存在与参数不匹配
+    let missing = page.call::<Value>("subtract", [1, 2]);
+    let mismatched = page.call::<i64>("add", "1 + 2");
+    assert_eq!(page.pump(), 2);
+    assert_eq!(missing.now_or_never().unwrap().unwrap_err().code, RpcError::METHOD_NOT_FOUND);
+    assert_eq!(mismatched.now_or_never().unwrap().unwrap_err().code, RpcError::INVALID_PARAMS);
+    // 3. 通知不被回复
+    page.notify("ping", "你好");
+    page.notify("ping", 42); // 参数不匹配的通知只被打印
+    assert_eq!(page.pump(), 0);
+    assert_eq!(*pings.borrow(), ["你好"]);
+    // 4. 畸形的请求
+    let replies = page.send_raw("{ 不是 JSON");
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["id"], Value::Null);
+    assert_eq!(replies[0]["error"]["code"], RpcError::PARSE_ERROR);
+    let replies = page.send_raw(r#"{"jsonrpc":"1.0","id":7,"method":"add","params":[1,2]}"#);
+    assert_eq!((&replies[0]["id"], &replies[0]["error"]["code"]), (&json!(7), &json!(RpcError::INVALID_REQUEST)));
+    let replies = page.send_raw(r#"{"jsonrpc":"2.0","id":"a","params":[]}"#);
+    assert_eq!((&replies[0]["id"], &replies[0]["error"]["code"]), (&json!("a"), &json!(RpcError::INVALID_REQUEST)));
+    // 5. 异步处理函数：执行器运转之后，才被回复
+    let waited = page.call::<u32>("wait", 1);
+    pool.run_until_stalled();
+    assert_eq!(page.pump(), 0);
+    signal.send(41).unwrap();
+    pool.run_until_stalled();
+    assert_eq!(page.pump(), 1);
+    assert_eq!(waited.now_or_never(), Some(Ok(42)));
+    // 6. Rust 端向页面推送事件
+    let received = Rc::new(RefCell::new(Vec::new()));
+    page.on("progress", {
+        let received = Rc::clone(&received);
+        move |params| received.borrow_mut().push(params.clone())
+    });
+    bridge.emit("progress", json!({ "percent": 50 }))?;
+    bridge.emit("progress", Point { x: 0.0, y: 1.0 })?;
+    bridge.emit("unheard", ())?;
+    assert_eq!(page.pump(), 3);
+    assert_eq!(*received.borrow(), [json!({ "percent": 50 }), json!({ "x": 0.0, "y": 1.0 })]);
+    // 7. 被注销的处理函数
+    assert!(bridge.unregister("add"));
+    assert!(!bridge.unregister("add"));
+    let unregistered = page.call::<i64>("add", [1, 2]);
+    page.pump();
+    assert_eq!(unregistered.now_or_never().unwrap().unwrap_err().code, RpcError::METHOD_NOT_FOUND);
+    println!("全部断言都通过了");
+    Ok(())
+}
@@ -0,0 +1,86 @@
+/**
+ * 在 Linux 上，以`ChannelHost`测试`nwg-webview/reactor.rs`的调度行为：跨线程唤醒、定时器、唤醒合并与事件循环的退出。
+ */
+#[path = "../nwg-webview/reactor.rs"]
+mod reactor;
+
+use ::futures::{channel::oneshot, future::{self, Either}, task::LocalSpawnExt};
+use ::std::{cell::RefCell, error::Error, panic::{self, AssertUnwindSafe}, rc::Rc, thread, time::{Duration, Instant}};
+use reactor::{ChannelHost, HostLoop, Reactor};
+fn main() -> Result<(), Box<dyn Error>> {
+    // 1. 已就绪的 Future 只需一轮
+    let mut host = ChannelHost::default();
+    let reactor = Reactor::new(&host);
+    assert_eq!(reactor.block_on(&mut host, async { 1 + 1 }), Some(2));
+    assert_eq!(host.turns(), 1);
+    // 2. 多次唤醒被合并为一轮
+    let mut host = ChannelHost::default();
+    let reactor = Reactor::new(&host);
+    let waker = host.waker();
+    (0..100).for_each(|_| waker.wake());
+    assert_eq!(reactor.block_on(&mut host, async {}), Some(()));
+    assert_eq!((host.remote().wakes(), host.turns()), (101, 1)); // 再加上提交任务时的那一次唤醒
+    // 3. 被其它线程唤醒的任务：事件循环在等待期间不空转
+    let mut host = ChannelHost::default();
+    let reactor = Reactor::new(&host);
+    let (sender, receiver) = oneshot::channel();
+    let worker = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        sender.send("来自工作线程").unwrap();
+    });
+    assert_eq!(reactor.block_on(&mut host, receiver), Some(Ok("来自工作线程")));
+    worker.join().unwrap();
+    assert!(host.turns() <= 3, "事件循环空转了 {} 轮", host.turns());
+    // 4. 定时器按截止时刻的先后被唤醒
+    let mut host = ChannelHost::default();
+    let reactor = Reactor::new(&host);
+    let handle = reactor.handle();
+    let fired = Rc::new(RefCell::new(Vec::new()));
+    for millis in [30, 10, 20] {
+        let sleep = handle.sleep(Duration::from_millis(millis));
+        let fired = Rc::clone(&fired);
+        handle.spawn_local(async move {
+            sleep.await;
+            fired.borrow_mut().push(millis);
+        })?;
+    }
+    let start = Instant::now();
+    reactor.block_on(&mut host, handle.sleep(Duration::from_millis(40)));
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    assert_eq!(*fired.borrow(), [10, 20, 30]);
+    assert!(host.turns() <= 8, "事件循环空转了 {} 轮", host.turns());
+    // 5. 被丢弃的定时器不再唤醒事件循环
+    let start = Instant::now();
+    let turns = host.turns();
+    let raced = reactor.block_on(&mut host, future::select(handle.sleep(Duration::from_millis(10)), handle.sleep(Duration::from_secs(60))));
+    assert!(matches!(raced, Some(Either::Left(_))));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let late = Instant::now() + Duration::from_millis(20);
+    reactor.block_on(&mut host, handle.sleep_until(late));
+    assert!(Instant::now() >= late);
+    assert!(host.turns() - turns <= 6, "事件循环空转了 {} 轮", host.turns() - turns);
+    // 6. 宿主自行退出（就像主窗体被关闭了）：run() 返回；block_on() 返回 None
+    let remote = host.remote();
+    let closer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        remote.quit();
+    });
+    assert_eq!(reactor.block_on(&mut host, future::pending::<()>()), None);
+    closer.join().unwrap();
+    let remote = host.remote();
+    handle.spawn_local(async move {
+        remote.quit();
+    })?;
+    reactor.run(&mut host);
+    // 7. 任务 panic 了：panic 穿过 block_on() 传播，但 IN_TURN 被复位，所以此后提交的任务仍会唤醒事件循环
+    let mut host = ChannelHost::default();
+    let reactor = Reactor::new(&host);
+    let handle = reactor.handle();
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| reactor.block_on(&mut host, async { panic!("任务里的 panic") })));
+    assert!(panicked.is_err());
+    let wakes = host.remote().wakes();
+    handle.spawn_local(async {})?;
+    assert_eq!(host.remote().wakes(), wakes + 1);
+    println!("全部断言都通过了");
+    Ok(())
+}
@@ -4,10 +4,10 @@
 #[path ="../nwg-webview/mod.rs"]
 mod webview;
 
-use ::futures::{FutureExt, executor::LocalPool, task::LocalSpawnExt};
+use ::futures::{FutureExt, task::LocalSpawnExt};
 use ::nwg::{self as nwg, Event as NwgEvent, FrameFlags, GridLayout, Window};
-use ::std::error::Error;
-use webview::WebviewContainer;
+use ::std::{cell::RefCell, error::Error, rc::Rc};
+use webview::{NwgHost, WebviewContainer, reactor::Reactor};
 fn main() -> Result<(), Box<dyn Error>> {
     // 开启高分辨率模式。从 COM API 开启的方式将会被废弃，推荐从【应用程序】配置清单文件开启。
     #[allow(deprecated)]
@@ -28,24 +28,33 @@ fn main() -> Result<(), Box<dyn Error>> {
     WebviewContainer::builder().parent(&window).window(&window).enabled(true).flags(FrameFlags::VISIBLE).build(&mut webview_container)?;
     let mut grid = GridLayout::default();
     GridLayout::builder().margin([0; 4]).max_column(Some(1)).max_row(Some(1)).child(0, 0, &webview_container).parent(&window).build(&mut grid)?;
+    // 以 win32 UI 的事件循环为【反应器】，对接 futures crate 的【执行器】
+    let mut host = NwgHost::new(&window)?;
+    let reactor = Reactor::new(&host);
     // 业务处理逻辑
-    let mut executor = {
-        let executor = LocalPool::new();
-        let webview_ready_fut = webview_container.ready_fut()?;
-        executor.spawner().spawn_local(async move {
+    let handle = reactor.handle();
+    let webview_ready_fut = webview_container.ready_fut()?;
+    // 桥一直存活至事件循环结束。它被析构时注销消息处理函数，以拆开它与 WebView 之间的引用环
+    let bridge = Rc::new(RefCell::new(None));
+    handle.spawn_local({
+        let handle = handle.clone();
+        let bridge = Rc::clone(&bridge);
+        async move {
             let webview = webview_ready_fut.await.unwrap();
+            // 页面脚本以 await window.rustBridge.call('greet', '张三') 调用 Rust 处理函数
+            let attached = webview::attach_bridge(&webview, handle)?;
+            attached.register("greet", |name: String| Ok(format!("你好，{name}")));
+            bridge.borrow_mut().replace(attached);
             webview.navigate("https://www.minxing365.com")?;
             Ok::<_, Box<dyn Error>>(())
         }.map(|result| {
             if let Err(err) = result {
                 eprintln!("[app_main]{err}");
             }
-        }))?;
-        executor
-    };
+        })
+    })?;
     // 阻塞主线程，等待用户手动关闭主窗体
-    nwg::dispatch_thread_events_with_callback(move ||
-        // 以 win32 UI 的事件循环为【反应器】，对接 futures crate 的【执行器】
-        executor.run_until_stalled());
+    reactor.run(&mut host);
+    drop(bridge);
     Ok(())
 }
@@ -0,0 +1,146 @@
+use ::async_std::net::TcpStream;
+#[cfg(unix)]
+use ::async_std::os::unix::net::UnixStream;
+use ::futures::{io::{AsyncRead, AsyncWrite}, lock::Mutex};
+use ::std::{io::{Error as IoError, ErrorKind, Result as IoResult}, marker::PhantomData, sync::Arc};
+use crate::drone_model::{Coordinate, Emergency, Fault, Hovering, Idle, Midair, State};
+use super::{Command, Endpoint, RemoteState, Telemetry, protocol::{read_frame, write_frame}};
+/// 可搬运指令帧的双向字节流，比如 TCP 或 Unix 套接字
+pub(crate) trait Transport: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T> Transport for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+/// 【无人机】遥控端。一条连接可被多架`RemoteDrone`共享，但其上的指令是被逐条收发的。
+#[derive(Clone)]
+pub(crate) struct RemoteClient {
+    connection: Arc<Mutex<Box<dyn Transport>>>
+}
+impl RemoteClient {
+    pub(crate) fn new<T>(transport: T) -> Self
+    where T: Transport + 'static {
+        Self {
+            connection: Arc::new(Mutex::new(Box::new(transport)))
+        }
+    }
+    pub(crate) async fn connect(endpoint: &Endpoint) -> IoResult<Self> {
+        Ok(match endpoint {
+            Endpoint::Tcp(addr) => Self::new(TcpStream::connect(addr).await?),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Self::new(UnixStream::connect(path).await?)
+        })
+    }
+    /// 发送一条指令，并等待其遥测数据
+    pub(crate) async fn call(&self, command: Command) -> IoResult<Telemetry> {
+        let mut connection = self.connection.lock().await;
+        write_frame(&mut *connection, &command.encode()).await?;
+        let payload = read_frame(&mut *connection).await?
+            .ok_or_else(|| IoError::new(ErrorKind::UnexpectedEof, "仿真服务端关闭了连接"))?;
+        Ok(Telemetry::decode(payload)?)
+    }
+    /// 在地面上放一架新【无人机】
+    pub(crate) async fn spawn(&self, longitude: f32, latitude: f32) -> IoResult<RemoteDrone<Idle>> {
+        let (id, state, coordinate) = status(self.call(Command::Spawn { longitude, latitude }).await?)?;
+        match state {
+            RemoteState::Idle => Ok(RemoteDrone::new(self.clone(), id, coordinate, None)),
+            state => Err(unexpected(&state))
+        }
+    }
+    /// 中止`id`号【无人机】的飞行。与`RemoteDrone`的方法不同，它不会被类型状态所约束，所以能从别
+    /// 的连接打断另一条连接上正在进行的飞行。
+    pub(crate) async fn abort(&self, id: u32) -> IoResult<Telemetry> {
+        self.call(Command::Abort { id }).await
+    }
+}
+/// 从遥测数据里提取出【无人机】的状态与坐标
+fn status(telemetry: Telemetry) -> IoResult<(u32, RemoteState, Coordinate)> {
+    match telemetry {
+        Telemetry::Status { id, state, coordinate } => Ok((id, state, coordinate)),
+        Telemetry::Error { code, .. } => Err(IoError::new(ErrorKind::Other, code)),
+        telemetry => Err(IoError::new(ErrorKind::InvalidData, format!("非预期的遥测数据 {:?}", telemetry)))
+    }
+}
+fn unexpected(state: &RemoteState) -> IoError {
+    IoError::new(ErrorKind::InvalidData, format!("非预期的无人机状态 {:?}", state))
+}
+/// 远程【无人机】。与`Drone<S>`一样，用【类型·状态】约束可被发送的指令。
+///
+/// 【飞行·状态】由仿真服务端全权负责，所以每个【状态·过渡】成员方法都相当于`Drone<S>`的
+/// “指令 + `fly()`”组合。
+pub(crate) struct RemoteDrone<S>
+where S: State {
+    client: RemoteClient,
+    id: u32,
+    /// 最近一次被上报的坐标
+    coordinate: Coordinate,
+    /// 致使【无人机】切入【应急·状态】的故障原因
+    fault: Option<Fault>,
+    state: PhantomData<S>
+}
+/// 所有状态共有的成员方法
+impl<S> RemoteDrone<S>
+where S: State {
+    fn new(client: RemoteClient, id: u32, coordinate: Coordinate, fault: Option<Fault>) -> Self {
+        Self { client, id, coordinate, fault, state: PhantomData }
+    }
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+    /// 最近一次被上报的坐标
+    pub(crate) fn coordinate(&self) -> &Coordinate {
+        &self.coordinate
+    }
+    /// 发送【状态·过渡】指令，再按被上报的状态，过渡为`Ok(RemoteDrone<T>)`或`Err(RemoteDrone<Emergency>)`
+    async fn transit<T>(self, command: Command, expected: RemoteState) -> IoResult<Result<RemoteDrone<T>, RemoteDrone<Emergency>>>
+    where T: State {
+        let (id, state, coordinate) = status(self.client.call(command).await?)?;
+        match state {
+            RemoteState::Emergency(fault) => Ok(Err(RemoteDrone::new(self.client, id, coordinate, Some(fault)))),
+            state if state == expected => Ok(Ok(RemoteDrone::new(self.client, id, coordinate, None))),
+            state => Err(unexpected(&state))
+        }
+    }
+}
+impl RemoteDrone<Idle> {
+    /// 【起飞】对应`Drone<Idle>::take_off(altitude).fly(None)`
+    pub(crate) async fn take_off(self, altitude: f32) -> IoResult<Result<RemoteDrone<Hovering>, RemoteDrone<Emergency>>> {
+        let command = Command::TakeOff { id: self.id, altitude };
+        self.transit(command, RemoteState::Hovering).await
+    }
+}
+impl RemoteDrone<Hovering> {
+    /// 【着落】对应`Drone<Hovering>::land().fly(None)`
+    pub(crate) async fn land(self) -> IoResult<Result<RemoteDrone<Idle>, RemoteDrone<Emergency>>> {
+        let command = Command::Land { id: self.id };
+        self.transit(command, RemoteState::Idle).await
+    }
+    /// 【飘移】对应`Drone<Hovering>::move_to(destination).fly(None)`
+    pub(crate) async fn move_to(self, destination: Coordinate) -> IoResult<Result<RemoteDrone<Hovering>, RemoteDrone<Emergency>>> {
+        let command = Command::MoveTo { id: self.id, destination };
+        self.transit(command, RemoteState::Hovering).await
+    }
+    /// 【中止】对应`Drone<Hovering>::emergency(Fault::Aborted).descend(None)`
+    pub(crate) async fn abort(self) -> IoResult<RemoteDrone<Idle>> {
+        let command = Command::Abort { id: self.id };
+        self.transit(command, RemoteState::Idle).await?.map_err(|drone| unexpected(&RemoteState::Emergency(drone.fault().clone())))
+    }
+}
+impl RemoteDrone<Emergency> {
+    /// 致使【无人机】切入【应急·状态】的故障原因
+    pub(crate) fn fault(&self) -> &Fault {
+        self.fault.as_ref().expect("应急状态必有故障原因")
+    }
+    /// 【迫降】对应`Drone<Emergency>::descend(None)`
+    pub(crate) async fn descend(self) -> IoResult<RemoteDrone<Idle>> {
+        let command = Command::Abort { id: self.id };
+        self.transit(command, RemoteState::Idle).await?.map_err(|drone| unexpected(&RemoteState::Emergency(drone.fault().clone())))
+    }
+}
+/// 空中的【无人机】独有成员方法
+impl<S> RemoteDrone<S>
+where S: Midair {
+    /// 拍照，并返回拍摄坐标
+    pub(crate) async fn take_picture(&self) -> IoResult<Coordinate> {
+        match self.client.call(Command::Photo { id: self.id }).await? {
+            Telemetry::Picture { coordinate, .. } => Ok(coordinate),
+            telemetry => status(telemetry).and_then(|(_, state, _)| Err(unexpected(&state)))
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! 【无人机】远程操控：以`bytes`编解码的“长度前缀·定长字段”二进制帧协议、托管`Drone<S>`的本地
+//! 仿真服务端，与镜像了【类型·状态】接口的遥控端。仅需`localhost`的 TCP 或 Unix 套接字。
+mod client;
+mod protocol;
+mod server;
+pub(crate) use client::RemoteClient;
+pub(crate) use protocol::{Command, Endpoint, ErrorCode, RemoteState, Telemetry};
+pub(crate) use server::SimulatorServer;
@@ -0,0 +1,323 @@
+use ::bytes::{Buf, BufMut, Bytes, BytesMut};
+use ::futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ::std::{error::Error, fmt::{Display, Formatter, self}, io::{Error as IoError, ErrorKind, Result as IoResult}, str::FromStr};
+#[cfg(unix)]
+use ::std::path::PathBuf;
+use crate::drone_model::{Coordinate, Fault};
+/// 单帧有效载荷的长度上限。超长的帧被视作损坏的数据流。
+pub(crate) const MAX_FRAME_LEN: usize = 1024;
+// -------------------------
+// 帧格式：u32 大端字节序的有效载荷长度 + 有效载荷。
+// 有效载荷的首字节是操作码，其后是大端字节序的定长字段。
+// -------------------------
+const SPAWN: u8 = 0x01;
+const TAKE_OFF: u8 = 0x02;
+const MOVE_TO: u8 = 0x03;
+const LAND: u8 = 0x04;
+const PHOTO: u8 = 0x05;
+const ABORT: u8 = 0x06;
+const STATUS: u8 = 0x81;
+const PICTURE: u8 = 0x82;
+const ACK: u8 = 0x83;
+const ERROR: u8 = 0x8f;
+/// 遥控端 ➜ 仿真服务端的指令
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Command {
+    /// 在地面上放一架新【无人机】
+    Spawn { longitude: f32, latitude: f32 },
+    /// 起飞，并拉升至指定高度后悬浮
+    TakeOff { id: u32, altitude: f32 },
+    /// 飞往指定坐标后悬浮
+    MoveTo { id: u32, destination: Coordinate },
+    /// 着落
+    Land { id: u32 },
+    /// 拍照
+    Photo { id: u32 },
+    /// 中止：飞行中的【无人机】切入【应急·状态】；悬浮或应急的【无人机】受控迫降
+    Abort { id: u32 }
+}
+/// 仿真服务端 ➜ 遥控端的遥测数据
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Telemetry {
+    /// 【无人机】的状态与坐标
+    Status { id: u32, state: RemoteState, coordinate: Coordinate },
+    /// 照片的拍摄坐标
+    Picture { id: u32, coordinate: Coordinate },
+    /// 中止指令已被转交给飞行中的【无人机】
+    Ack { id: u32 },
+    /// 指令执行失败
+    Error { id: u32, code: ErrorCode }
+}
+/// 遥测数据里的【无人机】状态。【飞行·状态】是个过程，所以不会被上报。
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RemoteState {
+    Idle,
+    Hovering,
+    Emergency(Fault)
+}
+/// 指令执行失败的原因
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ErrorCode {
+    /// 没有该编号的【无人机】
+    UnknownDrone = 1,
+    /// 【无人机】当前所处的状态不支持该指令
+    InvalidState = 2,
+    /// 【无人机】正在执行另一条指令
+    Busy = 3,
+    /// 指令帧无法被解析
+    Malformed = 4
+}
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::UnknownDrone => write!(f, "没有该编号的无人机"),
+            ErrorCode::InvalidState => write!(f, "无人机当前所处的状态不支持该指令"),
+            ErrorCode::Busy => write!(f, "无人机正在执行另一条指令"),
+            ErrorCode::Malformed => write!(f, "指令帧无法被解析")
+        }
+    }
+}
+impl Error for ErrorCode {}
+/// 帧内容解析失败
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ProtocolError {
+    /// 有效载荷的字节数少于该操作码所需
+    Truncated,
+    /// 未知的操作码
+    UnknownOpcode(u8),
+    /// 有效载荷的末尾有多余的字节
+    TrailingBytes(usize),
+    /// 未知的枚举值
+    UnknownValue(&'static str, u8)
+}
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Truncated => write!(f, "有效载荷被截断了"),
+            ProtocolError::UnknownOpcode(opcode) => write!(f, "未知的操作码 {:#04x}", opcode),
+            ProtocolError::TrailingBytes(count) => write!(f, "有效载荷的末尾多出了 {} 个字节", count),
+            ProtocolError::UnknownValue(name, value) => write!(f, "未知的{}值 {}", name, value)
+        }
+    }
+}
+impl Error for ProtocolError {}
+impl From<ProtocolError> for IoError {
+    fn from(value: ProtocolError) -> Self {
+        IoError::new(ErrorKind::InvalidData, value)
+    }
+}
+type ProtocolResult<T> = Result<T, ProtocolError>;
+/// 确保有效载荷还剩至少`len`个字节
+fn ensure(payload: &Bytes, len: usize) -> ProtocolResult<()> {
+    if payload.remaining() < len {
+        return Err(ProtocolError::Truncated);
+    }
+    Ok(())
+}
+fn put_coordinate(buf: &mut BytesMut, coordinate: &Coordinate) {
+    buf.put_f32(coordinate.longitude());
+    buf.put_f32(coordinate.latitude());
+    buf.put_f32(coordinate.altitude());
+}
+fn get_coordinate(payload: &mut Bytes) -> ProtocolResult<Coordinate> {
+    ensure(payload, 12)?;
+    Ok(Coordinate::new(payload.get_f32(), payload.get_f32(), payload.get_f32()))
+}
+fn get_id(payload: &mut Bytes) -> ProtocolResult<u32> {
+    ensure(payload, 4)?;
+    Ok(payload.get_u32())
+}
+/// 确保有效载荷已被读尽
+fn finish<T>(payload: Bytes, value: T) -> ProtocolResult<T> {
+    if payload.has_remaining() {
+        return Err(ProtocolError::TrailingBytes(payload.remaining()));
+    }
+    Ok(value)
+}
+impl Command {
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(17);
+        match self {
+            Command::Spawn { longitude, latitude } => {
+                buf.put_u8(SPAWN);
+                buf.put_f32(*longitude);
+                buf.put_f32(*latitude);
+            },
+            Command::TakeOff { id, altitude } => {
+                buf.put_u8(TAKE_OFF);
+                buf.put_u32(*id);
+                buf.put_f32(*altitude);
+            },
+            Command::MoveTo { id, destination } => {
+                buf.put_u8(MOVE_TO);
+                buf.put_u32(*id);
+                put_coordinate(&mut buf, destination);
+            },
+            Command::Land { id } => {
+                buf.put_u8(LAND);
+                buf.put_u32(*id);
+            },
+            Command::Photo { id } => {
+                buf.put_u8(PHOTO);
+                buf.put_u32(*id);
+            },
+            Command::Abort { id } => {
+                buf.put_u8(ABORT);
+                buf.put_u32(*id);
+            }
+        }
+        buf.freeze()
+    }
+    pub(crate) fn decode(mut payload: Bytes) -> ProtocolResult<Self> {
+        ensure(&payload, 1)?;
+        let command = match payload.get_u8() {
+            SPAWN => {
+                ensure(&payload, 8)?;
+                Command::Spawn { longitude: payload.get_f32(), latitude: payload.get_f32() }
+            },
+            TAKE_OFF => {
+                let id = get_id(&mut payload)?;
+                ensure(&payload, 4)?;
+                Command::TakeOff { id, altitude: payload.get_f32() }
+            },
+            MOVE_TO => Command::MoveTo { id: get_id(&mut payload)?, destination: get_coordinate(&mut payload)? },
+            LAND => Command::Land { id: get_id(&mut payload)? },
+            PHOTO => Command::Photo { id: get_id(&mut payload)? },
+            ABORT => Command::Abort { id: get_id(&mut payload)? },
+            opcode => return Err(ProtocolError::UnknownOpcode(opcode))
+        };
+        finish(payload, command)
+    }
+}
+impl Telemetry {
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(19);
+        match self {
+            Telemetry::Status { id, state, coordinate } => {
+                buf.put_u8(STATUS);
+                buf.put_u32(*id);
+                // 状态码 + 故障码 + 故障参数（电机编号）
+                let (state, fault, motor) = match state {
+                    RemoteState::Idle => (0, 0, 0),
+                    RemoteState::Hovering => (1, 0, 0),
+                    RemoteState::Emergency(Fault::LinkLost) => (2, 1, 0),
+                    RemoteState::Emergency(Fault::Motor(motor)) => (2, 2, *motor),
+                    RemoteState::Emergency(Fault::PoisonedCoordinate) => (2, 3, 0),
+                    RemoteState::Emergency(Fault::Aborted) => (2, 4, 0)
+                };
+                buf.put_u8(state);
+                buf.put_u8(fault);
+                buf.put_u8(motor);
+                put_coordinate(&mut buf, coordinate);
+            },
+            Telemetry::Picture { id, coordinate } => {
+                buf.put_u8(PICTURE);
+                buf.put_u32(*id);
+                put_coordinate(&mut buf, coordinate);
+            },
+            Telemetry::Ack { id } => {
+                buf.put_u8(ACK);
+                buf.put_u32(*id);
+            },
+            Telemetry::Error { id, code } => {
+                buf.put_u8(ERROR);
+                buf.put_u32(*id);
+                buf.put_u8(*code as u8);
+            }
+        }
+        buf.freeze()
+    }
+    pub(crate) fn decode(mut payload: Bytes) -> ProtocolResult<Self> {
+        ensure(&payload, 1)?;
+        let telemetry = match payload.get_u8() {
+            STATUS => {
+                let id = get_id(&mut payload)?;
+                ensure(&payload, 3)?;
+                let state = match (payload.get_u8(), payload.get_u8(), payload.get_u8()) {
+                    (0, _, _) => RemoteState::Idle,
+                    (1, _, _) => RemoteState::Hovering,
+                    (2, 1, _) => RemoteState::Emergency(Fault::LinkLost),
+                    (2, 2, motor) => RemoteState::Emergency(Fault::Motor(motor)),
+                    (2, 3, _) => RemoteState::Emergency(Fault::PoisonedCoordinate),
+                    (2, 4, _) => RemoteState::Emergency(Fault::Aborted),
+                    (2, fault, _) => return Err(ProtocolError::UnknownValue("故障码", fault)),
+                    (state, _, _) => return Err(ProtocolError::UnknownValue("状态码", state))
+                };
+                Telemetry::Status { id, state, coordinate: get_coordinate(&mut payload)? }
+            },
+            PICTURE => Telemetry::Picture { id: get_id(&mut payload)?, coordinate: get_coordinate(&mut payload)? },
+            ACK => Telemetry::Ack { id: get_id(&mut payload)? },
+            ERROR => {
+                let id = get_id(&mut payload)?;
+                ensure(&payload, 1)?;
+                let code = match payload.get_u8() {
+                    1 => ErrorCode::UnknownDrone,
+                    2 => ErrorCode::InvalidState,
+                    3 => ErrorCode::Busy,
+                    4 => ErrorCode::Malformed,
+                    code => return Err(ProtocolError::UnknownValue("错误码", code))
+                };
+                Telemetry::Error { id, code }
+            },
+            opcode => return Err(ProtocolError::UnknownOpcode(opcode))
+        };
+        finish(payload, telemetry)
+    }
+}
+/// 读取一帧。若对端已关闭了连接，则返回`None`。
+pub(crate) async fn read_frame<R>(reader: &mut R) -> IoResult<Option<Bytes>>
+where R: AsyncRead + Unpin {
+    let mut header = [0_u8; 4];
+    match reader.read_exact(&mut header).await {
+        Ok(()) => (),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err)
+    }
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(IoError::new(ErrorKind::InvalidData, format!("帧长度 {} 超过了上限 {}", len, MAX_FRAME_LEN)));
+    }
+    let mut payload = BytesMut::zeroed(len);
+    reader.read_exact(&mut payload).await?;
+    Ok(Some(payload.freeze()))
+}
+/// 写入一帧
+pub(crate) async fn write_frame<W>(writer: &mut W, payload: &[u8]) -> IoResult<()>
+where W: AsyncWrite + Unpin {
+    let mut frame = BytesMut::with_capacity(4 + payload.len());
+    frame.put_u32(payload.len() as u32);
+    frame.put_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+/// 仿真服务端的监听地址：`tcp://127.0.0.1:7878`或`unix:///tmp/drone.sock`。缺省协议头时，按 TCP 地址处理。
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Endpoint {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf)
+}
+impl FromStr for Endpoint {
+    type Err = IoError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(Endpoint::Tcp(addr.to_string()));
+        }
+        if let Some(_path) = s.strip_prefix("unix://") {
+            #[cfg(unix)]
+            return Ok(Endpoint::Unix(PathBuf::from(_path)));
+            #[cfg(not(unix))]
+            return Err(IoError::new(ErrorKind::Unsupported, "当前平台不支持 Unix 套接字"));
+        }
+        Ok(Endpoint::Tcp(s.to_string()))
+    }
+}
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "tcp://{}", addr),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display())
+        }
+    }
+}
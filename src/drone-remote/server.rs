@@ -0,0 +1,202 @@
+use ::async_std::{net::TcpListener, task};
+#[cfg(unix)]
+use ::async_std::os::unix::net::UnixListener;
+use ::futures::{io::{AsyncRead, AsyncWrite}, StreamExt};
+use ::std::{collections::HashMap, io::Result as IoResult, mem, sync::{Arc, Mutex, MutexGuard, atomic::{AtomicU32, Ordering}}};
+use crate::drone_model::{Coordinate, Drone, Emergency, Fault, FaultInjector, Hovering, Idle};
+use super::{Command, Endpoint, ErrorCode, RemoteState, Telemetry, protocol::{read_frame, write_frame}};
+/// 被仿真服务端托管的【无人机】。编译时的【类型·状态】在此被“擦除”为运行时的枚举值。
+enum HostedDrone {
+    Idle(Drone<Idle>),
+    Hovering(Drone<Hovering>),
+    Emergency(Drone<Emergency>),
+    /// 正在执行指令（比如，飞行中）。保留【故障注入器】，以便中止指令能打断飞行。
+    Busy(FaultInjector)
+}
+impl HostedDrone {
+    fn fault_injector(&self) -> FaultInjector {
+        match self {
+            HostedDrone::Idle(drone) => drone.fault_injector(),
+            HostedDrone::Hovering(drone) => drone.fault_injector(),
+            HostedDrone::Emergency(drone) => drone.fault_injector(),
+            HostedDrone::Busy(faults) => faults.clone()
+        }
+    }
+    fn status(&self, id: u32) -> Telemetry {
+        let (state, coordinate) = match self {
+            HostedDrone::Idle(drone) => (RemoteState::Idle, drone.coordinate()),
+            HostedDrone::Hovering(drone) => (RemoteState::Hovering, drone.coordinate()),
+            HostedDrone::Emergency(drone) => (RemoteState::Emergency(drone.fault().clone()), drone.coordinate()),
+            HostedDrone::Busy(_) => return Telemetry::Error { id, code: ErrorCode::Busy }
+        };
+        Telemetry::Status { id, state, coordinate }
+    }
+}
+impl From<Result<Drone<Hovering>, Drone<Emergency>>> for HostedDrone {
+    fn from(value: Result<Drone<Hovering>, Drone<Emergency>>) -> Self {
+        value.map_or_else(HostedDrone::Emergency, HostedDrone::Hovering)
+    }
+}
+impl From<Result<Drone<Idle>, Drone<Emergency>>> for HostedDrone {
+    fn from(value: Result<Drone<Idle>, Drone<Emergency>>) -> Self {
+        value.map_or_else(HostedDrone::Emergency, HostedDrone::Idle)
+    }
+}
+/// 【无人机】仿真服务端：经由 TCP 或 Unix 套接字托管多架【无人机】。
+///
+/// 同一条连接上的指令被逐条执行，即飞行指令会阻塞该连接直至飞行结束；但，其它连接依旧可以
+/// 向飞行中的【无人机】发送中止指令。
+#[derive(Clone, Default)]
+pub(crate) struct SimulatorServer {
+    drones: Arc<Mutex<HashMap<u32, HostedDrone>>>,
+    next_id: Arc<AtomicU32>
+}
+impl SimulatorServer {
+    /// 监听`endpoint`，直至出错
+    pub(crate) async fn serve(self, endpoint: &Endpoint) -> IoResult<()> {
+        match endpoint {
+            Endpoint::Tcp(addr) => self.serve_tcp(TcpListener::bind(addr).await?).await,
+            #[cfg(unix)]
+            Endpoint::Unix(path) => self.serve_unix(UnixListener::bind(path).await?).await
+        }
+    }
+    pub(crate) async fn serve_tcp(self, listener: TcpListener) -> IoResult<()> {
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            self.clone().spawn_connection(stream?);
+        }
+        Ok(())
+    }
+    #[cfg(unix)]
+    pub(crate) async fn serve_unix(self, listener: UnixListener) -> IoResult<()> {
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            self.clone().spawn_connection(stream?);
+        }
+        Ok(())
+    }
+    fn spawn_connection<T>(self, stream: T)
+    where T: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+        task::spawn(async move {
+            if let Err(err) = self.handle_connection(stream).await {
+                eprintln!("[SimulatorServer]{}", err);
+            }
+        });
+    }
+    /// 逐帧读取指令，执行，再回写遥测数据
+    pub(crate) async fn handle_connection<T>(&self, mut stream: T) -> IoResult<()>
+    where T: AsyncRead + AsyncWrite + Unpin {
+        while let Some(payload) = read_frame(&mut stream).await? {
+            let telemetry = match Command::decode(payload) {
+                Ok(command) => self.execute(command).await,
+                Err(err) => {
+                    eprintln!("[SimulatorServer]{}", err);
+                    Telemetry::Error { id: 0, code: ErrorCode::Malformed }
+                }
+            };
+            write_frame(&mut stream, &telemetry.encode()).await?;
+        }
+        Ok(())
+    }
+    async fn execute(&self, command: Command) -> Telemetry {
+        match command {
+            Command::Spawn { longitude, latitude } => {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+                let drone = HostedDrone::Idle(Drone::new(Coordinate::new(longitude, latitude, 0_f32)));
+                let telemetry = drone.status(id);
+                self.put(id, drone);
+                telemetry
+            },
+            Command::Photo { id } => self.with_drone(id, |drone| match drone {
+                HostedDrone::Hovering(drone) => {
+                    drone.take_picture();
+                    Telemetry::Picture { id, coordinate: drone.coordinate() }
+                },
+                HostedDrone::Emergency(drone) => {
+                    drone.take_picture();
+                    Telemetry::Picture { id, coordinate: drone.coordinate() }
+                },
+                HostedDrone::Idle(_) => Telemetry::Error { id, code: ErrorCode::InvalidState },
+                HostedDrone::Busy(_) => Telemetry::Error { id, code: ErrorCode::Busy }
+            }).unwrap_or(Telemetry::Error { id, code: ErrorCode::UnknownDrone }),
+            Command::Abort { id } => {
+                // 打断飞行中的【无人机】，由它自己切入【应急·状态】
+                let in_flight = self.with_drone(id, |drone| match drone {
+                    HostedDrone::Busy(faults) => {
+                        faults.inject(Fault::Aborted);
+                        Some(Telemetry::Ack { id })
+                    },
+                    _ => None
+                }).flatten();
+                match in_flight {
+                    Some(telemetry) => telemetry,
+                    None => self.operate(id, command).await
+                }
+            },
+            Command::TakeOff { id, .. } | Command::MoveTo { id, .. } | Command::Land { id } => self.operate(id, command).await
+        }
+    }
+    /// 取出【无人机】，执行需要（异步）等待的指令，再放回【无人机】
+    async fn operate(&self, id: u32, command: Command) -> Telemetry {
+        let drone = match self.take(id) {
+            Ok(drone) => drone,
+            Err(code) => return Telemetry::Error { id, code }
+        };
+        let drone = match (drone, command) {
+            (HostedDrone::Idle(drone), Command::TakeOff { altitude, .. }) => drone.take_off(altitude).fly(None).await.into(),
+            (HostedDrone::Hovering(drone), Command::MoveTo { destination, .. }) => drone.move_to(destination).fly(None).await.into(),
+            (HostedDrone::Hovering(drone), Command::Land { .. }) => drone.land().fly(None).await.into(),
+            (HostedDrone::Hovering(drone), Command::Abort { .. }) => HostedDrone::Idle(drone.emergency(Fault::Aborted).descend(None).await),
+            (HostedDrone::Emergency(drone), Command::Abort { .. }) => HostedDrone::Idle(drone.descend(None).await),
+            (drone @ HostedDrone::Idle(_), Command::Abort { .. }) => drone,
+            (drone, _) => {
+                self.put(id, drone);
+                return Telemetry::Error { id, code: ErrorCode::InvalidState };
+            }
+        };
+        self.settle(id, drone)
+    }
+    /// 原地读取【无人机】。若没有该编号的【无人机】，则返回`None`
+    fn with_drone<R>(&self, id: u32, callback: impl FnOnce(&HostedDrone) -> R) -> Option<R> {
+        get_mutex_lock!(self.drones, |drones: &mut MutexGuard<'_, HashMap<u32, HostedDrone>>| {
+            drones.get(&id).map(callback)
+        })
+    }
+    /// 取出【无人机】，并将其标记为`Busy`
+    fn take(&self, id: u32) -> Result<HostedDrone, ErrorCode> {
+        get_mutex_lock!(self.drones, |drones: &mut MutexGuard<'_, HashMap<u32, HostedDrone>>| {
+            match drones.get_mut(&id) {
+                None => Err(ErrorCode::UnknownDrone),
+                Some(HostedDrone::Busy(_)) => Err(ErrorCode::Busy),
+                Some(drone) => {
+                    let faults = drone.fault_injector();
+                    Ok(mem::replace(drone, HostedDrone::Busy(faults)))
+                }
+            }
+        })
+    }
+    /// 放回执行完指令的【无人机】，并上报其状态。
+    ///
+    /// 中止指令可能在飞行刚结束、而【无人机】尚未被放回时被应答。为了不令该故障残留至下一条指令，
+    /// 在同一把锁内兑现它：悬浮的【无人机】切入【应急·状态】；其它状态的【无人机】已不在飞行中，
+    /// 丢弃该故障，就像中止一架待命的【无人机】一样。
+    fn settle(&self, id: u32, drone: HostedDrone) -> Telemetry {
+        get_mutex_lock!(self.drones, |drones: &mut MutexGuard<'_, HashMap<u32, HostedDrone>>| {
+            let drone = match drone {
+                HostedDrone::Hovering(drone) => drone.check_faults().into(),
+                drone => {
+                    drone.fault_injector().take();
+                    drone
+                }
+            };
+            let telemetry = drone.status(id);
+            drones.insert(id, drone);
+            telemetry
+        })
+    }
+    fn put(&self, id: u32, drone: HostedDrone) {
+        get_mutex_lock!(self.drones, |drones: &mut MutexGuard<'_, HashMap<u32, HostedDrone>>| {
+            drones.insert(id, drone);
+        });
+    }
+}
@@ -0,0 +1,216 @@
+//! 外部函数的【错误约定】（跨`bin`）复用工具箱：将 C 端的返回值与 errno 解码为`Result<T, FfiError>`。
+//!
+//! 挂载方式（必须被挂载为`crate`根下的`ffi_error`模块，因为宏内部经由`$crate::ffi_error`路径引用本模块的类型）：
+//! ```ignore
+//! #[path = "../ffi_error.rs"]
+//! #[macro_use]
+//! mod ffi_error;
+//! ```
+//! 【错误约定】由宏调用里的`#[error_convention(约定名)]`声明。包括：
+//! 1. `nonzero` —— 返回值非零即是错误码，比如`errno_t`。成功时的值是`()`
+//! 2. `negative_errno` —— 负的返回值是被取反的 errno，比如 Linux 系统调用。成功时的值是返回值本身
+//! 3. `null` —— 空指针表示出错，且 errno 已被设置。成功时的值是`NonNull<T>`
+//! 4. `errno` —— 调用前清零 errno，调用后 errno 非零即表示出错。成功时的值是返回值本身
+//!
+//! 宏包括：
+//! 1. `error_convention!()` —— 由约定名得到【错误约定】类型
+//! 2. `ffi_result_fn!()` —— 为无回调函数的（被导入）外部函数生成返回`Result<T, FfiError>`的包装函数。
+//!    带回调函数的外部函数则由`ffi_closure_shim_fn!()`生成包装函数。
+use ::libc::c_int;
+use ::std::{error::Error, fmt::{Display, Formatter, Result as FmtResult}, io::Error as IoError, ptr::NonNull};
+/// 外部函数调用失败。携带外部函数名与被解码的 OS 错误
+#[derive(Debug)]
+pub struct FfiError {
+    function: &'static str,
+    os_error: IoError
+}
+impl FfiError {
+    /// 由外部函数名与 OS 错误构造
+    pub fn new(function: &'static str, os_error: IoError) -> Self {
+        Self { function, os_error }
+    }
+    /// 出错的外部函数名
+    pub fn function(&self) -> &'static str {
+        self.function
+    }
+    /// 被解码的 OS 错误
+    pub fn os_error(&self) -> &IoError {
+        &self.os_error
+    }
+    /// OS 错误的原始错误码，即 errno
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.os_error.raw_os_error()
+    }
+}
+impl Display for FfiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "外部函数 {} 调用失败：{}", self.function, self.os_error)
+    }
+}
+impl Error for FfiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.os_error)
+    }
+}
+impl From<FfiError> for IoError {
+    fn from(err: FfiError) -> Self {
+        IoError::new(err.os_error.kind(), err)
+    }
+}
+/// 【错误约定】。`T`是（被导入）外部函数的返回值类型
+pub(crate) trait ErrorConvention<T> {
+    /// 成功时的值
+    type Ok;
+    /// 在调用外部函数之前执行，比如清零 errno
+    fn before_call() {}
+    /// 在外部函数返回之后，立即解码它的返回值
+    fn check(function: &'static str, ret: T) -> Result<Self::Ok, FfiError>;
+}
+// 各`bin`仅用到其中的一部分【错误约定】
+/// 返回值非零即是错误码
+#[allow(dead_code)]
+pub(crate) enum NonZero {}
+/// 负的返回值是被取反的 errno
+#[allow(dead_code)]
+pub(crate) enum NegativeErrno {}
+/// 空指针表示出错，且 errno 已被设置
+#[allow(dead_code)]
+pub(crate) enum Null {}
+/// errno 非零即表示出错
+#[allow(dead_code)]
+pub(crate) enum Errno {}
+impl ErrorConvention<c_int> for NonZero {
+    type Ok = ();
+    fn check(function: &'static str, ret: c_int) -> Result<Self::Ok, FfiError> {
+        match ret {
+            0 => Ok(()),
+            code => Err(FfiError::new(function, IoError::from_raw_os_error(code)))
+        }
+    }
+}
+macro_rules! impl_negative_errno {
+    ($( $int: ty ),*) => {$(
+        impl ErrorConvention<$int> for NegativeErrno {
+            type Ok = $int;
+            fn check(function: &'static str, ret: $int) -> Result<Self::Ok, FfiError> {
+                if ret >= 0 {
+                    return Ok(ret);
+                }
+                let errno = ret.checked_neg().and_then(|errno| c_int::try_from(errno).ok()).unwrap_or(c_int::MAX);
+                Err(FfiError::new(function, IoError::from_raw_os_error(errno)))
+            }
+        }
+    )*};
+}
+// c_int、c_long 与 ssize_t 都是它们之一的类型别名
+impl_negative_errno!(i32, i64, isize);
+impl<T> ErrorConvention<*mut T> for Null {
+    type Ok = NonNull<T>;
+    fn check(function: &'static str, ret: *mut T) -> Result<Self::Ok, FfiError> {
+        NonNull::new(ret).ok_or_else(|| FfiError::new(function, IoError::last_os_error()))
+    }
+}
+impl<T> ErrorConvention<*const T> for Null {
+    type Ok = NonNull<T>;
+    fn check(function: &'static str, ret: *const T) -> Result<Self::Ok, FfiError> {
+        <Self as ErrorConvention<*mut T>>::check(function, ret as *mut T)
+    }
+}
+impl<T> ErrorConvention<T> for Errno {
+    type Ok = T;
+    fn before_call() {
+        set_errno(0);
+    }
+    fn check(function: &'static str, ret: T) -> Result<Self::Ok, FfiError> {
+        match errno() {
+            0 => Ok(ret),
+            errno => Err(FfiError::new(function, IoError::from_raw_os_error(errno)))
+        }
+    }
+}
+#[cfg(any(target_os = "linux", target_os = "emscripten", target_os = "redox"))]
+use ::libc::__errno_location as errno_location;
+#[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+use ::libc::__errno as errno_location;
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly"))]
+use ::libc::__error as errno_location;
+#[cfg(windows)]
+extern "C" {
+    #[link_name = "_errno"]
+    fn errno_location() -> *mut c_int;
+}
+/// 当前线程的 errno
+pub(crate) fn errno() -> c_int {
+    unsafe { *errno_location() }
+}
+/// 设置当前线程的 errno
+pub(crate) fn set_errno(value: c_int) {
+    unsafe { *errno_location() = value }
+}
+/// 由约定名得到【错误约定】类型
+#[allow(unused_macro_rules)]
+macro_rules! error_convention {
+    (nonzero) => { $crate::ffi_error::NonZero };
+    (negative_errno) => { $crate::ffi_error::NegativeErrno };
+    (null) => { $crate::ffi_error::Null };
+    (errno) => { $crate::ffi_error::Errno };
+}
+/// 为无回调函数的（被导入）外部函数生成同名、同形参，但返回`Result<T, FfiError>`的包装函数。
+/// 若外部函数的形参含有裸指针，则应以`unsafe fn`声明，令包装函数也是 unsafe 的。
+/// 若外部函数的链接符号与函数名不同，则在`#[error_convention]`之前以`#[link_name = "符号"]`声明。
+/// ```ignore
+/// ffi_result_fn! {
+///     #[error_convention(null)]
+///     #[cfg(unix)]
+///     unsafe fn localtime_r(time: *const time_t, tm: *mut tm) -> *mut tm;
+///     #[link_name = "_localtime64_s"]
+///     #[error_convention(nonzero)]
+///     #[cfg(windows)]
+///     unsafe fn localtime_s(tm: *mut tm, time: *const time_t) -> errno_t;
+/// }
+/// ```
+#[allow(unused_macros, unused_macro_rules)]
+macro_rules! ffi_result_fn {
+    () => {};
+    (
+        $( #[link_name = $link_name: literal] )?
+        #[error_convention($convention: ident)]
+        $( #[$attr: meta] )*
+        $vis: vis unsafe fn $c_fn_name: ident ( $( $param: ident : $param_type: ty ),* $(,)? ) $( -> $c_fn_ret: ty )?;
+        $( $rest: tt )*
+    ) => {
+        $( #[$attr] )*
+        $vis unsafe fn $c_fn_name($( $param: $param_type ),*) -> ffi_result_fn!(@result $convention [$( $c_fn_ret )?]) {
+            ffi_result_fn!(@call $convention [$( $link_name )?] $c_fn_name [$( $c_fn_ret )?] ($( $param: $param_type ),*))
+        }
+        ffi_result_fn!($( $rest )*);
+    };
+    (
+        $( #[link_name = $link_name: literal] )?
+        #[error_convention($convention: ident)]
+        $( #[$attr: meta] )*
+        $vis: vis fn $c_fn_name: ident ( $( $param: ident : $param_type: ty ),* $(,)? ) $( -> $c_fn_ret: ty )?;
+        $( $rest: tt )*
+    ) => {
+        $( #[$attr] )*
+        $vis fn $c_fn_name($( $param: $param_type ),*) -> ffi_result_fn!(@result $convention [$( $c_fn_ret )?]) {
+            ffi_result_fn!(@call $convention [$( $link_name )?] $c_fn_name [$( $c_fn_ret )?] ($( $param: $param_type ),*))
+        }
+        ffi_result_fn!($( $rest )*);
+    };
+    (@result $convention: ident [$( $c_fn_ret: ty )?]) => {
+        Result<<error_convention!($convention) as $crate::ffi_error::ErrorConvention<ffi_result_fn!(@or_unit $( $c_fn_ret )?)>>::Ok, $crate::ffi_error::FfiError>
+    };
+    (@call $convention: ident [$( $link_name: literal )?] $c_fn_name: ident [$( $c_fn_ret: ty )?] ($( $param: ident : $param_type: ty ),*)) => {{
+        extern "C" {
+            $( #[link_name = $link_name] )?
+            fn $c_fn_name($( $param: $param_type ),*) $( -> $c_fn_ret )?;
+        }
+        type Convention = error_convention!($convention);
+        <Convention as $crate::ffi_error::ErrorConvention<ffi_result_fn!(@or_unit $( $c_fn_ret )?)>>::before_call();
+        let ret = unsafe { $c_fn_name($( $param ),*) };
+        <Convention as $crate::ffi_error::ErrorConvention<ffi_result_fn!(@or_unit $( $c_fn_ret )?)>>::check(stringify!($c_fn_name), ret)
+    }};
+    (@or_unit) => { () };
+    (@or_unit $c_fn_ret: ty) => { $c_fn_ret };
+}
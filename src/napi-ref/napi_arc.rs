@@ -0,0 +1,96 @@
+//! 可跨线程传递的 NapiRc：`NapiArc`。
+//!
+//! `NapiRc`包装了`Rc`，所以永远离不开 JS 线程。但原生模块常在 rayon 或异步运行时的工作线程上处理业务，且需要持有
+//! JS 堆对象。`NapiArc`与`NapiRc`一样是“二段式”引用计数，只是 Rust 端的【引用个数】由`Arc`原子地跟踪：
+//! 1. 任何线程都可克隆与析构`NapiArc`。但只有 JS 线程能取出它指向的`napi_value`。
+//! 2. 当最后一个`NapiArc`在 JS 线程上被析构时，直接调用`napi_delete_reference()`；在其它线程上被析构时，
+//!    则经由线程安全函数将`napi_delete_reference()`排入 JS 线程的事件队列，而不是在错误的线程上调用它。
+//! 3. 工作线程也可经由`NapiArc::call()`将使用 JS 堆对象的闭包排入 JS 线程的事件队列。
+//! 4. 启用 tracker 登记簿之后，`napi_ref`被登记，且`NapiArc`的个数被跟踪。
+use ::std::{fmt::{Debug, Formatter, Result as FmtResult}, sync::Arc, thread::{self, ThreadId}};
+use crate::{nj_sys::{napi_call_threadsafe_function, napi_create_reference, napi_create_threadsafe_function, napi_delete_reference,
+    napi_get_reference_value, napi_ref, napi_release_threadsafe_function, napi_threadsafe_function, napi_value}, tracker::Tracked};
+/// 仅在 JS 线程上被使用的 napi_ref
+struct JsThreadRef(napi_ref);
+// 安全：JsThreadRef 可随 NapiArc 去往任何线程，但 napi_ref 只会在 owner 线程上被解引用与析构
+unsafe impl Send for JsThreadRef {}
+unsafe impl Sync for JsThreadRef {}
+impl JsThreadRef {
+    /// 以方法（而不是字段访问）取出 napi_ref，以免闭包仅捕获不是 Send 的字段
+    fn into_inner(self) -> napi_ref {
+        self.0
+    }
+}
+struct Shared {
+    reference: JsThreadRef,
+    /// JS 线程，即创建 napi_ref 的线程
+    owner: ThreadId,
+    /// 将调用排入 JS 线程事件队列的线程安全函数。被析构时，由 JS 线程释放它
+    function: Option<napi_threadsafe_function>,
+    tracked: Tracked
+}
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let reference = JsThreadRef(self.reference.0);
+        let function = self.function.take().unwrap();
+        if thread::current().id() == self.owner {
+            #[cfg(debug_assertions)]
+            println!("[NapiArc::drop]在 JS 线程上引用计数归零了，级联析构 N-API 端的引用");
+            napi_delete_reference(reference.into_inner());
+            napi_release_threadsafe_function(function);
+        } else {
+            #[cfg(debug_assertions)]
+            println!("[NapiArc::drop]在工作线程上引用计数归零了，将析构 N-API 端引用的调用排入 JS 线程的事件队列");
+            napi_call_threadsafe_function(&function.clone(), Box::new(move || {
+                napi_delete_reference(reference.into_inner());
+                napi_release_threadsafe_function(function);
+            }));
+        }
+    }
+}
+pub(crate) struct NapiArc(Arc<Shared>);
+impl NapiArc {
+    /// 须在 JS 线程上调用
+    #[track_caller]
+    pub(crate) fn new(value: napi_value) -> Self {
+        let reference = napi_create_reference(value, 1);
+        NapiArc(Arc::new(Shared {
+            reference: JsThreadRef(reference),
+            owner: thread::current().id(),
+            function: Some(napi_create_threadsafe_function()),
+            tracked: Tracked::new("NapiArc", &reference)
+        }))
+    }
+    /// 若在 JS 线程上，则取出被强引用的 JS 堆对象；否则返回 None
+    pub(crate) fn value(&self) -> Option<napi_value> {
+        self.is_js_thread().then(|| napi_get_reference_value(self.0.reference.0).expect("被强引用的 JS 堆对象不应被回收"))
+    }
+    pub(crate) fn is_js_thread(&self) -> bool {
+        thread::current().id() == self.0.owner
+    }
+    /// 可在任何线程上调用。`f`会在 JS 线程的事件循环里被调用，且被克隆的 NapiArc 令 JS 堆对象一直存活至那时
+    pub(crate) fn call(&self, f: impl FnOnce(napi_value) + Send + 'static) {
+        let this = self.clone();
+        let function = self.0.function.as_ref().unwrap();
+        napi_call_threadsafe_function(function, Box::new(move || f(this.value().unwrap())));
+    }
+    pub(crate) fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.0)
+    }
+}
+impl Clone for NapiArc {
+    fn clone(&self) -> Self {
+        self.0.tracked.cloned();
+        NapiArc(Arc::clone(&self.0))
+    }
+}
+impl Drop for NapiArc {
+    fn drop(&mut self) {
+        self.0.tracked.released();
+    }
+}
+impl Debug for NapiArc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("NapiArc").field("reference", &self.0.reference.0).field("strong", &Arc::strong_count(&self.0)).finish()
+    }
+}
@@ -0,0 +1,549 @@
+//! serde 与`napi_value`之间的桥：`to_value()`与`from_value()`。
+//!
+//! 任何实现了`Serialize`与`Deserialize`的 Rust 类型都可经由`napi_value`往返。映射规则与`serde_json`相仿：
+//! 1. 结构体与映射表 —— JS 对象。映射表的键须是字符串、字符或整数（被转换为字符串）。
+//! 2. 序列、元组与元组结构体 —— JS 数组。字节串也是由数字组成的 JS 数组。
+//! 3. 整数 —— 在安全整数（`Number.MAX_SAFE_INTEGER`）范围内的是 JS 数字，超出的是 BigInt。反序列化时，JS 数字与
+//!    BigInt 都被接受，且由 serde 检查其是否在目标类型的范围内。
+//! 4. `None`与单元 —— `null`。反序列化时，`null`与`undefined`都被接受。
+//! 5. 单元枚举值 —— 变体名字符串；其它枚举值 —— 以变体名为唯一属性名的 JS 对象，即 serde 的外部标签表示法。
+//!
+//! 类型不符等错误都被标注了出错值在根值里的路径，比如`$.items[2].name: invalid type: ...`。
+#![allow(dead_code)]
+use ::serde::{de::{self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor},
+    forward_to_deserialize_any, ser::{self, Impossible, Serialize}};
+use ::std::{error::Error as StdError, fmt::{Display, Formatter, Result as FmtResult}};
+use crate::nj_sys::{self, napi_status, napi_value, napi_valuetype};
+/// 2^53 - 1
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+/// 被标注了路径的错误
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Error {
+    /// 由外至内的路径片段，形如`.name`或`[2]`
+    path: Vec<String>,
+    message: String
+}
+impl Error {
+    fn new(message: impl Display) -> Self {
+        Error { path: Vec::new(), message: message.to_string() }
+    }
+    /// 出错值在根值里的路径，形如`$.items[2].name`
+    pub(crate) fn path(&self) -> String {
+        format!("${}", self.path.concat())
+    }
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+    /// 随错误向外冒泡，逐层地在路径前端添加片段
+    fn at(mut self, segment: impl Display) -> Self {
+        self.path.insert(0, segment.to_string());
+        self
+    }
+    fn at_field(self, name: &str) -> Self {
+        self.at(format_args!(".{}", name))
+    }
+    fn at_index(self, index: usize) -> Self {
+        self.at(format_args!("[{}]", index))
+    }
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.path(), self.message)
+    }
+}
+impl StdError for Error {}
+impl ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::new(message)
+    }
+}
+impl de::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::new(message)
+    }
+}
+impl From<napi_status> for Error {
+    fn from(status: napi_status) -> Self {
+        Error::new(format_args!("N-API 调用失败：{:?}", status))
+    }
+}
+pub(crate) fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<napi_value, Error> {
+    value.serialize(Serializer)
+}
+pub(crate) fn from_value<T: DeserializeOwned>(value: napi_value) -> Result<T, Error> {
+    T::deserialize(Deserializer(value))
+}
+// ----- 序列化 -----
+pub(crate) struct Serializer;
+impl Serializer {
+    fn integer(value: i128) -> Result<napi_value, Error> {
+        Ok(if (-MAX_SAFE_INTEGER as i128..=MAX_SAFE_INTEGER as i128).contains(&value) {
+            nj_sys::napi_create_double(value as f64)
+        } else if let Ok(value) = i64::try_from(value) {
+            nj_sys::napi_create_bigint_int64(value)
+        } else if let Ok(value) = u64::try_from(value) {
+            nj_sys::napi_create_bigint_uint64(value)
+        } else {
+            return Err(Error::new(format_args!("整数 {} 超出了 BigInt 的表示范围", value)));
+        })
+    }
+    /// 外部标签表示法：{ variant: value }
+    fn tagged(variant: &str, value: napi_value) -> Result<napi_value, Error> {
+        let object = nj_sys::napi_create_object();
+        nj_sys::napi_set_named_property(object, variant, value)?;
+        Ok(object)
+    }
+}
+impl ser::Serializer for Serializer {
+    type Ok = napi_value;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObject;
+    fn serialize_bool(self, v: bool) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_get_boolean(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_i128(self, v: i128) -> Result<napi_value, Error> {
+        Self::integer(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<napi_value, Error> {
+        Self::integer(v.into())
+    }
+    fn serialize_u128(self, v: u128) -> Result<napi_value, Error> {
+        Self::integer(i128::try_from(v).map_err(|_| Error::new(format_args!("整数 {} 超出了 BigInt 的表示范围", v)))?)
+    }
+    fn serialize_f32(self, v: f32) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_create_double(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_create_double(v))
+    }
+    fn serialize_char(self, v: char) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_create_string_utf8(v.encode_utf8(&mut [0; 4])))
+    }
+    fn serialize_str(self, v: &str) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_create_string_utf8(v))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<napi_value, Error> {
+        v.serialize(self)
+    }
+    fn serialize_none(self) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_get_null())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<napi_value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<napi_value, Error> {
+        Ok(nj_sys::napi_get_null())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<napi_value, Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<napi_value, Error> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<napi_value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<napi_value, Error> {
+        let value = value.serialize(Serializer).map_err(|err| err.at_field(variant))?;
+        Self::tagged(variant, value)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray { array: nj_sys::napi_create_array(), len: 0, variant: None })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray { variant: Some(variant), ..self.serialize_seq(Some(len))? })
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject { object: nj_sys::napi_create_object(), key: None, variant: None })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject { variant: Some(variant), ..self.serialize_map(Some(len))? })
+    }
+}
+pub(crate) struct SerializeArray {
+    array: napi_value,
+    len: u32,
+    /// 元组枚举值的变体名
+    variant: Option<&'static str>
+}
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let element = value.serialize(Serializer).map_err(|err| err.at_index(self.len as usize))?;
+        nj_sys::napi_set_element(self.array, self.len, element)?;
+        self.len += 1;
+        Ok(())
+    }
+    fn finish(self) -> Result<napi_value, Error> {
+        match self.variant {
+            Some(variant) => Serializer::tagged(variant, self.array),
+            None => Ok(self.array)
+        }
+    }
+}
+macro_rules! impl_serialize_array {
+    ($( $trait: ident :: $method: ident ),*) => {$(
+        impl ser::$trait for SerializeArray {
+            type Ok = napi_value;
+            type Error = Error;
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                self.push(value).map_err(|err| match self.variant {
+                    Some(variant) => err.at_field(variant),
+                    None => err
+                })
+            }
+            fn end(self) -> Result<napi_value, Error> {
+                self.finish()
+            }
+        }
+    )*};
+}
+impl_serialize_array!(SerializeSeq::serialize_element, SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field, SerializeTupleVariant::serialize_field);
+pub(crate) struct SerializeObject {
+    object: napi_value,
+    /// 已被序列化、但尚未配对值的键
+    key: Option<String>,
+    /// 结构体枚举值的变体名
+    variant: Option<&'static str>
+}
+impl SerializeObject {
+    fn set<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let property = value.serialize(Serializer).map_err(|err| err.at_field(key))?;
+        nj_sys::napi_set_named_property(self.object, key, property)?;
+        Ok(())
+    }
+    fn finish(self) -> Result<napi_value, Error> {
+        match self.variant {
+            Some(variant) => Serializer::tagged(variant, self.object),
+            None => Ok(self.object)
+        }
+    }
+}
+impl ser::SerializeMap for SerializeObject {
+    type Ok = napi_value;
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_value() 之前须先调用 serialize_key()");
+        self.set(&key, value)
+    }
+    fn end(self) -> Result<napi_value, Error> {
+        self.finish()
+    }
+}
+macro_rules! impl_serialize_object {
+    ($( $trait: ident ),*) => {$(
+        impl ser::$trait for SerializeObject {
+            type Ok = napi_value;
+            type Error = Error;
+            fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+                self.set(key, value).map_err(|err| match self.variant {
+                    Some(variant) => err.at_field(variant),
+                    None => err
+                })
+            }
+            fn end(self) -> Result<napi_value, Error> {
+                self.finish()
+            }
+        }
+    )*};
+}
+impl_serialize_object!(SerializeStruct, SerializeStructVariant);
+/// 将映射表的键序列化为属性名
+struct KeySerializer;
+macro_rules! serialize_key_via_to_string {
+    ($( $method: ident : $type: ty ),*) => {$(
+        fn $method(self, v: $type) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+    )*};
+}
+macro_rules! reject_key {
+    ($( $method: ident ( $( $arg: ident : $type: ty ),* ) -> $ok: ty ; )*) => {$(
+        fn $method(self, $( $arg: $type ),*) -> Result<$ok, Error> {
+            let _ = ($( $arg ),*);
+            Err(Error::new("JS 对象的属性名只能是字符串、字符或整数"))
+        }
+    )*};
+}
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+    serialize_key_via_to_string!(serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64, serialize_i128: i128,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64, serialize_u128: u128,
+        serialize_char: char, serialize_str: &str);
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+    reject_key! {
+        serialize_bool(v: bool) -> String;
+        serialize_f32(v: f32) -> String;
+        serialize_f64(v: f64) -> String;
+        serialize_bytes(v: &[u8]) -> String;
+        serialize_none() -> String;
+        serialize_unit() -> String;
+        serialize_unit_struct(name: &'static str) -> String;
+        serialize_seq(len: Option<usize>) -> Impossible<String, Error>;
+        serialize_tuple(len: usize) -> Impossible<String, Error>;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Impossible<String, Error>;
+        serialize_tuple_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Impossible<String, Error>;
+        serialize_map(len: Option<usize>) -> Impossible<String, Error>;
+        serialize_struct(name: &'static str, len: usize) -> Impossible<String, Error>;
+        serialize_struct_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Impossible<String, Error>;
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, Error> {
+        Err(Error::new("JS 对象的属性名只能是字符串、字符或整数"))
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<String, Error> {
+        Err(Error::new("JS 对象的属性名只能是字符串、字符或整数"))
+    }
+}
+// ----- 反序列化 -----
+pub(crate) struct Deserializer(pub(crate) napi_value);
+impl Deserializer {
+    fn type_name(&self) -> &'static str {
+        match nj_sys::napi_typeof(self.0) {
+            napi_valuetype::napi_undefined => "undefined",
+            napi_valuetype::napi_null => "null",
+            napi_valuetype::napi_boolean => "boolean",
+            napi_valuetype::napi_number => "number",
+            napi_valuetype::napi_string => "string",
+            napi_valuetype::napi_bigint => "bigint",
+            napi_valuetype::napi_object if nj_sys::napi_is_array(self.0) => "array",
+            napi_valuetype::napi_object => "object"
+        }
+    }
+    fn is_nullish(&self) -> bool {
+        matches!(nj_sys::napi_typeof(self.0), napi_valuetype::napi_undefined | napi_valuetype::napi_null)
+    }
+}
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.0;
+        match nj_sys::napi_typeof(value) {
+            napi_valuetype::napi_undefined | napi_valuetype::napi_null => visitor.visit_unit(),
+            napi_valuetype::napi_boolean => visitor.visit_bool(nj_sys::napi_get_value_bool(value)?),
+            napi_valuetype::napi_number => {
+                // 整数值的 JS 数字被当作整数，以便反序列化为整数类型
+                let number = nj_sys::napi_get_value_double(value)?;
+                if number.fract() == 0.0 && number.abs() <= MAX_SAFE_INTEGER as f64 {
+                    visitor.visit_i64(number as i64)
+                } else {
+                    visitor.visit_f64(number)
+                }
+            },
+            napi_valuetype::napi_bigint => match (nj_sys::napi_get_value_bigint_int64(value)?, nj_sys::napi_get_value_bigint_uint64(value)?) {
+                ((signed, true), _) => visitor.visit_i64(signed),
+                (_, (unsigned, true)) => visitor.visit_u64(unsigned),
+                _ => Err(Error::new("BigInt 超出了 i64 与 u64 的范围"))
+            },
+            napi_valuetype::napi_string => visitor.visit_string(nj_sys::napi_get_value_string_utf8(value)?),
+            napi_valuetype::napi_object if nj_sys::napi_is_array(value) => {
+                let len = nj_sys::napi_get_array_length(value)?;
+                let mut access = ArrayAccess { array: value, index: 0, len };
+                let result = visitor.visit_seq(&mut access)?;
+                match access.index == access.len {
+                    true => Ok(result),
+                    false => Err(de::Error::invalid_length(len as usize, &"更少的数组元素"))
+                }
+            },
+            napi_valuetype::napi_object => {
+                let names = nj_sys::napi_get_property_names(value)?;
+                let len = nj_sys::napi_get_array_length(names)?;
+                visitor.visit_map(ObjectAccess { object: value, names, index: 0, len, key: None })
+            }
+        }
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.is_nullish() {
+            true => visitor.visit_none(),
+            false => visitor.visit_some(self)
+        }
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        let value = self.0;
+        match nj_sys::napi_typeof(value) {
+            // 单元变体
+            napi_valuetype::napi_string => visitor.visit_enum(nj_sys::napi_get_value_string_utf8(value)?.into_deserializer()),
+            // 外部标签表示法
+            napi_valuetype::napi_object if !nj_sys::napi_is_array(value) => {
+                let names = nj_sys::napi_get_property_names(value)?;
+                if nj_sys::napi_get_array_length(names)? != 1 {
+                    return Err(Error::new("枚举值须是变体名字符串，或仅有一个属性的 JS 对象"));
+                }
+                let variant = nj_sys::napi_get_value_string_utf8(nj_sys::napi_get_element(names, 0)?)?;
+                let value = nj_sys::napi_get_named_property(value, &variant)?;
+                visitor.visit_enum(Enum { variant, value })
+            },
+            _ => Err(de::Error::invalid_type(de::Unexpected::Other(self.type_name()), &"变体名字符串，或仅有一个属性的 JS 对象"))
+        }
+    }
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+struct ArrayAccess {
+    array: napi_value,
+    index: u32,
+    len: u32
+}
+impl<'de> SeqAccess<'de> for ArrayAccess {
+    type Error = Error;
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        let element = nj_sys::napi_get_element(self.array, index)?;
+        seed.deserialize(Deserializer(element)).map(Some).map_err(|err| err.at_index(index as usize))
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+struct ObjectAccess {
+    object: napi_value,
+    /// 由属性名组成的 JS 数组
+    names: napi_value,
+    index: u32,
+    len: u32,
+    /// 已被反序列化、但尚未取值的属性名
+    key: Option<String>
+}
+impl<'de> MapAccess<'de> for ObjectAccess {
+    type Error = Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let key = nj_sys::napi_get_value_string_utf8(nj_sys::napi_get_element(self.names, self.index)?)?;
+        self.index += 1;
+        let result = seed.deserialize(KeyDeserializer(key.clone())).map_err(|err| err.at_field(&key))?;
+        self.key = Some(key);
+        Ok(Some(result))
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let key = self.key.take().expect("next_value_seed() 之前须先调用 next_key_seed()");
+        let value = nj_sys::napi_get_named_property(self.object, &key)?;
+        seed.deserialize(Deserializer(value)).map_err(|err| err.at_field(&key))
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+/// 将属性名反序列化为映射表的键。整数键从字符串解析而来
+struct KeyDeserializer(String);
+macro_rules! deserialize_key_via_parse {
+    ($( $method: ident => $visit: ident ),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.0.parse() {
+                Ok(key) => visitor.$visit(key),
+                Err(_) => visitor.visit_string(self.0)
+            }
+        }
+    )*};
+}
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+    deserialize_key_via_parse!(deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64, deserialize_i128 => visit_i128, deserialize_u8 => visit_u8, deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32, deserialize_u64 => visit_u64, deserialize_u128 => visit_u128);
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+struct Enum {
+    variant: String,
+    value: napi_value
+}
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = Error;
+    type Variant = Variant;
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Variant), Error> {
+        let variant = seed.deserialize(self.variant.as_str().into_deserializer()).map_err(|err: Error| err.at_field(&self.variant))?;
+        Ok((variant, Variant { name: self.variant, value: self.value }))
+    }
+}
+struct Variant {
+    name: String,
+    value: napi_value
+}
+impl<'de> VariantAccess<'de> for Variant {
+    type Error = Error;
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(Deserializer(self.value)).map_err(|err: Error| err.at_field(&self.name))
+    }
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(Deserializer(self.value)).map_err(|err| err.at_field(&self.name))
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(Deserializer(self.value), visitor).map_err(|err| err.at_field(&self.name))
+    }
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(Deserializer(self.value), visitor).map_err(|err| err.at_field(&self.name))
+    }
+}
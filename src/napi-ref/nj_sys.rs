@@ -0,0 +1,736 @@
+//! 本例程对 N-API bindings 的 Stub —— 一个进程内的模拟 JS VM，因为 nj_sys 并没有在
+//! playground.org 的 top 100 依赖包清单里。
+//!
+//! 被模拟的 N-API 外部函数接口包括：
+//! 1. `napi_value`代表 JS VM 堆内存里的一个对象的句柄。同一个对象可有多个句柄，须以`napi_strict_equals()`比较
+//! 2. `napi_ref`代表指向`napi_value`值的引用计数指针。引用计数为零的`napi_ref`是弱引用，它不阻止 GC 回收 JS 堆对象
+//! 3. `napi_create_reference()`函数从`napi_value`构造`napi_ref`，`napi_delete_reference()`函数析构掉`napi_ref`
+//! 4. `napi_reference_ref()`与`napi_reference_unref()`函数增减`napi_ref`的引用计数，并返回新的引用计数
+//! 5. `napi_get_reference_value()`函数为`napi_ref`指向的 JS 堆对象创建新句柄。若 JS 堆对象已被回收，则返回`None`
+//! 6. `napi_create_object()`函数分配 JS 堆对象。值模型还包括数组、字符串、数字、BigInt、布尔值、`null`与`undefined`：
+//!    `napi_create_*()`与`napi_get_boolean()`、`napi_get_null()`、`napi_get_undefined()`创建它们，`napi_typeof()`与
+//!    `napi_is_array()`查询它们的类型，`napi_get_value_*()`取出它们的值（类型不符时返回`napi_status`错误），
+//!    `napi_set_named_property()`、`napi_get_named_property()`、`napi_get_property_names()`、`napi_set_element()`、
+//!    `napi_get_element()`与`napi_get_array_length()`读写对象的属性与数组的元素。简单起见，每个值都被分配于 JS 堆
+//! 7. `napi_threadsafe_function`是唯一可被跨线程传递的句柄。任何线程都可经由`napi_call_threadsafe_function()`
+//!    将调用排入 JS 线程的事件队列。`napi_create_threadsafe_function()`与`napi_release_threadsafe_function()`
+//!    函数分别创建与释放它
+//! 8. 句柄作用域：`napi_open_handle_scope()`与`napi_close_handle_scope()`。句柄属于创建它时最内层的句柄作用域，
+//!    且随该作用域的关闭而失效。可逃逸的句柄作用域：`napi_open_escapable_handle_scope()`与
+//!    `napi_close_escapable_handle_scope()`。`napi_escape_handle()`将句柄提升至外层的句柄作用域，且仅能被调用一次
+//! 9. `napi_wrap()`将原生对象的指针与终结器附着于 JS 堆对象。JS 堆对象被 GC 回收之后，终结器被调用以释放原生对象。
+//!    `napi_unwrap()`取出被附着的指针。`napi_remove_wrap()`取出并解除附着，且不调用终结器
+//! 10. `napi_create_promise()`创建 Promise 与兑现它的`napi_deferred`。`napi_resolve_deferred()`或
+//!     `napi_reject_deferred()`兑现 Promise，且每个`napi_deferred`仅能被使用一次。Promise 被兑现之前，`napi_deferred`扎根它
+//! 11. `napi_create_async_work()`创建由`execute`与`complete`两个回调组成的 async work。`napi_queue_async_work()`在工作线程
+//!     上调用`execute`（就像 libuv 的线程池），再将`complete`排入 JS 线程的事件队列。`complete`被调用之后，须以
+//!     `napi_delete_async_work()`释放 async work。`execute`的 panic 被捕获，且`complete`仍被调用
+//!
+//! 像真实的 N-API 一样，每个 JS 线程各有一个 VM。`napi_value`与`napi_ref`都不是`Send`的，且都只能被交给创建它们的
+//! VM。JS 线程以`run_event_loop()`执行被排队的调用。尚有 async work 在工作线程上执行时，事件循环会等待它们完成。
+//!
+//! 模拟 VM 另有几个（N-API 之外的）入口，以供例程断言引用计数是否被平衡：
+//! 1. `gc()`回收没有被任何强引用（引用计数大于零的`napi_ref`）或未关闭的句柄作用域扎根，也不能经由属性与元素从
+//!    被扎根的 JS 堆对象触及的 JS 堆对象。
+//!    简单起见，没有打开任何句柄作用域时被创建的句柄不扎根 JS 堆对象，就好像 JS 端已丢弃了它。
+//!    终结器在`gc()`返回之前、VM 的借用被释放之后被调用，所以终结器可以再调用 N-API（比如，析构原生对象持有的`napi_ref`）。
+//!    但线程退出时，VM 不再调用尚存对象的终结器
+//! 2. `is_alive()`与`reference_count()`查询 JS 堆对象是否存活，与它被强引用的总计数。`promise_result()`查询 Promise 的结果
+//! 3. `live_references()`列出尚未被析构的`napi_ref`；`assert_no_leaks()`断言其为空，且所有`napi_threadsafe_function`、
+//!    句柄作用域、`napi_deferred`与 async work 都已被释放，否则报告泄漏
+//! 4. 重复析构`napi_ref`、析构之后再使用`napi_ref`、引用计数减至负数、使用已被回收的`napi_value`、使用已失效的句柄、
+//!    未按嵌套顺序关闭句柄作用域、重复包装同一个 JS 堆对象，与在其它线程上使用`napi_value`或`napi_ref`都会立即 panic
+#![allow(dead_code, non_camel_case_types)]
+use ::std::{cell::RefCell, collections::VecDeque, ffi::c_void, fmt::{Debug, Formatter, Result as FmtResult}, marker::PhantomData,
+    panic::{self, AssertUnwindSafe}, sync::{atomic::{AtomicUsize, Ordering}, Arc, Condvar, Mutex, PoisonError}, thread};
+/// 真实的 napi_value 与 napi_ref 都是裸指针，所以既不是 Send 的，也不是 Sync 的
+type NotSend = PhantomData<*mut ()>;
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct napi_value {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct napi_ref {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct napi_deferred {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct napi_async_work {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct napi_handle_scope {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct napi_escapable_handle_scope {
+    vm: usize,
+    index: usize,
+    _not_send: NotSend
+}
+// 形如 napi_value(VM 编号:句柄编号)
+macro_rules! impl_handle_debug {
+    ($( $handle: ident ),*) => {$(
+        impl Debug for $handle {
+            fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+                write!(f, concat!(stringify!($handle), "({}:{})"), self.vm, self.index)
+            }
+        }
+    )*};
+}
+impl_handle_debug!(napi_value, napi_ref, napi_deferred, napi_async_work, napi_handle_scope, napi_escapable_handle_scope);
+/// 被排入 JS 线程事件队列的调用
+pub(crate) type napi_threadsafe_call = Box<dyn FnOnce() + Send>;
+/// 在工作线程上被调用
+pub(crate) type napi_async_execute = Box<dyn FnOnce() + Send>;
+/// 在 JS 线程上被调用。参数是被完成的 async work，以便释放它
+pub(crate) type napi_async_complete = Box<dyn FnOnce(napi_async_work)>;
+/// JS 线程的事件队列。工作线程排队之后，唤醒等待 async work 的事件循环
+#[derive(Default)]
+struct EventQueueInner {
+    calls: Mutex<VecDeque<napi_threadsafe_call>>,
+    ready: Condvar
+}
+type EventQueue = Arc<EventQueueInner>;
+impl EventQueueInner {
+    fn push(&self, call: napi_threadsafe_call) {
+        self.calls.lock().unwrap_or_else(PoisonError::into_inner).push_back(call);
+        self.ready.notify_one();
+    }
+}
+#[derive(Clone)]
+pub(crate) struct napi_threadsafe_function {
+    queue: EventQueue
+}
+/// 原生对象的终结器。参数依次是被附着的指针与`napi_wrap()`的`finalize_hint`
+pub(crate) type napi_finalize = unsafe fn(data: *mut c_void, hint: *mut c_void);
+/// 被附着于 JS 堆对象的原生对象
+#[derive(Debug)]
+struct Wrap {
+    data: *mut c_void,
+    finalize: napi_finalize,
+    hint: *mut c_void
+}
+/// `napi_typeof()`的返回值。数组也是`napi_object`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum napi_valuetype {
+    napi_undefined,
+    napi_null,
+    napi_boolean,
+    napi_number,
+    napi_string,
+    napi_object,
+    napi_bigint
+}
+/// 被模拟的 N-API 函数的错误码（`napi_ok`以外的）
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum napi_status {
+    napi_invalid_arg,
+    napi_object_expected,
+    napi_string_expected,
+    napi_number_expected,
+    napi_boolean_expected,
+    napi_array_expected,
+    napi_bigint_expected
+}
+/// JS 值。对象的属性与数组的元素都是其它 JS 堆对象的编号
+#[derive(Debug)]
+enum Value {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    /// 简单起见，仅支持 i64 与 u64 范围内的 BigInt
+    BigInt(i128),
+    String(String),
+    /// 按插入顺序排列的属性
+    Object(Vec<(String, usize)>),
+    Array(Vec<usize>),
+    /// 尚未被兑现时是 None
+    Promise(Option<Result<usize, usize>>)
+}
+impl Value {
+    fn children(&self) -> Vec<usize> {
+        match self {
+            Value::Object(properties) => properties.iter().map(|(_, child)| *child).collect(),
+            Value::Array(elements) => elements.clone(),
+            Value::Promise(Some(Ok(child) | Err(child))) => vec![*child],
+            _ => Vec::new()
+        }
+    }
+}
+/// JS 堆对象
+#[derive(Debug)]
+struct Object {
+    alive: bool,
+    value: Value,
+    wrap: Option<Wrap>
+}
+/// 句柄
+#[derive(Debug)]
+struct Handle {
+    object: usize,
+    /// 所属的句柄作用域。None 表示没有打开任何句柄作用域时被创建的句柄
+    scope: Option<usize>
+}
+#[derive(Debug)]
+struct Reference {
+    /// 创建 napi_ref 时的句柄
+    value: napi_value,
+    object: usize,
+    ref_count: u32,
+    deleted: bool
+}
+#[derive(Debug)]
+struct Deferred {
+    promise: usize,
+    settled: bool
+}
+struct AsyncWork {
+    execute: Option<napi_async_execute>,
+    complete: Option<napi_async_complete>,
+    deleted: bool
+}
+#[derive(Debug)]
+struct Scope {
+    parent: Option<usize>,
+    escapable: bool,
+    escaped: bool,
+    open: bool
+}
+/// 模拟 VM。每个线程一个，就像每个 JS 线程（主线程或 Worker）各有一个 napi_env
+struct Vm {
+    id: usize,
+    objects: Vec<Object>,
+    handles: Vec<Handle>,
+    references: Vec<Reference>,
+    scopes: Vec<Scope>,
+    /// 未关闭的句柄作用域，由外至内
+    open_scopes: Vec<usize>,
+    deferreds: Vec<Deferred>,
+    async_works: Vec<AsyncWork>,
+    /// 已被排队、但其 complete 尚未被调用的 async work 个数
+    async_works_in_flight: usize,
+    queue: EventQueue,
+    /// 尚未被释放的 napi_threadsafe_function 个数
+    threadsafe_functions: usize
+}
+impl Vm {
+    fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Vm {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            objects: Vec::new(),
+            handles: Vec::new(),
+            references: Vec::new(),
+            scopes: Vec::new(),
+            open_scopes: Vec::new(),
+            deferreds: Vec::new(),
+            async_works: Vec::new(),
+            async_works_in_flight: 0,
+            queue: EventQueue::default(),
+            threadsafe_functions: 0
+        }
+    }
+    /// 在`scope`（缺省为最内层的句柄作用域）里创建句柄
+    fn new_handle(&mut self, object: usize, scope: Option<usize>) -> napi_value {
+        let scope = scope.or_else(|| self.open_scopes.last().copied());
+        self.handles.push(Handle { object, scope });
+        napi_value { vm: self.id, index: self.handles.len() - 1, _not_send: PhantomData }
+    }
+    /// 检查句柄，并返回它指向的 JS 堆对象的编号
+    fn object(&mut self, value: napi_value, operation: &str) -> usize {
+        assert_eq!(value.vm, self.id, "[{}]{:?} 不属于本线程的 VM", operation, value);
+        let handle = &self.handles[value.index];
+        if let Some(scope) = handle.scope {
+            assert!(self.scopes[scope].open, "[{}]{:?} 所属的句柄作用域已被关闭", operation, value);
+        }
+        assert!(self.objects[handle.object].alive, "[{}]{:?} 已被 GC 回收", operation, value);
+        handle.object
+    }
+    /// 检查句柄，并返回它指向的 JS 值
+    fn value(&mut self, value: napi_value, operation: &str) -> &mut Value {
+        let object = self.object(value, operation);
+        &mut self.objects[object].value
+    }
+    /// 分配 JS 堆对象，并在最内层的句柄作用域里为它创建句柄
+    fn alloc(&mut self, value: Value) -> napi_value {
+        self.objects.push(Object { alive: true, value, wrap: None });
+        self.new_handle(self.objects.len() - 1, None)
+    }
+    fn reference(&mut self, reference: napi_ref, operation: &str) -> &mut Reference {
+        assert_eq!(reference.vm, self.id, "[{}]{:?} 不属于本线程的 VM", operation, reference);
+        let entry = &mut self.references[reference.index];
+        assert!(!entry.deleted, "[{}]{:?} 已被析构：重复析构，或析构之后再使用", operation, reference);
+        entry
+    }
+    fn open_scope(&mut self, escapable: bool) -> usize {
+        self.scopes.push(Scope { parent: self.open_scopes.last().copied(), escapable, escaped: false, open: true });
+        self.open_scopes.push(self.scopes.len() - 1);
+        self.scopes.len() - 1
+    }
+    fn close_scope(&mut self, vm: usize, scope: usize, operation: &str) {
+        assert_eq!(vm, self.id, "[{}]句柄作用域不属于本线程的 VM", operation);
+        assert_eq!(self.open_scopes.last(), Some(&scope), "[{}]句柄作用域已被关闭，或未按嵌套顺序关闭", operation);
+        self.open_scopes.pop();
+        self.scopes[scope].open = false;
+    }
+}
+thread_local! {
+    static VM: RefCell<Vm> = RefCell::new(Vm::new());
+}
+fn with_vm<R>(f: impl FnOnce(&mut Vm) -> R) -> R {
+    VM.with(|vm| f(&mut vm.borrow_mut()))
+}
+pub(crate) fn napi_create_object() -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Object(Vec::new())))
+}
+pub(crate) fn napi_create_array() -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Array(Vec::new())))
+}
+pub(crate) fn napi_create_double(value: f64) -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Number(value)))
+}
+pub(crate) fn napi_create_bigint_int64(value: i64) -> napi_value {
+    with_vm(|vm| vm.alloc(Value::BigInt(value.into())))
+}
+pub(crate) fn napi_create_bigint_uint64(value: u64) -> napi_value {
+    with_vm(|vm| vm.alloc(Value::BigInt(value.into())))
+}
+pub(crate) fn napi_create_string_utf8(value: &str) -> napi_value {
+    with_vm(|vm| vm.alloc(Value::String(value.to_string())))
+}
+pub(crate) fn napi_get_boolean(value: bool) -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Boolean(value)))
+}
+pub(crate) fn napi_get_null() -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Null))
+}
+pub(crate) fn napi_get_undefined() -> napi_value {
+    with_vm(|vm| vm.alloc(Value::Undefined))
+}
+pub(crate) fn napi_typeof(value: napi_value) -> napi_valuetype {
+    with_vm(|vm| match vm.value(value, "napi_typeof") {
+        Value::Undefined => napi_valuetype::napi_undefined,
+        Value::Null => napi_valuetype::napi_null,
+        Value::Boolean(_) => napi_valuetype::napi_boolean,
+        Value::Number(_) => napi_valuetype::napi_number,
+        Value::BigInt(_) => napi_valuetype::napi_bigint,
+        Value::String(_) => napi_valuetype::napi_string,
+        Value::Object(_) | Value::Array(_) | Value::Promise(_) => napi_valuetype::napi_object
+    })
+}
+pub(crate) fn napi_is_array(value: napi_value) -> bool {
+    with_vm(|vm| matches!(vm.value(value, "napi_is_array"), Value::Array(_)))
+}
+pub(crate) fn napi_get_value_bool(value: napi_value) -> Result<bool, napi_status> {
+    with_vm(|vm| match vm.value(value, "napi_get_value_bool") {
+        Value::Boolean(value) => Ok(*value),
+        _ => Err(napi_status::napi_boolean_expected)
+    })
+}
+pub(crate) fn napi_get_value_double(value: napi_value) -> Result<f64, napi_status> {
+    with_vm(|vm| match vm.value(value, "napi_get_value_double") {
+        Value::Number(value) => Ok(*value),
+        _ => Err(napi_status::napi_number_expected)
+    })
+}
+/// 返回截断后的值，与是否无损
+pub(crate) fn napi_get_value_bigint_int64(value: napi_value) -> Result<(i64, bool), napi_status> {
+    with_vm(|vm| match vm.value(value, "napi_get_value_bigint_int64") {
+        Value::BigInt(value) => Ok((*value as i64, i64::try_from(*value).is_ok())),
+        _ => Err(napi_status::napi_bigint_expected)
+    })
+}
+/// 返回截断后的值，与是否无损
+pub(crate) fn napi_get_value_bigint_uint64(value: napi_value) -> Result<(u64, bool), napi_status> {
+    with_vm(|vm| match vm.value(value, "napi_get_value_bigint_uint64") {
+        Value::BigInt(value) => Ok((*value as u64, u64::try_from(*value).is_ok())),
+        _ => Err(napi_status::napi_bigint_expected)
+    })
+}
+pub(crate) fn napi_get_value_string_utf8(value: napi_value) -> Result<String, napi_status> {
+    with_vm(|vm| match vm.value(value, "napi_get_value_string_utf8") {
+        Value::String(value) => Ok(value.clone()),
+        _ => Err(napi_status::napi_string_expected)
+    })
+}
+/// 已有的同名属性被覆盖，且不改变属性的顺序
+pub(crate) fn napi_set_named_property(object: napi_value, name: &str, value: napi_value) -> Result<(), napi_status> {
+    with_vm(|vm| {
+        let child = vm.object(value, "napi_set_named_property");
+        match vm.value(object, "napi_set_named_property") {
+            Value::Object(properties) => match properties.iter_mut().find(|(key, _)| key == name) {
+                Some((_, property)) => *property = child,
+                None => properties.push((name.to_string(), child))
+            },
+            _ => return Err(napi_status::napi_object_expected)
+        }
+        Ok(())
+    })
+}
+/// 不存在的属性是`undefined`
+pub(crate) fn napi_get_named_property(object: napi_value, name: &str) -> Result<napi_value, napi_status> {
+    with_vm(|vm| {
+        let child = match vm.value(object, "napi_get_named_property") {
+            Value::Object(properties) => properties.iter().find(|(key, _)| key == name).map(|(_, child)| *child),
+            _ => return Err(napi_status::napi_object_expected)
+        };
+        Ok(match child {
+            Some(child) => vm.new_handle(child, None),
+            None => vm.alloc(Value::Undefined)
+        })
+    })
+}
+/// 返回由属性名（字符串）组成的数组
+pub(crate) fn napi_get_property_names(object: napi_value) -> Result<napi_value, napi_status> {
+    with_vm(|vm| {
+        let names = match vm.value(object, "napi_get_property_names") {
+            Value::Object(properties) => properties.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>(),
+            _ => return Err(napi_status::napi_object_expected)
+        };
+        let elements = names.into_iter().map(|name| {
+            vm.objects.push(Object { alive: true, value: Value::String(name), wrap: None });
+            vm.objects.len() - 1
+        }).collect();
+        Ok(vm.alloc(Value::Array(elements)))
+    })
+}
+/// 越界写入时，以`undefined`填补空隙
+pub(crate) fn napi_set_element(array: napi_value, index: u32, value: napi_value) -> Result<(), napi_status> {
+    with_vm(|vm| {
+        let child = vm.object(value, "napi_set_element");
+        let len = match vm.value(array, "napi_set_element") {
+            Value::Array(elements) => elements.len(),
+            _ => return Err(napi_status::napi_array_expected)
+        };
+        let holes = (len..index as usize).map(|_| {
+            vm.objects.push(Object { alive: true, value: Value::Undefined, wrap: None });
+            vm.objects.len() - 1
+        }).collect::<Vec<_>>();
+        if let Value::Array(elements) = vm.value(array, "napi_set_element") {
+            elements.extend(holes);
+            match elements.get_mut(index as usize) {
+                Some(element) => *element = child,
+                None => elements.push(child)
+            }
+        }
+        Ok(())
+    })
+}
+/// 越界的元素是`undefined`
+pub(crate) fn napi_get_element(array: napi_value, index: u32) -> Result<napi_value, napi_status> {
+    with_vm(|vm| {
+        let child = match vm.value(array, "napi_get_element") {
+            Value::Array(elements) => elements.get(index as usize).copied(),
+            _ => return Err(napi_status::napi_array_expected)
+        };
+        Ok(match child {
+            Some(child) => vm.new_handle(child, None),
+            None => vm.alloc(Value::Undefined)
+        })
+    })
+}
+pub(crate) fn napi_get_array_length(array: napi_value) -> Result<u32, napi_status> {
+    with_vm(|vm| match vm.value(array, "napi_get_array_length") {
+        Value::Array(elements) => Ok(elements.len() as u32),
+        _ => Err(napi_status::napi_array_expected)
+    })
+}
+/// 两个句柄是否指向同一个 JS 堆对象
+pub(crate) fn napi_strict_equals(lhs: napi_value, rhs: napi_value) -> bool {
+    with_vm(|vm| vm.object(lhs, "napi_strict_equals") == vm.object(rhs, "napi_strict_equals"))
+}
+pub(crate) fn napi_create_reference(value: napi_value, initial_refcount: u32) -> napi_ref {
+    with_vm(|vm| {
+        let object = vm.object(value, "napi_create_reference");
+        vm.references.push(Reference { value, object, ref_count: initial_refcount, deleted: false });
+        napi_ref { vm: vm.id, index: vm.references.len() - 1, _not_send: PhantomData }
+    })
+}
+pub(crate) fn napi_delete_reference(reference: napi_ref) {
+    with_vm(|vm| vm.reference(reference, "napi_delete_reference").deleted = true)
+}
+pub(crate) fn napi_reference_ref(reference: napi_ref) -> u32 {
+    with_vm(|vm| {
+        let entry = vm.reference(reference, "napi_reference_ref");
+        entry.ref_count += 1;
+        entry.ref_count
+    })
+}
+pub(crate) fn napi_reference_unref(reference: napi_ref) -> u32 {
+    with_vm(|vm| {
+        let entry = vm.reference(reference, "napi_reference_unref");
+        entry.ref_count = entry.ref_count.checked_sub(1).unwrap_or_else(|| panic!("[napi_reference_unref]{:?} 的引用计数已是零", reference));
+        entry.ref_count
+    })
+}
+pub(crate) fn napi_get_reference_value(reference: napi_ref) -> Option<napi_value> {
+    with_vm(|vm| {
+        let object = vm.reference(reference, "napi_get_reference_value").object;
+        vm.objects[object].alive.then(|| vm.new_handle(object, None))
+    })
+}
+pub(crate) fn napi_open_handle_scope() -> napi_handle_scope {
+    with_vm(|vm| napi_handle_scope { vm: vm.id, index: vm.open_scope(false), _not_send: PhantomData })
+}
+pub(crate) fn napi_close_handle_scope(scope: napi_handle_scope) {
+    with_vm(|vm| vm.close_scope(scope.vm, scope.index, "napi_close_handle_scope"))
+}
+pub(crate) fn napi_open_escapable_handle_scope() -> napi_escapable_handle_scope {
+    with_vm(|vm| napi_escapable_handle_scope { vm: vm.id, index: vm.open_scope(true), _not_send: PhantomData })
+}
+pub(crate) fn napi_close_escapable_handle_scope(scope: napi_escapable_handle_scope) {
+    with_vm(|vm| vm.close_scope(scope.vm, scope.index, "napi_close_escapable_handle_scope"))
+}
+/// 在`scope`的外层句柄作用域里，为`value`指向的 JS 堆对象创建新句柄。每个可逃逸的句柄作用域仅能逃逸一次
+pub(crate) fn napi_escape_handle(scope: napi_escapable_handle_scope, value: napi_value) -> napi_value {
+    with_vm(|vm| {
+        let object = vm.object(value, "napi_escape_handle");
+        assert_eq!(scope.vm, vm.id, "[napi_escape_handle]{:?} 不属于本线程的 VM", scope);
+        let entry = &mut vm.scopes[scope.index];
+        assert!(entry.open && entry.escapable, "[napi_escape_handle]{:?} 已被关闭", scope);
+        assert!(!entry.escaped, "[napi_escape_handle]{:?} 已逃逸过一次了", scope);
+        entry.escaped = true;
+        let parent = entry.parent;
+        vm.handles.push(Handle { object, scope: parent });
+        napi_value { vm: vm.id, index: vm.handles.len() - 1, _not_send: PhantomData }
+    })
+}
+pub(crate) fn napi_create_promise() -> (napi_deferred, napi_value) {
+    with_vm(|vm| {
+        let promise = vm.alloc(Value::Promise(None));
+        vm.deferreds.push(Deferred { promise: vm.handles[promise.index].object, settled: false });
+        (napi_deferred { vm: vm.id, index: vm.deferreds.len() - 1, _not_send: PhantomData }, promise)
+    })
+}
+fn settle(deferred: napi_deferred, result: Result<napi_value, napi_value>, operation: &str) {
+    with_vm(|vm| {
+        let child = match result {
+            Ok(value) => Ok(vm.object(value, operation)),
+            Err(value) => Err(vm.object(value, operation))
+        };
+        assert_eq!(deferred.vm, vm.id, "[{}]{:?} 不属于本线程的 VM", operation, deferred);
+        let entry = &mut vm.deferreds[deferred.index];
+        assert!(!entry.settled, "[{}]{:?} 已被兑现过了", operation, deferred);
+        entry.settled = true;
+        let promise = entry.promise;
+        vm.objects[promise].value = Value::Promise(Some(child));
+    })
+}
+pub(crate) fn napi_resolve_deferred(deferred: napi_deferred, resolution: napi_value) {
+    settle(deferred, Ok(resolution), "napi_resolve_deferred")
+}
+pub(crate) fn napi_reject_deferred(deferred: napi_deferred, rejection: napi_value) {
+    settle(deferred, Err(rejection), "napi_reject_deferred")
+}
+pub(crate) fn napi_is_promise(value: napi_value) -> bool {
+    with_vm(|vm| matches!(vm.value(value, "napi_is_promise"), Value::Promise(_)))
+}
+pub(crate) fn napi_create_async_work(execute: napi_async_execute, complete: napi_async_complete) -> napi_async_work {
+    with_vm(|vm| {
+        vm.async_works.push(AsyncWork { execute: Some(execute), complete: Some(complete), deleted: false });
+        napi_async_work { vm: vm.id, index: vm.async_works.len() - 1, _not_send: PhantomData }
+    })
+}
+/// 每个 async work 仅能被排队一次
+pub(crate) fn napi_queue_async_work(work: napi_async_work) {
+    let (execute, queue) = with_vm(|vm| {
+        assert_eq!(work.vm, vm.id, "[napi_queue_async_work]{:?} 不属于本线程的 VM", work);
+        let execute = vm.async_works[work.index].execute.take().unwrap_or_else(|| panic!("[napi_queue_async_work]{:?} 已被排队过了", work));
+        vm.async_works_in_flight += 1;
+        (execute, Arc::clone(&vm.queue))
+    });
+    let index = work.index;
+    thread::spawn(move || {
+        // execute 的 panic 已被默认的 panic hook 报告。无论如何，都要调用 complete，以免事件循环一直等待
+        let _ = panic::catch_unwind(AssertUnwindSafe(execute));
+        queue.push(Box::new(move || {
+            let (work, complete) = with_vm(|vm| {
+                vm.async_works_in_flight -= 1;
+                (napi_async_work { vm: vm.id, index, _not_send: PhantomData }, vm.async_works[index].complete.take().unwrap())
+            });
+            complete(work);
+        }));
+    });
+}
+pub(crate) fn napi_delete_async_work(work: napi_async_work) {
+    with_vm(|vm| {
+        assert_eq!(work.vm, vm.id, "[napi_delete_async_work]{:?} 不属于本线程的 VM", work);
+        let entry = &mut vm.async_works[work.index];
+        assert!(!entry.deleted, "[napi_delete_async_work]{:?} 已被释放过了", work);
+        assert!(entry.execute.is_some() || entry.complete.is_none(), "[napi_delete_async_work]{:?} 尚未完成", work);
+        entry.deleted = true;
+    })
+}
+/// 一个 JS 堆对象至多被包装一次
+///
+/// # Safety
+/// 除非`data`先被`napi_remove_wrap()`取走，JS 堆对象被回收之后，`gc()`会调用`finalize(data, hint)`恰好一次，所以这次
+/// 调用须是安全的。并且，`napi_unwrap()`的调用者须能按附着者的约定解读`data`。
+pub(crate) unsafe fn napi_wrap(value: napi_value, data: *mut c_void, finalize: napi_finalize, hint: *mut c_void) {
+    with_vm(|vm| {
+        let object = vm.object(value, "napi_wrap");
+        let object = &mut vm.objects[object];
+        assert!(matches!(object.value, Value::Object(_)), "[napi_wrap]{:?} 不是 JS 对象", value);
+        assert!(object.wrap.is_none(), "[napi_wrap]{:?} 已被包装过了", value);
+        object.wrap = Some(Wrap { data, finalize, hint });
+    })
+}
+/// 若 JS 堆对象未被包装，则返回 None
+pub(crate) fn napi_unwrap(value: napi_value) -> Option<*mut c_void> {
+    with_vm(|vm| {
+        let object = vm.object(value, "napi_unwrap");
+        vm.objects[object].wrap.as_ref().map(|wrap| wrap.data)
+    })
+}
+/// 解除附着，且不调用终结器。由调用者负责释放原生对象
+pub(crate) fn napi_remove_wrap(value: napi_value) -> Option<*mut c_void> {
+    with_vm(|vm| {
+        let object = vm.object(value, "napi_remove_wrap");
+        vm.objects[object].wrap.take().map(|wrap| wrap.data)
+    })
+}
+/// 回收没有被任何强引用或未关闭的句柄作用域扎根的 JS 堆对象，再调用被回收对象的终结器。返回被回收的对象个数
+pub(crate) fn gc() -> usize {
+    let (collected, finalizers) = with_vm(|vm| {
+        let mut rooted = vec![false; vm.objects.len()];
+        for entry in vm.references.iter().filter(|entry| !entry.deleted && entry.ref_count > 0) {
+            rooted[entry.object] = true;
+        }
+        for handle in &vm.handles {
+            if matches!(handle.scope, Some(scope) if vm.scopes[scope].open) {
+                rooted[handle.object] = true;
+            }
+        }
+        for deferred in vm.deferreds.iter().filter(|deferred| !deferred.settled) {
+            rooted[deferred.promise] = true;
+        }
+        // 标记经由属性与元素可触及的 JS 堆对象
+        let mut pending = rooted.iter().enumerate().filter(|(_, rooted)| **rooted).map(|(object, _)| object).collect::<Vec<_>>();
+        while let Some(object) = pending.pop() {
+            for child in vm.objects[object].value.children() {
+                if !rooted[child] {
+                    rooted[child] = true;
+                    pending.push(child);
+                }
+            }
+        }
+        let (mut collected, mut finalizers) = (0, Vec::new());
+        for (object, rooted) in vm.objects.iter_mut().zip(rooted) {
+            if object.alive && !rooted {
+                object.alive = false;
+                finalizers.extend(object.wrap.take());
+                collected += 1;
+            }
+        }
+        (collected, finalizers)
+    });
+    for Wrap { data, finalize, hint } in finalizers {
+        // 安全：napi_wrap() 的调用者保证终结器可被以被附着的指针调用，且指针在此之前未被 napi_remove_wrap() 取走
+        unsafe { finalize(data, hint) };
+    }
+    collected
+}
+/// 句柄指向的 JS 堆对象是否存活。即便句柄已失效，也可查询
+pub(crate) fn is_alive(value: napi_value) -> bool {
+    with_vm(|vm| value.vm == vm.id && vm.objects[vm.handles[value.index].object].alive)
+}
+/// 句柄指向的 JS 堆对象被（尚未被析构的）napi_ref 强引用的总计数
+pub(crate) fn reference_count(value: napi_value) -> u32 {
+    with_vm(|vm| {
+        let object = vm.handles[value.index].object;
+        vm.references.iter().filter(|entry| !entry.deleted && entry.object == object).map(|entry| entry.ref_count).sum()
+    })
+}
+/// Promise 的结果：尚未被兑现时是 None。`value`不是 Promise 时 panic
+pub(crate) fn promise_result(value: napi_value) -> Option<Result<napi_value, napi_value>> {
+    with_vm(|vm| {
+        let result = match vm.value(value, "promise_result") {
+            Value::Promise(result) => *result,
+            _ => panic!("[promise_result]{:?} 不是 Promise", value)
+        };
+        result.map(|result| match result {
+            Ok(child) => Ok(vm.new_handle(child, None)),
+            Err(child) => Err(vm.new_handle(child, None))
+        })
+    })
+}
+/// 尚未被析构的 napi_ref，及创建它时的 napi_value 与引用计数
+pub(crate) fn live_references() -> Vec<(napi_ref, napi_value, u32)> {
+    with_vm(|vm| vm.references.iter().enumerate()
+        .filter(|(_, entry)| !entry.deleted)
+        .map(|(index, entry)| (napi_ref { vm: vm.id, index, _not_send: PhantomData }, entry.value, entry.ref_count))
+        .collect())
+}
+/// 断言没有尚未被析构的 napi_ref，且所有 napi_threadsafe_function、句柄作用域、napi_deferred 与 async work 都已被释放
+#[track_caller]
+pub(crate) fn assert_no_leaks() {
+    let live = live_references();
+    assert!(live.is_empty(), "泄漏了 {} 个 napi_ref：{:?}", live.len(), live);
+    let (threadsafe_functions, open_scopes, deferreds, async_works) = with_vm(|vm| (
+        vm.threadsafe_functions,
+        vm.open_scopes.len(),
+        vm.deferreds.iter().filter(|deferred| !deferred.settled).count(),
+        vm.async_works.iter().filter(|work| !work.deleted).count()
+    ));
+    assert_eq!(threadsafe_functions, 0, "泄漏了 {} 个 napi_threadsafe_function", threadsafe_functions);
+    assert_eq!(open_scopes, 0, "还有 {} 个句柄作用域未被关闭", open_scopes);
+    assert_eq!(deferreds, 0, "还有 {} 个 napi_deferred 未被兑现", deferreds);
+    assert_eq!(async_works, 0, "泄漏了 {} 个 async work", async_works);
+}
+pub(crate) fn napi_create_threadsafe_function() -> napi_threadsafe_function {
+    with_vm(|vm| {
+        vm.threadsafe_functions += 1;
+        napi_threadsafe_function { queue: Arc::clone(&vm.queue) }
+    })
+}
+/// 可在任何线程上调用。`call`会在 JS 线程的下一次`run_event_loop()`里被执行
+pub(crate) fn napi_call_threadsafe_function(function: &napi_threadsafe_function, call: napi_threadsafe_call) {
+    function.queue.push(call);
+}
+/// 须在 JS 线程上调用，因为真实的 N-API 要求释放与创建配对于同一个 napi_env
+pub(crate) fn napi_release_threadsafe_function(function: napi_threadsafe_function) {
+    with_vm(|vm| {
+        assert!(Arc::ptr_eq(&function.queue, &vm.queue), "[napi_release_threadsafe_function]不属于本线程的 VM");
+        vm.threadsafe_functions = vm.threadsafe_functions.checked_sub(1).expect("[napi_release_threadsafe_function]重复释放");
+    })
+}
+/// JS 线程的事件循环：逐个执行被排队的调用（包括执行期间新排入的调用），直至队列为空、且没有正在执行的 async work。
+/// 返回被执行的调用个数
+pub(crate) fn run_event_loop() -> usize {
+    let queue = with_vm(|vm| Arc::clone(&vm.queue));
+    let mut count = 0;
+    loop {
+        // 先出队，再执行。以免被执行的调用再次排队时死锁
+        let call = {
+            let mut calls = queue.calls.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                match calls.pop_front() {
+                    Some(call) => break Some(call),
+                    // async work 的 complete 必定会被排队。持锁检查，以免错过其间的唤醒
+                    None if with_vm(|vm| vm.async_works_in_flight) > 0 => calls = queue.ready.wait(calls).unwrap_or_else(PoisonError::into_inner),
+                    None => break None
+                }
+            }
+        };
+        match call {
+            Some(call) => call(),
+            None => return count
+        }
+        count += 1;
+    }
+}
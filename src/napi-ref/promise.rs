@@ -0,0 +1,48 @@
+//! 由 Rust `Future`驱动的 JS Promise：`spawn_promise()`与`spawn_serde_promise()`。
+//!
+//! 1. `Future`被交给 async work，并在工作线程上被`futures::executor::block_on()`执行至完成，所以它必须是`Send`的。
+//! 2. 完成之后，输出值随 async work 的`complete`回调跳回 JS 线程，再由`complete`闭包转换为 JS 值，并兑现 Promise。
+//! 3. `Future`不能捕获`napi_value`或`NapiRc`，因为它们都不是`Send`的。而`complete`闭包从不离开 JS 线程，所以可以
+//!    捕获`NapiRc`：被捕获的 JS 堆对象一直存活，直至 Promise 被兑现、且闭包被析构。
+//! 4. 若`Future`panic 了，则 Promise 以错误消息被拒绝。
+use ::futures::executor;
+use ::serde::Serialize;
+use ::std::{fmt::Display, future::Future, sync::{Arc, Mutex, PoisonError}};
+use crate::{napi_serde, nj_sys::{self, napi_value}};
+/// 在工作线程上执行`future`，并返回 Promise。`complete`在 JS 线程上将输出值转换为 Promise 的结果：`Ok`兑现，`Err`拒绝
+pub(crate) fn spawn_promise<F, C>(future: F, complete: C) -> napi_value
+where F: Future + Send + 'static,
+      F::Output: Send + 'static,
+      C: FnOnce(F::Output) -> Result<napi_value, napi_value> + 'static {
+    let (deferred, promise) = nj_sys::napi_create_promise();
+    // 工作线程写入，JS 线程取出。若 future panic 了，则它一直是 None
+    let output = Arc::new(Mutex::new(None));
+    let execute = {
+        let output = Arc::clone(&output);
+        Box::new(move || {
+            let value = executor::block_on(future);
+            *output.lock().unwrap_or_else(PoisonError::into_inner) = Some(value);
+        })
+    };
+    let work = nj_sys::napi_create_async_work(execute, Box::new(move |work| {
+        nj_sys::napi_delete_async_work(work);
+        let output = output.lock().unwrap_or_else(PoisonError::into_inner).take();
+        match output.map(complete) {
+            Some(Ok(resolution)) => nj_sys::napi_resolve_deferred(deferred, resolution),
+            Some(Err(rejection)) => nj_sys::napi_reject_deferred(deferred, rejection),
+            None => nj_sys::napi_reject_deferred(deferred, nj_sys::napi_create_string_utf8("异步任务 panic 了"))
+        }
+    }));
+    nj_sys::napi_queue_async_work(work);
+    promise
+}
+/// 以 serde 转换输出值：`Ok(T)`兑现为`T`的 JS 值，`Err(E)`拒绝为错误消息字符串。转换失败也拒绝 Promise
+pub(crate) fn spawn_serde_promise<F, T, E>(future: F) -> napi_value
+where F: Future<Output = Result<T, E>> + Send + 'static,
+      T: Serialize + Send + 'static,
+      E: Display + Send + 'static {
+    spawn_promise(future, |output| match output {
+        Ok(value) => napi_serde::to_value(&value).map_err(|err| nj_sys::napi_create_string_utf8(&err.to_string())),
+        Err(err) => Err(nj_sys::napi_create_string_utf8(&err.to_string()))
+    })
+}
@@ -0,0 +1,143 @@
+//! 句柄作用域的 RAII 守卫：`HandleScope`与`EscapableHandleScope`。
+//!
+//! 真实的 N-API 在每次调用原生函数时都隐式地打开一个句柄作用域，期间创建的`napi_value`都被它扎根，直至原生函数返回。
+//! 所以，在循环里创建大量临时值的原生函数应为每次迭代打开嵌套的句柄作用域，令临时值及时地变为可被回收的。
+//! 1. 守卫被析构时关闭句柄作用域。嵌套的守卫借用外层的守卫，所以编译器保证内层的守卫先被析构。
+//! 2. `Local<'s>`是被生命周期`'s`绑定于句柄作用域的`napi_value`。它借用创建它的守卫，所以编译器拒绝在句柄作用域
+//!    被关闭之后再使用它。比如，下例编译失败（本模块被`bin`经由`#[path]`挂载，不会被 doctest 执行，所以仅是示意）：
+//!    ```ignore
+//!    let outer = HandleScope::open();
+//!    let object = {
+//!        let inner = HandleScope::nested(&outer);
+//!        inner.create_object()
+//!    }; // error[E0597]: `inner` does not live long enough
+//!    ```
+//! 3. `EscapableHandleScope::escape()`是将`Local`提升至外层句柄作用域的唯一途径，且仅能被调用一次。
+//! 4. 新值总是属于最内层的句柄作用域。所以，在内层的守卫尚存时经由外层的守卫创建新值会 panic。
+#![allow(dead_code)]
+use ::std::{cell::Cell, fmt::{Debug, Formatter, Result as FmtResult}, marker::PhantomData};
+use crate::nj_sys::{self, napi_escapable_handle_scope, napi_handle_scope, napi_ref, napi_value};
+thread_local! {
+    /// 本线程上尚未被关闭的句柄作用域的层数
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+fn enter() -> usize {
+    DEPTH.with(|depth| {
+        depth.set(depth.get() + 1);
+        depth.get()
+    })
+}
+fn leave(depth: usize) {
+    DEPTH.with(|current| {
+        assert_eq!(current.get(), depth, "句柄作用域未按嵌套顺序关闭");
+        current.set(depth - 1);
+    })
+}
+/// 被生命周期`'s`绑定于句柄作用域的`napi_value`
+#[derive(Copy, Clone)]
+pub(crate) struct Local<'s> {
+    value: napi_value,
+    _scope: PhantomData<&'s ()>
+}
+impl Local<'_> {
+    fn new(value: napi_value) -> Self {
+        Local { value, _scope: PhantomData }
+    }
+    /// 供 N-API 外部函数使用的原始句柄。它不再受编译器的检查
+    pub(crate) fn raw(&self) -> napi_value {
+        self.value
+    }
+    pub(crate) fn strict_equals(&self, other: Local<'_>) -> bool {
+        nj_sys::napi_strict_equals(self.value, other.value)
+    }
+}
+impl Debug for Local<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Local({:?})", self.value)
+    }
+}
+/// 被`HandleScope`与`EscapableHandleScope`实现
+pub(crate) trait Scope {
+    /// 被打开时的嵌套层数
+    fn depth(&self) -> usize;
+    fn is_innermost(&self) -> bool {
+        DEPTH.with(Cell::get) == self.depth()
+    }
+    #[track_caller]
+    fn create_object(&self) -> Local<'_> {
+        assert!(self.is_innermost(), "[create_object]新值只能被创建于最内层的句柄作用域");
+        Local::new(nj_sys::napi_create_object())
+    }
+    /// 若 JS 堆对象已被回收，则返回 None
+    #[track_caller]
+    fn reference_value(&self, reference: napi_ref) -> Option<Local<'_>> {
+        assert!(self.is_innermost(), "[reference_value]新值只能被创建于最内层的句柄作用域");
+        nj_sys::napi_get_reference_value(reference).map(Local::new)
+    }
+}
+pub(crate) struct HandleScope<'p> {
+    raw: napi_handle_scope,
+    depth: usize,
+    _parent: PhantomData<&'p ()>
+}
+impl HandleScope<'static> {
+    /// 打开最外层的句柄作用域，就像 N-API 在调用原生函数时所做的那样
+    pub(crate) fn open() -> Self {
+        HandleScope { raw: nj_sys::napi_open_handle_scope(), depth: enter(), _parent: PhantomData }
+    }
+}
+impl<'p> HandleScope<'p> {
+    /// 打开嵌套于`parent`之内的句柄作用域
+    pub(crate) fn nested(parent: &'p impl Scope) -> Self {
+        debug_assert!(parent.is_innermost());
+        HandleScope { raw: nj_sys::napi_open_handle_scope(), depth: enter(), _parent: PhantomData }
+    }
+}
+impl Scope for HandleScope<'_> {
+    fn depth(&self) -> usize {
+        self.depth
+    }
+}
+impl Drop for HandleScope<'_> {
+    fn drop(&mut self) {
+        nj_sys::napi_close_handle_scope(self.raw);
+        leave(self.depth);
+    }
+}
+impl Debug for HandleScope<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("HandleScope").field("raw", &self.raw).field("depth", &self.depth).finish()
+    }
+}
+pub(crate) struct EscapableHandleScope<'p> {
+    raw: napi_escapable_handle_scope,
+    depth: usize,
+    _parent: PhantomData<&'p ()>
+}
+impl<'p> EscapableHandleScope<'p> {
+    /// 打开嵌套于`parent`之内的可逃逸句柄作用域。最外层的句柄作用域无处可逃，所以必须有`parent`
+    pub(crate) fn nested(parent: &'p impl Scope) -> Self {
+        debug_assert!(parent.is_innermost());
+        EscapableHandleScope { raw: nj_sys::napi_open_escapable_handle_scope(), depth: enter(), _parent: PhantomData }
+    }
+    /// 将`value`提升至外层的句柄作用域。第二次调用会 panic
+    pub(crate) fn escape(&self, value: Local<'_>) -> Local<'p> {
+        Local::new(nj_sys::napi_escape_handle(self.raw, value.value))
+    }
+}
+impl Scope for EscapableHandleScope<'_> {
+    fn depth(&self) -> usize {
+        self.depth
+    }
+}
+impl Drop for EscapableHandleScope<'_> {
+    fn drop(&mut self) {
+        nj_sys::napi_close_escapable_handle_scope(self.raw);
+        leave(self.depth);
+    }
+}
+impl Debug for EscapableHandleScope<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("EscapableHandleScope").field("raw", &self.raw).field("depth", &self.depth).finish()
+    }
+}
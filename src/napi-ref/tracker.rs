@@ -0,0 +1,123 @@
+//! 尚存`napi_ref`的调试登记簿：记录每个`NapiRc`、`NapiWeak`与`NapiArc`背后的`napi_ref`被创建的位置、调用栈与存活的克隆个数。
+//!
+//! `NapiRc`泄漏时（比如，循环引用，或被遗忘于长寿结构体里的克隆），JS 堆对象永远不会被回收，却没有任何提示。
+//! 1. 登记簿是可选的：`enable()`之后被创建的`napi_ref`才被登记。`enable()`返回守卫，守卫被析构时（通常是`main()`
+//!    返回时）打印仍存活的`napi_ref`，再停止登记。
+//! 2. `live()`列出、`dump()`打印仍存活的`napi_ref`；`assert_no_live_refs()`断言其为空，否则 panic 并列出它们的创建位置。
+//! 3. 创建位置由`#[track_caller]`逐层传递，所以它指向调用`NapiRc::new()`等构造函数的业务代码，而不是本模块。
+//! 4. `NapiArc`可被跨线程克隆，所以登记簿是全局的，并记录创建`napi_ref`的线程。
+#![allow(dead_code)]
+use ::std::{backtrace::Backtrace, collections::BTreeMap, fmt::{Debug, Display, Formatter, Result as FmtResult}, panic::Location,
+    sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex, PoisonError}, thread};
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Mutex<BTreeMap<usize, LiveRef>> = Mutex::new(BTreeMap::new());
+fn registry() -> ::std::sync::MutexGuard<'static, BTreeMap<usize, LiveRef>> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+/// 被登记的`napi_ref`
+#[derive(Clone, Debug)]
+pub(crate) struct LiveRef {
+    /// 登记序号
+    pub(crate) id: usize,
+    /// NapiRc 或 NapiArc
+    pub(crate) kind: &'static str,
+    /// napi_ref 的 Debug 文本。napi_ref 不是 Send 的，所以不能被保存于全局的登记簿
+    pub(crate) reference: String,
+    /// 存活的克隆个数：NapiRc 与 NapiWeak 的个数之和，或 NapiArc 的个数
+    pub(crate) clones: usize,
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) thread: String,
+    pub(crate) backtrace: Arc<Backtrace>
+}
+impl Display for LiveRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "#{} {} {}：存活的克隆 {} 个，创建于 {}（线程 {}）", self.id, self.kind, self.reference, self.clones, self.location, self.thread)
+    }
+}
+/// `enable()`的守卫。被析构时打印仍存活的`napi_ref`，再停止登记
+#[must_use = "守卫被析构时即停止登记"]
+pub(crate) struct TrackerGuard(());
+impl Drop for TrackerGuard {
+    fn drop(&mut self) {
+        if !live().is_empty() {
+            eprintln!("[napi-ref tracker]退出时：");
+            dump();
+        }
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+}
+/// 开始登记此后被创建的`napi_ref`
+pub(crate) fn enable() -> TrackerGuard {
+    ENABLED.store(true, Ordering::Relaxed);
+    TrackerGuard(())
+}
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+/// 按创建顺序列出仍存活的`napi_ref`
+pub(crate) fn live() -> Vec<LiveRef> {
+    registry().values().cloned().collect()
+}
+/// 打印仍存活的`napi_ref`与它们被创建时的调用栈。返回它们的个数
+pub(crate) fn dump() -> usize {
+    let live = live();
+    eprintln!("[napi-ref tracker]还有 {} 个存活的 napi_ref", live.len());
+    for entry in &live {
+        eprintln!("  {}\n{}", entry, entry.backtrace);
+    }
+    live.len()
+}
+/// 断言没有仍存活的`napi_ref`，否则 panic 并列出它们的创建位置
+#[track_caller]
+pub(crate) fn assert_no_live_refs() {
+    let live = live();
+    if !live.is_empty() {
+        let entries = live.iter().map(|entry| format!("\n  {}", entry)).collect::<String>();
+        panic!("还有 {} 个存活的 napi_ref：{}", live.len(), entries);
+    }
+}
+/// 被`NapiRc`与`NapiArc`的共享状态持有的登记凭证。未启用登记簿时，它什么都不做
+pub(crate) struct Tracked(Option<usize>);
+impl Tracked {
+    #[track_caller]
+    pub(crate) fn new(kind: &'static str, reference: &dyn Debug) -> Self {
+        if !is_enabled() {
+            return Tracked(None);
+        }
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let thread = thread::current().name().map_or_else(|| format!("{:?}", thread::current().id()), str::to_string);
+        registry().insert(id, LiveRef {
+            id,
+            kind,
+            reference: format!("{:?}", reference),
+            clones: 1,
+            location: Location::caller(),
+            thread,
+            backtrace: Arc::new(Backtrace::force_capture())
+        });
+        Tracked(Some(id))
+    }
+    /// 克隆、降级或升级时调用
+    pub(crate) fn cloned(&self) {
+        self.update(|clones| clones + 1);
+    }
+    /// 克隆被析构时调用
+    pub(crate) fn released(&self) {
+        self.update(|clones| clones - 1);
+    }
+    fn update(&self, f: impl FnOnce(usize) -> usize) {
+        if let Some(id) = self.0 {
+            if let Some(entry) = registry().get_mut(&id) {
+                entry.clones = f(entry.clones);
+            }
+        }
+    }
+}
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            registry().remove(&id);
+        }
+    }
+}
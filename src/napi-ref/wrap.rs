@@ -0,0 +1,109 @@
+//! 将 Rust 对象包装进 JS 堆对象：`NapiBox<T>`。
+//!
+//! 与`NapiRc`相反，`NapiBox<T>`令 JS 堆对象拥有 Rust 对象：
+//! 1. `NapiBox::new()`将`Box<RefCell<T>>`经由`napi_wrap()`附着于 JS 堆对象。JS 堆对象被 GC 回收之后，终结器析构
+//!    `Box`，所以`T`的`Drop`在 GC 的终结阶段被执行。
+//! 2. `NapiBox`内含一个`NapiRc`，所以它（与它的克隆）令 JS 堆对象一直存活，从而令被借出的`&T`与`&mut T`一直有效。
+//!    全部`NapiBox`被析构之后，JS 堆对象才可被回收。
+//! 3. `borrow()`与`borrow_mut()`在运行时检查借用规则，就像`RefCell`一样。经由同一个 JS 堆对象的不同`NapiBox`借出的
+//!    引用共享同一个借用标记。
+//! 4. `NapiBox::from_value()`从 JS 端传回的`napi_value`取回被包装的 Rust 对象，并检查其类型。
+use ::std::{any::{self, Any}, cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut}, error::Error, ffi::c_void,
+    fmt::{Debug, Display, Formatter, Result as FmtResult}, marker::PhantomData, ptr};
+use crate::{napi_rc::NapiRc, nj_sys::{self, napi_value}};
+/// 被附着于 JS 堆对象的是瘦指针`*mut Box<dyn Any>`，以便取回时检查类型
+type Payload = Box<dyn Any>;
+/// 终结器：析构被附着的 Rust 对象
+unsafe fn finalize(data: *mut c_void, _hint: *mut c_void) {
+    #[cfg(debug_assertions)]
+    println!("[NapiBox::finalize]JS 堆对象被回收了，析构被包装的 Rust 对象");
+    drop(unsafe { Box::from_raw(data as *mut Payload) });
+}
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WrapError {
+    /// JS 堆对象已包装了另一个 Rust 对象
+    AlreadyWrapped,
+    /// JS 堆对象没有包装任何 Rust 对象
+    NotWrapped,
+    /// JS 堆对象包装的不是`expected`类型的 Rust 对象
+    TypeMismatch { expected: &'static str }
+}
+impl Display for WrapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            WrapError::AlreadyWrapped => write!(f, "JS 堆对象已包装了另一个 Rust 对象"),
+            WrapError::NotWrapped => write!(f, "JS 堆对象没有包装任何 Rust 对象"),
+            WrapError::TypeMismatch { expected } => write!(f, "JS 堆对象包装的不是 {} 类型的 Rust 对象", expected)
+        }
+    }
+}
+impl Error for WrapError {}
+pub(crate) struct NapiBox<T: 'static> {
+    rc: NapiRc,
+    cell: *const RefCell<T>,
+    _type: PhantomData<RefCell<T>>
+}
+impl<T: 'static> NapiBox<T> {
+    /// 将`native`包装进`value`指向的 JS 堆对象。若 JS 堆对象已被包装过，则`native`被析构，并返回错误
+    #[track_caller]
+    pub(crate) fn new(value: napi_value, native: T) -> Result<Self, WrapError> {
+        if nj_sys::napi_unwrap(value).is_some() {
+            return Err(WrapError::AlreadyWrapped);
+        }
+        let payload: Payload = Box::new(RefCell::new(native));
+        let data = Box::into_raw(Box::new(payload));
+        // 安全：finalize 析构的正是 data 指向的 Payload；且本 crate 里只有此处调用 napi_wrap()，所以被附着的指针都是 *mut Payload
+        unsafe { nj_sys::napi_wrap(value, data as *mut c_void, finalize, ptr::null_mut()) };
+        Self::from_value(value)
+    }
+    /// 取回`value`指向的 JS 堆对象所包装的 Rust 对象
+    #[track_caller]
+    pub(crate) fn from_value(value: napi_value) -> Result<Self, WrapError> {
+        let data = nj_sys::napi_unwrap(value).ok_or(WrapError::NotWrapped)?;
+        // 安全：napi_wrap() 是 unsafe 的，且只被 NapiBox::new() 调用，所以被附着的指针都指向 Payload；在 JS 堆对象存活期间，
+        // 终结器也不会被调用
+        let payload = unsafe { &*(data as *const Payload) };
+        let cell = payload.downcast_ref::<RefCell<T>>().ok_or(WrapError::TypeMismatch { expected: any::type_name::<T>() })?;
+        Ok(NapiBox { rc: NapiRc::new(value), cell, _type: PhantomData })
+    }
+    /// 被包装的 Rust 对象已被借出为`&mut T`时 panic
+    pub(crate) fn borrow(&self) -> Ref<'_, T> {
+        self.cell().borrow()
+    }
+    /// 被包装的 Rust 对象已被借出时 panic
+    pub(crate) fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.cell().borrow_mut()
+    }
+    pub(crate) fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
+        self.cell().try_borrow()
+    }
+    pub(crate) fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        self.cell().try_borrow_mut()
+    }
+    /// 令 JS 堆对象存活的强引用
+    pub(crate) fn rc(&self) -> &NapiRc {
+        &self.rc
+    }
+    pub(crate) fn value(&self) -> napi_value {
+        self.rc.value()
+    }
+    fn cell(&self) -> &RefCell<T> {
+        // 安全：self.rc 令 JS 堆对象存活，所以终结器尚未析构 RefCell
+        unsafe { &*self.cell }
+    }
+}
+impl<T: 'static> Clone for NapiBox<T> {
+    fn clone(&self) -> Self {
+        NapiBox { rc: self.rc.clone(), cell: self.cell, _type: PhantomData }
+    }
+}
+impl<T: Debug + 'static> Debug for NapiBox<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut debug = f.debug_struct("NapiBox");
+        debug.field("reference", &*self.rc);
+        match self.try_borrow() {
+            Ok(native) => debug.field("native", &*native),
+            Err(_) => debug.field("native", &"<已被借出为 &mut T>")
+        }.finish()
+    }
+}
@@ -0,0 +1,280 @@
+//! Rust 与页面脚本之间的 JSON-RPC 2.0 桥。与平台无关，所以可在 Linux 上被编译与测试。
+//!
+//! 1. `Bridge::register()`与`Bridge::register_async()`按方法名登记 Rust 处理函数。参数与返回值都经由 serde 转换。
+//! 2. 页面脚本的调用是 JSON-RPC 请求：`{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}`。`Bridge::handle_message()`
+//!    分派请求，再经由`Transport`回复 JSON-RPC 响应；页面端的`rustBridge.call()`将其兑现为 Promise（见`BRIDGE_SCRIPT`）。
+//!    没有`id`的请求是通知，不被回复。暂不支持批量请求。
+//! 3. `Bridge::emit()`向页面推送事件，即以事件名为方法名的 JSON-RPC 通知。页面端以`rustBridge.on()`监听它。
+//! 4. 同步处理函数被立即回复；异步处理函数的`Future`被交给执行器，待其完成时再回复。
+//! 5. `Transport`只负责投递 JSON 文本。`memory_transport()`与`MemoryPage`在内存里模拟页面，以供测试；
+//!    WebView2 的适配器见`bridge_webview2.rs`。
+#![allow(dead_code)]
+use ::futures::{channel::oneshot, future::LocalBoxFuture, task::{LocalSpawn, LocalSpawnExt}, FutureExt};
+use ::serde::{de::DeserializeOwned, Deserialize, Serialize};
+use ::serde_json::{json, Value};
+use ::std::{cell::{Cell, RefCell}, collections::{HashMap, VecDeque}, error::Error, fmt::{Display, Formatter, Result as FmtResult}, future::Future, rc::Rc};
+/// 被注入页面的脚本：在`window.rustBridge`上提供`call(method, params) -> Promise`、`notify(method, params)`与
+/// `on(event, listener) -> 取消监听的函数`。它经由 WebView2 的`window.chrome.webview`收发 JSON 文本
+pub(crate) const BRIDGE_SCRIPT: &str = r#"(() => {
+    const pending = new Map(), listeners = new Map();
+    let nextId = 1;
+    const post = message => window.chrome.webview.postMessage(JSON.stringify(message));
+    window.chrome.webview.addEventListener('message', ({ data }) => {
+        const message = typeof data === 'string' ? JSON.parse(data) : data;
+        if (message.id !== undefined && pending.has(message.id)) {
+            const { resolve, reject } = pending.get(message.id);
+            pending.delete(message.id);
+            message.error ? reject(Object.assign(new Error(message.error.message), message.error)) : resolve(message.result);
+        } else if (typeof message.method === 'string') {
+            (listeners.get(message.method) || []).forEach(listener => listener(message.params));
+        }
+    });
+    window.rustBridge = {
+        call: (method, params) => new Promise((resolve, reject) => {
+            const id = nextId++;
+            pending.set(id, { resolve, reject });
+            post({ jsonrpc: '2.0', id, method, params });
+        }),
+        notify: (method, params) => post({ jsonrpc: '2.0', method, params }),
+        on(event, listener) {
+            listeners.set(event, [...(listeners.get(event) || []), listener]);
+            return () => listeners.set(event, listeners.get(event).filter(l => l !== listener));
+        }
+    };
+})();"#;
+/// JSON-RPC 的错误对象
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) data: Option<Value>
+}
+impl RpcError {
+    pub(crate) const PARSE_ERROR: i64 = -32700;
+    pub(crate) const INVALID_REQUEST: i64 = -32600;
+    pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+    pub(crate) const INVALID_PARAMS: i64 = -32602;
+    pub(crate) const INTERNAL_ERROR: i64 = -32603;
+    /// 处理函数返回的业务错误
+    pub(crate) const SERVER_ERROR: i64 = -32000;
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into(), data: None }
+    }
+    pub(crate) fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "[{}]{}", self.code, self.message)
+    }
+}
+impl Error for RpcError {}
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        RpcError::new(RpcError::SERVER_ERROR, message)
+    }
+}
+impl From<&str> for RpcError {
+    fn from(message: &str) -> Self {
+        RpcError::new(RpcError::SERVER_ERROR, message)
+    }
+}
+#[derive(Debug)]
+pub(crate) enum BridgeError {
+    Json(::serde_json::Error),
+    /// 传输层投递失败
+    Transport(String)
+}
+impl Display for BridgeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            BridgeError::Json(err) => write!(f, "JSON 转换失败：{}", err),
+            BridgeError::Transport(err) => write!(f, "投递失败：{}", err)
+        }
+    }
+}
+impl Error for BridgeError {}
+impl From<::serde_json::Error> for BridgeError {
+    fn from(err: ::serde_json::Error) -> Self {
+        BridgeError::Json(err)
+    }
+}
+/// 向页面投递 JSON 文本
+pub(crate) trait Transport {
+    fn post(&self, message: &str) -> Result<(), BridgeError>;
+}
+type Reply = Result<Value, RpcError>;
+#[derive(Clone)]
+enum Handler {
+    Sync(Rc<dyn Fn(Value) -> Reply>),
+    Async(Rc<dyn Fn(Value) -> LocalBoxFuture<'static, Reply>>)
+}
+struct Inner {
+    handlers: RefCell<HashMap<String, Handler>>,
+    transport: Box<dyn Transport>,
+    spawner: Box<dyn LocalSpawn>
+}
+/// 与 UI 线程绑定，所以以`Rc`共享。克隆的`Bridge`共享同一组处理函数
+#[derive(Clone)]
+pub(crate) struct Bridge(Rc<Inner>);
+fn params<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+    ::serde_json::from_value(params).map_err(|err| RpcError::new(RpcError::INVALID_PARAMS, err.to_string()))
+}
+fn result<R: Serialize>(result: Result<R, RpcError>) -> Reply {
+    result.and_then(|result| ::serde_json::to_value(result).map_err(|err| RpcError::new(RpcError::INTERNAL_ERROR, err.to_string())))
+}
+impl Bridge {
+    /// `spawner`执行异步处理函数的`Future`，比如`LocalPool::spawner()`
+    pub(crate) fn new(transport: impl Transport + 'static, spawner: impl LocalSpawn + 'static) -> Self {
+        Bridge(Rc::new(Inner { handlers: RefCell::default(), transport: Box::new(transport), spawner: Box::new(spawner) }))
+    }
+    /// 登记同步处理函数。同名的处理函数被替换
+    pub(crate) fn register<P, R, F>(&self, method: &str, handler: F)
+    where P: DeserializeOwned, R: Serialize, F: Fn(P) -> Result<R, RpcError> + 'static {
+        let handler = move |value| result(params(value).and_then(&handler));
+        self.0.handlers.borrow_mut().insert(method.to_string(), Handler::Sync(Rc::new(handler)));
+    }
+    /// 登记异步处理函数。同名的处理函数被替换
+    pub(crate) fn register_async<P, R, F, Fut>(&self, method: &str, handler: F)
+    where P: DeserializeOwned, R: Serialize + 'static, F: Fn(P) -> Fut + 'static, Fut: Future<Output = Result<R, RpcError>> + 'static {
+        let handler = move |value| match params(value) {
+            Ok(params) => handler(params).map(result).boxed_local(),
+            Err(err) => ::futures::future::ready(Err(err)).boxed_local()
+        };
+        self.0.handlers.borrow_mut().insert(method.to_string(), Handler::Async(Rc::new(handler)));
+    }
+    /// 返回是否有被注销的处理函数
+    pub(crate) fn unregister(&self, method: &str) -> bool {
+        self.0.handlers.borrow_mut().remove(method).is_some()
+    }
+    /// 向页面推送事件
+    pub(crate) fn emit(&self, event: &str, payload: impl Serialize) -> Result<(), BridgeError> {
+        let message = json!({ "jsonrpc": "2.0", "method": event, "params": ::serde_json::to_value(payload)? });
+        self.0.transport.post(&message.to_string())
+    }
+    /// 分派页面发来的 JSON-RPC 请求或通知。由传输层的适配器在收到消息时调用
+    pub(crate) fn handle_message(&self, message: &str) {
+        let request = match ::serde_json::from_str::<Value>(message) {
+            Ok(request) => request,
+            Err(err) => return self.reply(Some(Value::Null), Err(RpcError::new(RpcError::PARSE_ERROR, err.to_string())))
+        };
+        let id = request.get("id").cloned();
+        let method = match (request.get("jsonrpc").and_then(Value::as_str), request.get("method").and_then(Value::as_str)) {
+            (Some("2.0"), Some(method)) => method,
+            _ => return self.reply(Some(id.unwrap_or(Value::Null)), Err(RpcError::new(RpcError::INVALID_REQUEST, "不是 JSON-RPC 2.0 请求")))
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        // 先克隆出处理函数，再调用它，以便处理函数再登记或注销其它处理函数
+        let handler = self.0.handlers.borrow().get(method).cloned();
+        match handler {
+            None => self.reply(id, Err(RpcError::new(RpcError::METHOD_NOT_FOUND, format!("没有名为 {} 的处理函数", method)))),
+            Some(Handler::Sync(handler)) => self.reply(id, handler(params)),
+            Some(Handler::Async(handler)) => {
+                let this = self.clone();
+                let future = handler(params).map(move |reply| this.reply(id, reply));
+                if let Err(err) = self.0.spawner.spawn_local(future) {
+                    eprintln!("[Bridge][handle_message]{err}");
+                }
+            }
+        }
+    }
+    /// `id`是 None 的请求是通知，不被回复
+    fn reply(&self, id: Option<Value>, reply: Reply) {
+        let Some(id) = id else {
+            if let Err(err) = reply {
+                eprintln!("[Bridge][notification]{err}");
+            }
+            return;
+        };
+        let response = match reply {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        };
+        if let Err(err) = self.0.transport.post(&response.to_string()) {
+            eprintln!("[Bridge][reply]{err}");
+        }
+    }
+}
+/// 页面端的收件箱
+pub(crate) type Inbox = Rc<RefCell<VecDeque<String>>>;
+/// 将消息投递进内存收件箱的传输层
+pub(crate) struct MemoryTransport(Inbox);
+impl Transport for MemoryTransport {
+    fn post(&self, message: &str) -> Result<(), BridgeError> {
+        self.0.borrow_mut().push_back(message.to_string());
+        Ok(())
+    }
+}
+pub(crate) fn memory_transport() -> (MemoryTransport, Inbox) {
+    let inbox = Inbox::default();
+    (MemoryTransport(Rc::clone(&inbox)), inbox)
+}
+type Listener = Rc<dyn Fn(&Value)>;
+/// 在内存里模拟注入了`BRIDGE_SCRIPT`的页面。`pump()`模拟页面事件循环的一轮：投递收件箱里的响应与事件
+pub(crate) struct MemoryPage {
+    bridge: Bridge,
+    inbox: Inbox,
+    next_id: Cell<u64>,
+    pending: RefCell<HashMap<u64, oneshot::Sender<Reply>>>,
+    listeners: RefCell<HashMap<String, Vec<Listener>>>
+}
+impl MemoryPage {
+    pub(crate) fn new(bridge: Bridge, inbox: Inbox) -> Self {
+        MemoryPage { bridge, inbox, next_id: Cell::new(1), pending: RefCell::default(), listeners: RefCell::default() }
+    }
+    /// 就像`rustBridge.call()`：请求被立即发出，而返回的`Future`在`pump()`投递了响应之后才完成
+    pub(crate) fn call<R: DeserializeOwned>(&self, method: &str, params: impl Serialize) -> impl Future<Output = Result<R, RpcError>> {
+        let id = self.next_id.replace(self.next_id.get() + 1);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.borrow_mut().insert(id, sender);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        async move {
+            let reply = receiver.await.unwrap_or_else(|_| Err(RpcError::new(RpcError::INTERNAL_ERROR, "页面已被关闭")))?;
+            ::serde_json::from_value(reply).map_err(|err| RpcError::new(RpcError::INTERNAL_ERROR, err.to_string()))
+        }
+    }
+    /// 就像`rustBridge.notify()`
+    pub(crate) fn notify(&self, method: &str, params: impl Serialize) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+    /// 就像`rustBridge.on()`
+    pub(crate) fn on(&self, event: &str, listener: impl Fn(&Value) + 'static) {
+        self.listeners.borrow_mut().entry(event.to_string()).or_default().push(Rc::new(listener));
+    }
+    /// 投递收件箱里的全部消息。返回被投递的消息个数
+    pub(crate) fn pump(&self) -> usize {
+        let mut count = 0;
+        // 逐个出队，以便监听函数再发出请求
+        while let Some(message) = self.inbox.borrow_mut().pop_front().map(|message| ::serde_json::from_str::<Value>(&message).unwrap()) {
+            count += 1;
+            let pending = message.get("id").and_then(Value::as_u64).and_then(|id| self.pending.borrow_mut().remove(&id));
+            match (pending, message.get("method").and_then(Value::as_str)) {
+                (Some(sender), _) => {
+                    let reply = match message.get("error") {
+                        Some(error) => Err(::serde_json::from_value(error.clone()).unwrap()),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null))
+                    };
+                    let _ = sender.send(reply);
+                },
+                (None, Some(event)) => {
+                    let listeners = self.listeners.borrow().get(event).cloned().unwrap_or_default();
+                    let params = message.get("params").cloned().unwrap_or(Value::Null);
+                    listeners.iter().for_each(|listener| listener(&params));
+                },
+                (None, None) => eprintln!("[MemoryPage][pump]无人认领的消息：{}", message)
+            }
+        }
+        count
+    }
+    /// 发出原始文本，再取出收件箱里未被投递的全部消息。以供测试畸形的请求
+    pub(crate) fn send_raw(&self, message: &str) -> Vec<Value> {
+        self.bridge.handle_message(message);
+        self.inbox.borrow_mut().drain(..).map(|message| ::serde_json::from_str(&message).unwrap()).collect()
+    }
+    fn send(&self, message: Value) {
+        self.bridge.handle_message(&message.to_string());
+    }
+}
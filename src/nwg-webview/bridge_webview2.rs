@@ -0,0 +1,55 @@
+//! `bridge::Bridge`的 WebView2 适配器：注入`BRIDGE_SCRIPT`，并经由`post_web_message_as_string()`与`add_web_message_received()`收发消息。
+//!
+//! `WebView`持有消息处理函数，处理函数持有`Bridge`，而`Bridge`又经由`WebViewTransport`持有`WebView`。这是个引用环，
+//! 所以`attach()`返回`AttachedBridge`守卫：它被析构（或被`detach()`）时注销消息处理函数，从而拆开引用环。
+use ::futures::task::LocalSpawn;
+use ::std::ops::Deref;
+use ::webview2::{EventRegistrationToken, WebView};
+use super::bridge::{Bridge, BridgeError, Transport, BRIDGE_SCRIPT};
+pub(crate) struct WebViewTransport(WebView);
+impl Transport for WebViewTransport {
+    fn post(&self, message: &str) -> Result<(), BridgeError> {
+        self.0.post_web_message_as_string(message).map_err(|err| BridgeError::Transport(err.to_string()))
+    }
+}
+/// 被附着于`WebView`的`Bridge`。须一直持有它，否则页面的调用不再被分派
+pub(crate) struct AttachedBridge {
+    bridge: Bridge,
+    webview: WebView,
+    token: Option<EventRegistrationToken>
+}
+impl AttachedBridge {
+    /// 注销消息处理函数。此后，页面的调用不再被分派
+    pub(crate) fn detach(mut self) -> ::webview2::Result<()> {
+        self.remove_handler()
+    }
+    fn remove_handler(&mut self) -> ::webview2::Result<()> {
+        self.token.take().map_or(Ok(()), |token| self.webview.remove_web_message_received(token))
+    }
+}
+impl Deref for AttachedBridge {
+    type Target = Bridge;
+    fn deref(&self) -> &Bridge {
+        &self.bridge
+    }
+}
+impl Drop for AttachedBridge {
+    fn drop(&mut self) {
+        if let Err(err) = self.remove_handler() {
+            eprintln!("[AttachedBridge][drop]{err}");
+        }
+    }
+}
+/// 在`WebView`导航之前调用，以便页面一被创建即可使用`window.rustBridge`
+pub(crate) fn attach(webview: &WebView, spawner: impl LocalSpawn + 'static) -> ::webview2::Result<AttachedBridge> {
+    let bridge = Bridge::new(WebViewTransport(webview.clone()), spawner);
+    webview.add_script_to_execute_on_document_created(BRIDGE_SCRIPT, |_| Ok(()))?;
+    let token = webview.add_web_message_received({
+        let bridge = bridge.clone();
+        move |_, args| {
+            bridge.handle_message(&args.try_get_web_message_as_string()?);
+            Ok(())
+        }
+    })?;
+    Ok(AttachedBridge { bridge, webview: webview.clone(), token: Some(token) })
+}
@@ -1,10 +1,16 @@
+mod bridge;
+mod bridge_webview2;
 mod builder;
+pub(crate) mod reactor;
+mod reactor_nwg;
 use ::deferred_future::LocalDeferredFuture;
 use ::futures::future::Shared;
 use ::nwg::{self as nwg, ControlHandle, EventHandler, Frame, NwgError, RawEventHandler};
 use ::std::{cell::RefCell, ops::Deref, rc::Rc};
 use ::webview2::{Controller, WebView};
+pub(crate) use bridge_webview2::{attach as attach_bridge, AttachedBridge};
 pub use builder::WebviewContainerBuilder;
+pub(crate) use reactor_nwg::NwgHost;
 
 pub type NwgResult<T> = Result<T, NwgError>;
 #[derive(Default)]
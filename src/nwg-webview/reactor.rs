@@ -0,0 +1,305 @@
+//! 以 GUI 的事件循环为【反应器】，对接 futures crate 的【执行器】。与 UI 工具库无关，所以可在 Linux 上被编译与测试。
+//!
+//! 1. `HostLoop`抽象了宿主的事件循环：它提供可跨线程唤醒自己的`HostWaker`，并在每次被唤醒之后调用一轮`turn`回调。
+//!    `turn`回调返回下一个定时器的截止时刻，以便宿主在没有被唤醒时也按时调用它。
+//! 2. `Reactor`在每一轮里：唤醒到期的定时器，再以`LocalPool::run_until_stalled()`执行全部就绪的任务。
+//! 3. 经由`Handle`被提交的任务，其`Waker`被包装过：任务被唤醒时（无论来自哪个线程），宿主的事件循环也被唤醒。
+//!    所以事件循环空闲时不必空转，不像`nwg::dispatch_thread_events_with_callback()`那样每轮都轮询执行器。
+//! 4. `Handle::sleep()`与`Handle::sleep_until()`是由反应器驱动的定时器。
+//! 5. `ChannelHost`是基于`std::sync::mpsc`的纯 Rust 事件循环，以供测试调度行为；nwg 的适配器见`reactor_nwg.rs`。
+#![allow(dead_code)]
+use ::futures::{executor::{LocalPool, LocalSpawner}, future::{FutureExt, LocalFutureObj}, task::{self, ArcWake, LocalSpawn, LocalSpawnExt, SpawnError}};
+use ::std::{cell::{Cell, RefCell}, collections::BTreeMap, future::Future, mem, pin::Pin, rc::Rc,
+    sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc::{self, Receiver, RecvTimeoutError, Sender}, Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker}, time::{Duration, Instant}};
+/// 唤醒宿主的事件循环。可在任意线程上被调用，且多次唤醒可被合并为一轮`turn`
+pub(crate) trait HostWaker: Send + Sync + 'static {
+    fn wake(&self);
+}
+/// `turn`回调的返回值
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Turn {
+    /// 等待下一次唤醒。若有截止时刻，则届时即使没有被唤醒，也再调用一轮`turn`
+    Wait(Option<Instant>),
+    /// 退出事件循环
+    Quit
+}
+/// 宿主的事件循环
+pub(crate) trait HostLoop {
+    fn waker(&self) -> Arc<dyn HostWaker>;
+    /// 运行事件循环，直至`turn`返回`Turn::Quit`，或宿主自行退出（比如，主窗体被关闭）。宿主须：
+    /// 1. 在开始时调用一轮`turn`；
+    /// 2. 在被唤醒之后调用一轮`turn`；
+    /// 3. 在`Turn::Wait(Some(deadline))`到期时调用一轮`turn`。
+    fn run(&mut self, turn: Box<dyn FnMut() -> Turn>);
+}
+thread_local! {
+    /// 当前线程是否正在执行某一轮 turn
+    static IN_TURN: Cell<bool> = const { Cell::new(false) };
+}
+/// 在某一轮 turn 期间置位`IN_TURN`。即使任务 panic 了，栈展开时它也会被析构，从而复位`IN_TURN`
+struct InTurn;
+impl InTurn {
+    fn enter() -> Self {
+        IN_TURN.with(|in_turn| in_turn.set(true));
+        InTurn
+    }
+}
+impl Drop for InTurn {
+    fn drop(&mut self) {
+        IN_TURN.with(|in_turn| in_turn.set(false));
+    }
+}
+type TimerKey = (Instant, u64);
+#[derive(Default)]
+struct Timers {
+    next_id: u64,
+    wakers: BTreeMap<TimerKey, Waker>
+}
+impl Timers {
+    /// 取出截止时刻不晚于`now`的定时器
+    fn expire(&mut self, now: Instant) -> Vec<Waker> {
+        let later = self.wakers.split_off(&(now, u64::MAX));
+        mem::replace(&mut self.wakers, later).into_values().collect()
+    }
+    fn next_deadline(&self) -> Option<Instant> {
+        self.wakers.keys().next().map(|(deadline, _)| *deadline)
+    }
+}
+/// 由反应器驱动的定时器
+pub(crate) struct Sleep {
+    deadline: Instant,
+    key: Option<TimerKey>,
+    timers: Rc<RefCell<Timers>>
+}
+impl Sleep {
+    pub(crate) fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+impl Future for Sleep {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut timers = self.timers.borrow_mut();
+        if Instant::now() >= self.deadline {
+            if let Some(key) = self.key {
+                timers.wakers.remove(&key);
+            }
+            return Poll::Ready(());
+        }
+        let key = self.key.unwrap_or_else(|| {
+            timers.next_id += 1;
+            (self.deadline, timers.next_id)
+        });
+        timers.wakers.insert(key, cx.waker().clone());
+        drop(timers);
+        self.key = Some(key);
+        Poll::Pending
+    }
+}
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.timers.borrow_mut().wakers.remove(&key);
+        }
+    }
+}
+/// 同时唤醒任务与宿主的事件循环
+struct HostAwareWaker {
+    task: Waker,
+    host: Arc<dyn HostWaker>
+}
+impl ArcWake for HostAwareWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.task.wake_by_ref();
+        // 在本轮 turn 里被唤醒的任务会被 run_until_stalled() 接着执行，所以不必再唤醒事件循环
+        if !IN_TURN.with(Cell::get) {
+            arc_self.host.wake();
+        }
+    }
+}
+/// 以`HostAwareWaker`轮询被包装的任务。任务的`Waker`不变时，复用上一次构造的`HostAwareWaker`
+struct WakeHost {
+    future: LocalFutureObj<'static, ()>,
+    host: Arc<dyn HostWaker>,
+    waker: Option<(Waker, Waker)>
+}
+impl Future for WakeHost {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        let waker = match &this.waker {
+            Some((task, waker)) if task.will_wake(cx.waker()) => waker.clone(),
+            _ => {
+                let waker = task::waker(Arc::new(HostAwareWaker { task: cx.waker().clone(), host: Arc::clone(&this.host) }));
+                this.waker = Some((cx.waker().clone(), waker.clone()));
+                waker
+            }
+        };
+        this.future.poll_unpin(&mut Context::from_waker(&waker))
+    }
+}
+/// 提交任务与创建定时器。可被克隆，且被克隆的`Handle`都指向同一个`Reactor`
+#[derive(Clone)]
+pub(crate) struct Handle {
+    spawner: LocalSpawner,
+    timers: Rc<RefCell<Timers>>,
+    host: Arc<dyn HostWaker>
+}
+impl Handle {
+    pub(crate) fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(Instant::now() + duration)
+    }
+    pub(crate) fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep { deadline, key: None, timers: Rc::clone(&self.timers) }
+    }
+}
+impl LocalSpawn for Handle {
+    fn spawn_local_obj(&self, future: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
+        let future = WakeHost { future, host: Arc::clone(&self.host), waker: None };
+        self.spawner.spawn_local_obj(LocalFutureObj::new(Box::new(future)))?;
+        // 任务可能是在事件循环的回调里（而不是在某一轮 turn 里）被提交的，所以唤醒事件循环，以便尽快执行它
+        if !IN_TURN.with(Cell::get) {
+            self.host.wake();
+        }
+        Ok(())
+    }
+    fn status_local(&self) -> Result<(), SpawnError> {
+        self.spawner.status_local()
+    }
+}
+/// 与 UI 线程绑定。`run()`与`block_on()`不可重入：不要在任务里调用它们
+pub(crate) struct Reactor {
+    pool: Rc<RefCell<LocalPool>>,
+    handle: Handle
+}
+impl Reactor {
+    pub(crate) fn new(host: &impl HostLoop) -> Self {
+        let pool = LocalPool::new();
+        let handle = Handle { spawner: pool.spawner(), timers: Rc::default(), host: host.waker() };
+        Reactor { pool: Rc::new(RefCell::new(pool)), handle }
+    }
+    pub(crate) fn handle(&self) -> Handle {
+        self.handle.clone()
+    }
+    /// 运行宿主的事件循环，直至宿主自行退出
+    pub(crate) fn run(&self, host: &mut impl HostLoop) {
+        let mut turn = self.turn();
+        host.run(Box::new(move || Turn::Wait(turn())));
+    }
+    /// 运行宿主的事件循环，直至`future`完成。若宿主先退出了，则返回 None
+    pub(crate) fn block_on<F: Future + 'static>(&self, host: &mut impl HostLoop, future: F) -> Option<F::Output> {
+        let output = Rc::new(RefCell::new(None));
+        self.handle.spawn_local({
+            let output = Rc::clone(&output);
+            future.map(move |value| *output.borrow_mut() = Some(value))
+        }).map_err(|err| eprintln!("[Reactor][block_on]{err}")).ok()?;
+        let mut turn = self.turn();
+        host.run(Box::new({
+            let output = Rc::clone(&output);
+            move || {
+                let deadline = turn();
+                if output.borrow().is_some() { Turn::Quit } else { Turn::Wait(deadline) }
+            }
+        }));
+        let output = output.borrow_mut().take();
+        output
+    }
+    /// 一轮：唤醒到期的定时器，再执行全部就绪的任务，直至没有到期的定时器。返回下一个定时器的截止时刻
+    fn turn(&self) -> impl FnMut() -> Option<Instant> {
+        let pool = Rc::clone(&self.pool);
+        let timers = Rc::clone(&self.handle.timers);
+        move || {
+            let _in_turn = InTurn::enter();
+            loop {
+                let expired = timers.borrow_mut().expire(Instant::now());
+                expired.into_iter().for_each(Waker::wake);
+                pool.borrow_mut().run_until_stalled();
+                let deadline = timers.borrow().next_deadline();
+                if deadline.map_or(true, |deadline| deadline > Instant::now()) {
+                    break deadline;
+                }
+            }
+        }
+    }
+}
+enum Message {
+    Wake,
+    Quit
+}
+/// 可跨线程唤醒或退出`ChannelHost`
+#[derive(Clone)]
+pub(crate) struct ChannelRemote {
+    // mpsc::Sender 不是 Sync 的
+    sender: Arc<Mutex<Sender<Message>>>,
+    wake_pending: Arc<AtomicBool>,
+    wakes: Arc<AtomicUsize>
+}
+impl ChannelRemote {
+    /// 就像主窗体被关闭了
+    pub(crate) fn quit(&self) {
+        let _ = self.sender.lock().unwrap_or_else(PoisonError::into_inner).send(Message::Quit);
+    }
+    /// 被合并之前的唤醒次数
+    pub(crate) fn wakes(&self) -> usize {
+        self.wakes.load(Ordering::Relaxed)
+    }
+}
+impl HostWaker for ChannelRemote {
+    fn wake(&self) {
+        self.wakes.fetch_add(1, Ordering::Relaxed);
+        // 尚未被处理的唤醒只投递一次
+        if !self.wake_pending.swap(true, Ordering::AcqRel) {
+            let _ = self.sender.lock().unwrap_or_else(PoisonError::into_inner).send(Message::Wake);
+        }
+    }
+}
+/// 基于`std::sync::mpsc`的事件循环
+pub(crate) struct ChannelHost {
+    remote: ChannelRemote,
+    receiver: Receiver<Message>,
+    turns: usize
+}
+impl Default for ChannelHost {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let remote = ChannelRemote { sender: Arc::new(Mutex::new(sender)), wake_pending: Arc::default(), wakes: Arc::default() };
+        ChannelHost { remote, receiver, turns: 0 }
+    }
+}
+impl ChannelHost {
+    pub(crate) fn remote(&self) -> ChannelRemote {
+        self.remote.clone()
+    }
+    /// 已被调用的`turn`轮数
+    pub(crate) fn turns(&self) -> usize {
+        self.turns
+    }
+}
+impl HostLoop for ChannelHost {
+    fn waker(&self) -> Arc<dyn HostWaker> {
+        Arc::new(self.remote.clone())
+    }
+    fn run(&mut self, mut turn: Box<dyn FnMut() -> Turn>) {
+        loop {
+            // 先清除标记，以便 turn 期间来自其它线程的唤醒再触发一轮
+            self.remote.wake_pending.store(false, Ordering::Release);
+            self.turns += 1;
+            let deadline = match turn() {
+                Turn::Quit => return,
+                Turn::Wait(deadline) => deadline
+            };
+            let message = match deadline {
+                None => self.receiver.recv().unwrap_or(Message::Quit),
+                Some(deadline) => match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(message) => message,
+                    Err(RecvTimeoutError::Timeout) => Message::Wake,
+                    Err(RecvTimeoutError::Disconnected) => Message::Quit
+                }
+            };
+            if let Message::Quit = message {
+                return;
+            }
+        }
+    }
+}
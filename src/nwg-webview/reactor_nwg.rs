@@ -0,0 +1,82 @@
+//! `reactor::HostLoop`的 nwg 适配器：以`Notice`唤醒 win32 UI 的事件循环，并以一个后台线程在定时器到期时发出`Notice`。
+//!
+//! `WebviewContainer::ready_fut()`在 WebView2 的回调里被兑现。等待它的任务被唤醒时，事件循环也随之被唤醒，所以不必每轮都轮询执行器。
+use ::nwg::{self as nwg, ControlHandle, Event as NwgEvent, Notice, NoticeSender};
+use ::std::{cell::{Cell, RefCell}, sync::{mpsc::{self, RecvTimeoutError, Sender}, Arc}, thread, time::Instant};
+use super::{NwgResult, reactor::{HostLoop, HostWaker, Turn}};
+struct NoticeWaker(NoticeSender);
+impl HostWaker for NoticeWaker {
+    fn wake(&self) {
+        self.0.notice();
+    }
+}
+pub(crate) struct NwgHost {
+    parent: ControlHandle,
+    notice: Notice,
+    /// 向后台线程投递下一个截止时刻。None 取消尚未到期的截止时刻
+    deadlines: Sender<Option<Instant>>
+}
+impl NwgHost {
+    /// `parent`通常是主窗体。`Notice`的事件被投递给它
+    pub(crate) fn new<C: Into<ControlHandle>>(parent: C) -> NwgResult<Self> {
+        let parent = parent.into();
+        let mut notice = Notice::default();
+        Notice::builder().parent(parent).build(&mut notice)?;
+        let (deadlines, receiver) = mpsc::channel::<Option<Instant>>();
+        let sender = notice.sender();
+        // NwgHost 被析构之后，deadlines 被关闭，后台线程随之退出
+        thread::spawn(move || {
+            let mut deadline = None;
+            loop {
+                let received = match deadline {
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                };
+                deadline = match received {
+                    Ok(next) => next,
+                    Err(RecvTimeoutError::Timeout) => {
+                        sender.notice();
+                        None
+                    },
+                    Err(RecvTimeoutError::Disconnected) => return
+                };
+            }
+        });
+        Ok(NwgHost { parent, notice, deadlines })
+    }
+}
+impl HostLoop for NwgHost {
+    fn waker(&self) -> Arc<dyn HostWaker> {
+        Arc::new(NoticeWaker(self.notice.sender()))
+    }
+    fn run(&mut self, turn: Box<dyn FnMut() -> Turn>) {
+        let turn = RefCell::new(turn);
+        // 某一轮 turn 期间，嵌套的模态消息循环又派发了 Notice。它被记下，待本轮结束之后再补一轮，而不是立即重新投递
+        let missed = Cell::new(false);
+        let deadlines = self.deadlines.clone();
+        let notice_handle = self.notice.handle;
+        let handler = nwg::full_bind_event_handler(&self.parent, move |event, _data, handle| {
+            if !matches!(event, NwgEvent::OnNotice) || handle != notice_handle {
+                return;
+            }
+            let Ok(mut turn) = turn.try_borrow_mut() else {
+                return missed.set(true);
+            };
+            loop {
+                missed.set(false);
+                match turn() {
+                    Turn::Quit => return nwg::stop_thread_dispatch(),
+                    Turn::Wait(deadline) => deadlines.send(deadline).unwrap_or_else(|err| eprintln!("[NwgHost][run]{err}"))
+                }
+                if !missed.get() {
+                    return;
+                }
+            }
+        });
+        // 开始时的一轮
+        self.notice.sender().notice();
+        // 阻塞主线程，直至主窗体被关闭或 turn 返回 Turn::Quit
+        nwg::dispatch_thread_events();
+        nwg::unbind_event_handler(&handler);
+    }
+}
@@ -0,0 +1,370 @@
+//! 【类型·状态】设计模式的（跨`bin`）复用工具箱。
+//!
+//! 挂载方式（必须被挂载为`crate`根下的`type_states`模块，因为宏内部经由`$crate::type_states`路径引用本模块的类型）：
+//! ```ignore
+//! #[path = "../type_states.rs"]
+//! #[macro_use]
+//! mod type_states;
+//! ```
+//! 包括：
+//! 1. `seal_by_trait!()` —— 限定 + 密封【状态·类型】
+//! 2. `group_by_trait!()` —— 分组【状态·类型】
+//! 3. `transitions!()` —— 声明【状态·过渡】边，生成“消费”紧前状态的【状态·过渡】成员方法与机读的【状态·过渡】表
+//! 4. `get_mutex_lock!()` —— 无视【互斥锁】中毒，读写被保护数据
+//!
+//! `seal_by_trait!()`与`group_by_trait!()`的【状态·类型】列表项支持两种写法：
+//! 1. 无泛型参数的【状态·类型】，比如`Idle`
+//! 2. `impl[泛型参数列表] 状态类型 where[限定条件列表]`。其中，泛型参数列表可包括生命周期参数、多个
+//!    类型参数与多个`trait bound`；`where[..]`是可选的。比如，
+//!    `impl['a, T: Clone + Send, U] Cache<'a, T, U> where[U: 'a + Debug]`
+#![allow(dead_code, unused_macros)]
+/// 宏功能：给【状态·类型】列表逐一实现（若干个）`marker trait`
+/// 意图：被`seal_by_trait!()`与`group_by_trait!()`共用的列表项解析器。按列表项逐个“咀嚼”，
+/// 因为`impl[..]`前缀与`impl Trait`类型语法有歧义，所以不能直接用重复匹配。
+macro_rules! impl_for_states {
+    (@impl $trait: path; [$($generics: tt)*] $state: ty; [$($bounds: tt)*]) => {
+        impl<$($generics)*> $trait for $state where $($bounds)* {}
+    };
+    ([$($trait: path),+];) => {};
+    (
+        [$($trait: path),+];
+        impl $generics: tt $state: ty where $bounds: tt $(, $($rest: tt)*)?
+    ) => {
+        $(
+            impl_for_states!(@impl $trait; $generics $state; $bounds);
+        )+
+        impl_for_states!([$($trait),+]; $($($rest)*)?);
+    };
+    (
+        [$($trait: path),+];
+        impl $generics: tt $state: ty $(, $($rest: tt)*)?
+    ) => {
+        impl_for_states!([$($trait),+]; impl $generics $state where [] $(, $($rest)*)?);
+    };
+    (
+        [$($trait: path),+];
+        $state: ty $(, $($rest: tt)*)?
+    ) => {
+        $(
+            impl $trait for $state {}
+        )+
+        impl_for_states!([$($trait),+]; $($($rest)*)?);
+    };
+}
+/// 宏功能：限定 + 密封【状态·类型】
+/// 意图：使公开的【业务功能】接口拒绝接收“下游”代码自定义的任
+/// 何【状态·类型】。
+///
+/// 例：`seal_by_trait!(pub State, [Idle, impl[S: Motionless] Flying<S>]);`
+///
+/// 可见性同时被用于【状态·类型】`trait`与密封`trait`，所以只能是`pub`或`pub(crate)`。在`bin`里，
+/// 请使用`pub(crate)`。
+///
+/// 若同一个模块内需要密封多组【状态·类型】，就得以`in`指明互不重名的（私有）密封模块名。
+/// 例：`seal_by_trait!(pub Phase in phase_sealed, [Draft, Published]);`
+macro_rules! seal_by_trait {
+    ($vis: vis $subtrait: ident in $supertrait_mod: ident, [$($states: tt)*]) => {
+        /// （私有的）密封`trait`
+        mod $supertrait_mod {
+            $vis trait Sealed {}
+        }
+        /// （公开的）【状态·类型】`trait`对外不可实现，
+        /// 因为它继承了（私有的）密封`trait`。
+        $vis trait $subtrait: $supertrait_mod::Sealed {}
+        // 给【状态·类型】限定条件
+        impl_for_states!([$supertrait_mod::Sealed, $subtrait]; $($states)*);
+    };
+    // 这是【宏】入口，提供了默认的【密封`trait`】名。
+    ($vis: vis $subtrait: ident, [$($states: tt)*]) => {
+        seal_by_trait!($vis $subtrait in sealed, [$($states)*]);
+    };
+}
+/// 宏功能：分组【状态·类型】
+/// 意图：使每组【状态·类型】都有（组内）独有的【成员方法】。
+///
+/// 例：`group_by_trait!(pub Midair: State + Debug, [Hovering, impl[S: Motionless] Flying<S>]);`
+macro_rules! group_by_trait {
+    ($vis: vis $group: ident $(: $supertrait: ident $(+ $supertraits: ident)*)?, [$($states: tt)*]) => {
+        /// 定义·分组`marker trait`
+        $vis trait $group $(: $supertrait $(+ $supertraits)*)? {}
+        // 通过标记【状态·类型】，将【状态·类型】分组
+        impl_for_states!([$group]; $($states)*);
+    };
+}
+/// 宏功能：声明【状态·过渡】边，生成“消费”紧前状态的【状态·过渡】成员方法，并从这些声明生成机读的【状态·过渡】表。
+///
+/// 首行`状态机::过渡方法 => 表名, initial 初始状态;`指明状态机类型、搬运共有字段的成员方法与被生成的
+/// 【状态·过渡】表`$graph`。之后的每个【状态·过渡】成员方法都被写作
+/// `fn 方法名[泛型参数] for[实例状态..](self: 紧前状态, ..) -> 紧后状态 [| 故障状态] where[..] { .. }`。
+/// 其中，
+/// 1. 同步方法的方法体仅需构造并返回【紧后状态】值。在方法体内，`self`就是处于【紧前状态】的状态机。
+///    宏再调用状态机的`$transit(self, state)`成员方法，将状态机过渡至【紧后状态】。`$transit`负责搬运
+///    所有状态共有的字段。
+/// 2. 异步方法（`async fn`）的方法体自行驱动状态机，并返回完整的输出：`状态机<紧后状态>`，或在声明了
+///    `| 故障状态`时返回`Result<状态机<紧后状态>, 状态机<故障状态>>`。
+/// 3. 泛型的【紧前状态】须以`for[..]`列出它在状态图里的全部实例状态。宏在编译时校验：状态图里的状态
+///    满足该成员方法的限定条件，当且仅当它被列出。所以，给状态分组新增成员而漏改`for[..]`会编译失败。
+///
+/// 每个被声明的成员方法都是【状态·过渡】表里的边，所以新增、删、改【状态·过渡】都会改变被生成的状态图。
+///
+/// 例：
+/// ```ignore
+/// transitions! {
+///     Drone::transit => TRANSITION_GRAPH, initial Idle;
+///     /// 【着落 - 状态·过渡】
+///     pub fn land(self: Hovering) -> Flying<Idle> {
+///         FlyingBuilder::default().origin(self.coordinate()).build().unwrap()
+///     }
+///     pub fn emergency[S: Manoeuvrable] for[Hovering, Flying<Idle>](self: S, fault: Fault) -> Emergency {
+///         Emergency { fault }
+///     }
+///     pub async fn fly(self: Flying<Idle>) -> Idle | Emergency {
+///         self.inner_fly(Idle).await
+///     }
+/// }
+/// ```
+macro_rules! transitions {
+    (@from [$($state: ty),+]) => {
+        &[$(stringify!($state)),+]
+    };
+    (@output $machine: ident, $to: ty) => {
+        $machine<$to>
+    };
+    (@output $machine: ident, $to: ty, $fault: ty) => {
+        Result<$machine<$to>, $machine<$fault>>
+    };
+    // 编译时校验：状态图里的每个状态都满足泛型【紧前状态】的限定条件，当且仅当它被`for[..]`列出。
+    // 满足限定条件的状态经由固有关联常量`HAS_METHOD`遮蔽`Fallback`的同名默认值；被列出的状态也同理。
+    (@check [$($state: ty,)*] { $method: ident [$($generics: tt)*] $from: ty [$($bounds: tt)*] [$($instance: ty),+] }) => {
+        const _: () = {
+            struct Probe<T: ?Sized>(::std::marker::PhantomData<T>);
+            trait Fallback {
+                const HAS_METHOD: bool = false;
+                const LISTED: bool = false;
+            }
+            impl<T: ?Sized> Fallback for Probe<T> {}
+            impl<$($generics)*> Probe<$from> where $($bounds)* {
+                const HAS_METHOD: bool = true;
+            }
+            $(impl Probe<$instance> {
+                const LISTED: bool = true;
+            })+
+            $(assert!(
+                Probe::<$state>::HAS_METHOD == Probe::<$state>::LISTED,
+                concat!("【状态·过渡】成员方法`", stringify!($method), "`的`for[..]`与其泛型【紧前状态】不一致：", stringify!($state))
+            );)*
+        };
+    };
+    // 将刚“咀嚼”的成员方法记作【状态·过渡】边（若有故障状态，则再多记一条故障边），再接着“咀嚼”余下的成员方法
+    (@edge $header: tt [$($edges: tt)*] [$($states: tt)*] $checks: tt $from: tt $method: ident -> $to: ty $(| $fault: ty)?; $($rest: tt)*) => {
+        transitions!(
+            @munch $header [$($edges)*
+                $crate::type_states::Transition {
+                    from: transitions!(@from $from),
+                    method: stringify!($method),
+                    to: stringify!($to),
+                    on_fault: false
+                },
+                $($crate::type_states::Transition {
+                    from: transitions!(@from $from),
+                    method: stringify!($method),
+                    to: stringify!($fault),
+                    on_fault: true
+                },)?
+            ] [$($states)* $to, $($fault,)?] $checks
+            $($rest)*
+        );
+    };
+    // 具体的【紧前状态】
+    (
+        @push $header: tt $edges: tt [$($states: tt)*] $checks: tt
+        [$from: ty] [] $generics: tt $bounds: tt $method: ident -> $to: ty $(| $fault: ty)?; $($rest: tt)*
+    ) => {
+        transitions!(@edge $header $edges [$($states)* $from,] $checks [$from] $method -> $to $(| $fault)?; $($rest)*);
+    };
+    // 泛型的【紧前状态】被展开为`for[..]`列出的实例状态，并待全部状态都已知后，再校验`for[..]`
+    (
+        @push $header: tt $edges: tt [$($states: tt)*] [$($checks: tt)*]
+        [$from: ty] [$($instance: ty),+] $generics: tt $bounds: tt $method: ident -> $to: ty $(| $fault: ty)?; $($rest: tt)*
+    ) => {
+        transitions!(
+            @edge $header $edges [$($states)* $($instance,)+] [$($checks)* { $method $generics $from $bounds [$($instance),+] }]
+            [$($instance),+] $method -> $to $(| $fault)?; $($rest)*
+        );
+    };
+    // 全部成员方法都已被“咀嚼”完，输出【状态·过渡】表
+    (@munch [$machine: ident :: $transit: ident => $graph: ident, initial $initial: ty] [$($edges: tt)*] $states: tt [$($check: tt)*]) => {
+        /// 由`transitions!()`宏从【状态·过渡】成员方法的声明生成的【状态·过渡】表
+        pub(crate) const $graph: $crate::type_states::TransitionGraph = $crate::type_states::TransitionGraph {
+            initial: stringify!($initial),
+            transitions: &[$($edges)*]
+        };
+        $(transitions!(@check $states $check);)*
+    };
+    // 异步的【状态·过渡】成员方法：方法体返回完整的输出
+    (
+        @munch [$machine: ident :: $transit: ident => $graph: ident, initial $initial: ty] $edges: tt $states: tt $checks: tt
+        $(#[$meta: meta])*
+        $vis: vis async fn $method: ident $([$($generics: tt)*])? $(for[$($instance: ty),+])? (
+            $self: ident: $from: ty $(, $arg: ident: $arg_type: ty)* $(,)?
+        ) -> $to: ty $(| $fault: ty)? $(where[$($bounds: tt)*])? $body: block
+        $($rest: tt)*
+    ) => {
+        impl<$($($generics)*)?> $machine<$from> $(where $($bounds)*)? {
+            $(#[$meta])*
+            $vis async fn $method($self $(, $arg: $arg_type)*) -> transitions!(@output $machine, $to $(, $fault)?) $body
+        }
+        transitions!(
+            @push [$machine :: $transit => $graph, initial $initial] $edges $states $checks
+            [$from] [$($($instance),+)?] [$($($generics)*)?] [$($($bounds)*)?] $method -> $to $(| $fault)?;
+            $($rest)*
+        );
+    };
+    // 同步的【状态·过渡】成员方法：方法体仅返回【紧后状态】值
+    (
+        @munch [$machine: ident :: $transit: ident => $graph: ident, initial $initial: ty] $edges: tt $states: tt $checks: tt
+        $(#[$meta: meta])*
+        $vis: vis fn $method: ident $([$($generics: tt)*])? $(for[$($instance: ty),+])? (
+            $self: ident: $from: ty $(, $arg: ident: $arg_type: ty)* $(,)?
+        ) -> $to: ty $(where[$($bounds: tt)*])? $body: block
+        $($rest: tt)*
+    ) => {
+        impl<$($($generics)*)?> $machine<$from> $(where $($bounds)*)? {
+            $(#[$meta])*
+            $vis fn $method($self $(, $arg: $arg_type)*) -> $machine<$to> {
+                let state: $to = $body;
+                $self.$transit(state)
+            }
+        }
+        transitions!(
+            @push [$machine :: $transit => $graph, initial $initial] $edges $states $checks
+            [$from] [$($($instance),+)?] [$($($generics)*)?] [$($($bounds)*)?] $method -> $to;
+            $($rest)*
+        );
+    };
+    // 这是【宏】入口
+    (
+        $machine: ident :: $transit: ident => $graph: ident, initial $initial: ty;
+        $($items: tt)*
+    ) => {
+        transitions!(@munch [$machine :: $transit => $graph, initial $initial] [] [$initial,] [] $($items)*);
+    };
+}
+/// 宏功能：若前一段占用互斥锁的程序运行崩溃了，当前程序依旧获取
+/// 被保护数据的读写权限，而不是随着前一段程序一起崩溃掉。但，此
+/// 决策是有读到脏数据风险的。
+macro_rules! get_mutex_lock {
+    ($mutex: expr, $closure: expr) => {
+        match $mutex.lock() {
+            Ok(mut value) => $closure(&mut value),
+            Err(mut err) => $closure(err.get_mut())
+        }
+    };
+    // 同上，但在读写“脏数据”之前，先回调`$on_poisoned`告知调用端【互斥锁】已中毒。
+    ($mutex: expr, $closure: expr, $on_poisoned: expr) => {
+        match $mutex.lock() {
+            Ok(mut value) => $closure(&mut value),
+            Err(mut err) => {
+                $on_poisoned();
+                $closure(err.get_mut())
+            }
+        }
+    };
+}
+// -------------------------
+// 从代码生成的【状态·过渡】图
+// -------------------------
+/// 一条【状态·过渡】边
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Transition {
+    /// 紧前状态。泛型的【紧前状态】有多个实例状态
+    pub(crate) from: &'static [&'static str],
+    /// 触发【状态·过渡】的成员方法
+    pub(crate) method: &'static str,
+    /// 紧后状态
+    pub(crate) to: &'static str,
+    /// 是否仅在故障时才会发生（即，成员方法返回`Err`）
+    pub(crate) on_fault: bool
+}
+/// 机读的【状态·过渡】表
+#[derive(Clone, Debug)]
+pub(crate) struct TransitionGraph {
+    /// 初始状态
+    pub(crate) initial: &'static str,
+    /// 【状态·过渡】边，按成员方法的声明次序排列
+    pub(crate) transitions: &'static [Transition]
+}
+impl TransitionGraph {
+    /// 所有状态名，按首次出现的次序排列
+    pub(crate) fn states(&self) -> Vec<String> {
+        let mut states = vec![normalize(self.initial)];
+        for (from, transition) in self.edges() {
+            for state in [from, transition.to] {
+                let state = normalize(state);
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+    /// 逐条列出【状态·过渡】边。泛型的【紧前状态】被展开为它的每个实例状态
+    pub(crate) fn edges(&self) -> impl Iterator<Item = (&'static str, &Transition)> {
+        self.transitions.iter().flat_map(|transition| transition.from.iter().map(move |&from| (from, transition)))
+    }
+    /// 渲染为制表符分隔的文本表。每行一条边：`紧前状态  成员方法  紧后状态  normal|fault`
+    pub(crate) fn to_table(&self) -> String {
+        self.edges().map(|(from, transition)| format!("{}\t{}\t{}\t{}\n",
+            normalize(from),
+            transition.method,
+            normalize(transition.to),
+            if transition.on_fault { "fault" } else { "normal" }
+        )).collect()
+    }
+    /// 渲染为 Graphviz DOT 有向图。故障边画作虚线。
+    pub(crate) fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph type_states {\n    __start [shape=point];\n");
+        dot.push_str(&format!("    __start -> \"{}\";\n", normalize(self.initial)));
+        for (from, transition) in self.edges() {
+            dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                normalize(from),
+                normalize(transition.to),
+                transition.method,
+                if transition.on_fault { ", style=dashed" } else { "" }
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /// 渲染为 Mermaid 状态图。故障边的标签带有`(fault)`后缀。
+    pub(crate) fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("stateDiagram-v2\n");
+        for state in self.states() {
+            if mermaid_id(&state) != state {
+                mermaid.push_str(&format!("    state \"{}\" as {}\n", state, mermaid_id(&state)));
+            }
+        }
+        mermaid.push_str(&format!("    [*] --> {}\n", mermaid_id(&normalize(self.initial))));
+        for (from, transition) in self.edges() {
+            mermaid.push_str(&format!("    {} --> {}: {}{}\n",
+                mermaid_id(&normalize(from)),
+                mermaid_id(&normalize(transition.to)),
+                transition.method,
+                if transition.on_fault { " (fault)" } else { "" }
+            ));
+        }
+        mermaid
+    }
+}
+/// 抹掉`stringify!()`可能插入的空白字符，比如`Flying < Idle >`
+fn normalize(state: &str) -> String {
+    state.chars().filter(|c| !c.is_whitespace()).collect()
+}
+/// Mermaid 状态标识符仅能由字母、数字与下划线组成
+fn mermaid_id(state: &str) -> String {
+    state.chars().filter_map(|c| match c {
+        '<' | ',' => Some('_'),
+        '>' => None,
+        c => Some(c)
+    }).collect()
+}